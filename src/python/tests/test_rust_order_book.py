@@ -0,0 +1,52 @@
+"""
+Smoke test for the Rust order book Python bindings.
+
+Build and install the extension first, with maturin (configured by
+src/rust_core/pyproject.toml):
+
+    cd src/rust_core
+    maturin develop --release
+
+or `pip install src/rust_core`. Then run `pytest src/python/tests`. The test
+is skipped when the extension isn't installed.
+"""
+
+import pytest
+
+rust_core = pytest.importorskip("rust_core")
+
+
+def test_orders_trades_and_depth():
+    book = rust_core.OrderBook()
+
+    # Rest a few orders on each side
+    assert book.add_order(1, 100.00, 10, False) == []
+    assert book.add_order(2, 100.50, 5, False) == []
+    assert book.add_order(3, 99.50, 8, True) == []
+    assert book.get_best_bid() == 99.50
+    assert book.get_best_ask() == 100.00
+
+    # Cross the spread: sweeps the 100.00 level and part of 100.50
+    trades = book.add_order(4, 100.50, 12, True)
+    assert [(t.price, t.quantity) for t in trades] == [(100.00, 10), (100.50, 2)]
+    assert all(t.buy_order_id == 4 and t.aggressor_is_buy for t in trades)
+
+    bids, asks = book.get_depth(5)
+    assert bids == [(99.50, 8)]
+    assert asks == [(100.50, 3)]
+
+
+def test_cancel():
+    book = rust_core.OrderBook()
+    book.add_order(3, 99.50, 8, True)
+
+    assert book.cancel_order(3)
+    assert not book.cancel_order(3)
+    assert book.get_best_bid() is None
+
+
+def test_invalid_order_raises():
+    book = rust_core.OrderBook()
+
+    with pytest.raises(ValueError):
+        book.add_order(5, -1.0, 10, True)
@@ -1,6 +1,7 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use rand::prelude::*;
-use rust_core::order_book::OrderBook;
+use rust_core::money::Qty;
+use rust_core::order_book::{BookSnapshot, OrderBook};
 
 fn benchmark_add_orders(c: &mut Criterion) {
     let mut rng = StdRng::seed_from_u64(42); // Deterministic seed for reproducibility
@@ -14,7 +15,12 @@ fn benchmark_add_orders(c: &mut Criterion) {
                 let quantity = rng.gen_range(1..100);
                 let is_buy = rng.gen_bool(0.5);
 
-                book.add_order(i, black_box(price), black_box(quantity), black_box(is_buy));
+                book.add_order(
+                    i,
+                    black_box(price),
+                    black_box(Qty(quantity)),
+                    black_box(is_buy),
+                );
             }
         });
     });
@@ -35,7 +41,7 @@ fn benchmark_mixed_operations(c: &mut Criterion) {
 
                 if rng.gen_bool(0.8) || order_ids.is_empty() {
                     // 80% add orders
-                    book.add_order(i, price, quantity, is_buy);
+                    book.add_order(i, price, Qty(quantity), is_buy);
                     order_ids.push(i);
                 } else {
                     // 20% cancel orders
@@ -57,7 +63,7 @@ fn benchmark_best_price_queries(c: &mut Criterion) {
         let price = 100.0 + (rng.gen::<f64>() * 10.0);
         let quantity = rng.gen_range(1..100);
         let is_buy = rng.gen_bool(0.5);
-        book.add_order(i, price, quantity, is_buy);
+        book.add_order(i, price, Qty(quantity), is_buy);
     }
 
     c.bench_function("best_price_queries", |b| {
@@ -87,11 +93,11 @@ fn benchmark_matching_engine(c: &mut Criterion) {
                 if i % 2 == 0 {
                     // Buy orders: 99.50 to 99.95
                     let price = base_price - spread - (i % 10) as f64 * 0.01;
-                    book.add_order(i, price, 100, true);
+                    book.add_order(i, price, Qty(100), true);
                 } else {
                     // Sell orders: 100.05 to 100.50
                     let price = base_price + spread + (i % 10) as f64 * 0.01;
-                    book.add_order(i, price, 100, false);
+                    book.add_order(i, price, Qty(100), false);
                 }
             }
 
@@ -102,12 +108,12 @@ fn benchmark_matching_engine(c: &mut Criterion) {
                 if rng.gen::<f64>() < 0.5 {
                     // Aggressive buy order (crosses the spread)
                     let price = 100.10 + rng.gen::<f64>() * 0.40; // 100.10 to 100.50
-                    let trades = book.add_order(i, price, quantity, true);
+                    let trades = book.add_order(i, price, Qty(quantity), true);
                     total_trades += trades.len();
                 } else {
                     // Aggressive sell order (crosses the spread)
                     let price = 99.90 - rng.gen::<f64>() * 0.40; // 99.50 to 99.90
-                    let trades = book.add_order(i, price, quantity, false);
+                    let trades = book.add_order(i, price, Qty(quantity), false);
                     total_trades += trades.len();
                 }
             }
@@ -117,11 +123,217 @@ fn benchmark_matching_engine(c: &mut Criterion) {
     });
 }
 
+fn benchmark_cancellations(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+
+    // 10k resting orders on 10 price levels per side, so every level holds
+    // ~500 orders and each cancel has to search a deep queue
+    let mut setup_book = || {
+        let mut book = OrderBook::new();
+        let mut order_ids = Vec::with_capacity(10_000);
+
+        for i in 0..10_000 {
+            let level = rng.gen_range(0..10) as f64 * 0.01;
+            let quantity = rng.gen_range(1..100);
+            let is_buy = rng.gen_bool(0.5);
+            let price = if is_buy {
+                99.90 - level
+            } else {
+                100.10 + level
+            };
+
+            book.add_order(i, price, Qty(quantity), is_buy);
+            order_ids.push(i);
+        }
+
+        order_ids.shuffle(&mut rng);
+        (book, order_ids)
+    };
+
+    c.bench_function("cancel_10k_orders", |b| {
+        b.iter_batched(
+            &mut setup_book,
+            |(mut book, order_ids)| {
+                for order_id in order_ids {
+                    black_box(book.cancel_order(order_id));
+                }
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+// Ticks away from the touch, geometrically distributed so that level k is
+// `decay` times as likely as level k - 1 (same shape as the backtest's
+// depth_decay_factor)
+fn sample_level_offset(rng: &mut StdRng, decay: f64) -> u32 {
+    let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+    (u.ln() / decay.ln()).floor() as u32
+}
+
+// Mostly small clips with an occasional block order
+fn sample_quantity(rng: &mut StdRng) -> Qty {
+    if rng.gen_bool(0.05) {
+        Qty(rng.gen_range(200..1000))
+    } else {
+        Qty(rng.gen_range(1..20))
+    }
+}
+
+fn benchmark_heavy_tailed_orders(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+
+    c.bench_function("heavy_tailed_10k_orders", |b| {
+        b.iter(|| {
+            let mut book = OrderBook::new();
+            let mut total_trades = 0;
+
+            for i in 0..10_000 {
+                let is_buy = rng.gen_bool(0.5);
+                let quantity = sample_quantity(&mut rng);
+                let offset = sample_level_offset(&mut rng, 0.8) as f64 * 0.01;
+
+                // 10% of orders cross the 5-cent spread and sweep the touch
+                let price = match (is_buy, rng.gen_bool(0.1)) {
+                    (true, false) => 99.95 - offset,
+                    (true, true) => 100.05 + offset,
+                    (false, false) => 100.05 + offset,
+                    (false, true) => 99.95 - offset,
+                };
+
+                let trades = book.add_order(i, black_box(price), quantity, is_buy);
+                total_trades += trades.len();
+            }
+
+            black_box(total_trades);
+        });
+    });
+}
+
+fn populated_book(rng: &mut StdRng, num_orders: u32) -> OrderBook {
+    let mut book = OrderBook::new();
+    for i in 0..num_orders {
+        let offset = rng.gen_range(0..200) as f64 * 0.01;
+        let quantity = rng.gen_range(1..100);
+        let is_buy = rng.gen_bool(0.5);
+        let price = if is_buy {
+            99.95 - offset
+        } else {
+            100.05 + offset
+        };
+        book.add_order(i, price, Qty(quantity), is_buy);
+    }
+    book
+}
+
+fn benchmark_snapshot_round_trip(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot");
+
+    for num_orders in [1_000, 10_000] {
+        let mut rng = StdRng::seed_from_u64(42);
+        let book = populated_book(&mut rng, num_orders);
+        let bytes = serde_json::to_vec(&book.to_snapshot()).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::new("serialize", num_orders),
+            &book,
+            |b, book| {
+                b.iter(|| serde_json::to_vec(&book.to_snapshot()).unwrap());
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("deserialize", num_orders),
+            &bytes,
+            |b, bytes| {
+                b.iter(|| {
+                    let snapshot: BookSnapshot = serde_json::from_slice(bytes).unwrap();
+                    OrderBook::from_snapshot(snapshot)
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// Amend every resting order of a 10k-order book once: a size cut in place,
+// a reprice to another resting level, and the checked path for the reprice
+fn benchmark_modifications(c: &mut Criterion) {
+    let mut group = c.benchmark_group("modify");
+    let num_orders = 10_000;
+
+    let setup = || {
+        let mut rng = StdRng::seed_from_u64(42);
+        let book = populated_book(&mut rng, num_orders);
+        let mut order_ids: Vec<u32> = (0..num_orders).collect();
+        order_ids.shuffle(&mut rng);
+        (book, order_ids)
+    };
+    // One tick further from the touch, so the amended order never crosses
+    let repriced = |book: &OrderBook, order_id: u32| {
+        let order = book.get_order(order_id).unwrap();
+        let price = if order.is_buy_side {
+            order.price - 0.01
+        } else {
+            order.price + 0.01
+        };
+        (price, order.quantity)
+    };
+
+    group.bench_function("size_down", |b| {
+        b.iter_batched(
+            setup,
+            |(mut book, order_ids)| {
+                for order_id in order_ids {
+                    let order = book.get_order(order_id).unwrap();
+                    let (price, quantity) = (order.price, order.quantity);
+                    let smaller = Qty(quantity.lots().div_ceil(2));
+                    black_box(book.modify_order(order_id, price, smaller));
+                }
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("reprice", |b| {
+        b.iter_batched(
+            setup,
+            |(mut book, order_ids)| {
+                for order_id in order_ids {
+                    let (price, quantity) = repriced(&book, order_id);
+                    black_box(book.modify_order(order_id, price, quantity));
+                }
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("try_reprice", |b| {
+        b.iter_batched(
+            setup,
+            |(mut book, order_ids)| {
+                for order_id in order_ids {
+                    let (price, quantity) = repriced(&book, order_id);
+                    black_box(book.try_modify_order(order_id, price, quantity).unwrap());
+                }
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_add_orders,
     benchmark_mixed_operations,
     benchmark_best_price_queries,
-    benchmark_matching_engine
+    benchmark_matching_engine,
+    benchmark_cancellations,
+    benchmark_heavy_tailed_orders,
+    benchmark_snapshot_round_trip,
+    benchmark_modifications
 );
 criterion_main!(benches);
@@ -1,22 +1,16 @@
-use rand::Rng;
-use rust_core::order_book::{OrderBook, Trade};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_core::error::{Error, Result};
+use rust_core::logging::{self, RECORD_TARGET};
+use rust_core::market_data::MarketTick;
+use rust_core::money::{price_to_tick, to_decimal, to_f64, Decimal, Qty, QUANTITY_SCALE};
+use rust_core::order_book::{BookSnapshot, DepthLevels, OrderBook, Trade, TradeFlowSummary};
+use rust_core::smart_order_router::FeeSchedule;
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
-
-#[derive(Debug)]
-struct MarketDataPoint {
-    timestamp: i64,
-    #[allow(dead_code)]
-    symbol: String,
-    bid: f64,
-    ask: f64,
-    #[allow(dead_code)]
-    bid_size: f64,
-    #[allow(dead_code)]
-    ask_size: f64,
-    last_price: f64,
-    volume: f64,
-}
+use std::time::Duration;
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone)]
 struct MarketLevel {
@@ -39,6 +33,155 @@ impl MarketDepth {
     }
 }
 
+// Market data input: flat top-of-book ticks, or JSON lines of full
+// BookSnapshots as written by websocket_client --snapshot-out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    Ticks,
+    Snapshots,
+}
+
+impl InputFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "csv" | "ticks" => Some(InputFormat::Ticks),
+            "snapshots" | "jsonl" => Some(InputFormat::Snapshots),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &str) -> Self {
+        if path.ends_with(".jsonl") || path.ends_with(".json") {
+            InputFormat::Snapshots
+        } else {
+            InputFormat::Ticks
+        }
+    }
+}
+
+// Shape of the simulated order latency around base_latency_us, with
+// latency_std_us as the standard deviation. Log-normal keeps the same mean
+// and std but has a fat right tail, for occasional spikes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum LatencyDistribution {
+    #[default]
+    Uniform,
+    Normal,
+    LogNormal,
+}
+
+impl LatencyDistribution {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "uniform" => Some(LatencyDistribution::Uniform),
+            "normal" => Some(LatencyDistribution::Normal),
+            "lognormal" | "log-normal" => Some(LatencyDistribution::LogNormal),
+            _ => None,
+        }
+    }
+
+    // One draw in microseconds; negative normal draws clamp to zero
+    fn sample<R: Rng>(&self, mean_us: f64, std_us: f64, rng: &mut R) -> f64 {
+        let sample = match self {
+            // Half-width sqrt(3) * std gives the configured variance
+            LatencyDistribution::Uniform => {
+                mean_us + (rng.gen::<f64>() * 2.0 - 1.0) * std_us * 3f64.sqrt()
+            }
+            LatencyDistribution::Normal => mean_us + std_us * standard_normal(rng),
+            LatencyDistribution::LogNormal => {
+                if mean_us <= 0.0 {
+                    return 0.0;
+                }
+                let sigma_sq = (1.0 + (std_us / mean_us).powi(2)).ln();
+                let mu = mean_us.ln() - sigma_sq / 2.0;
+                (mu + sigma_sq.sqrt() * standard_normal(rng)).exp()
+            }
+        };
+        sample.max(0.0)
+    }
+}
+
+// Box-Muller; 1 - u keeps the log argument in (0, 1]
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+// Aggregate a snapshot's orders into price levels, best first, with
+// quantities converted to `quantity_scale`. Orders are stored level by level
+// in priority order, so equal prices are adjacent.
+fn snapshot_levels(snapshot: &BookSnapshot, quantity_scale: u64) -> (DepthLevels, DepthLevels) {
+    let mut bids: DepthLevels = Vec::new();
+    let mut asks: DepthLevels = Vec::new();
+    for order in &snapshot.orders {
+        let side = if order.is_buy_side {
+            &mut bids
+        } else {
+            &mut asks
+        };
+        let order_quantity = if snapshot.quantity_scale == quantity_scale {
+            order.quantity
+        } else {
+            Qty::from_base(
+                order.quantity.to_base_f64(snapshot.quantity_scale),
+                quantity_scale,
+            )
+        };
+        match side.last_mut() {
+            Some((price, quantity)) if price_to_tick(*price) == price_to_tick(order.price) => {
+                *quantity += order_quantity;
+            }
+            _ => side.push((order.price, order_quantity)),
+        }
+    }
+    (bids, asks)
+}
+
+// Rolling realized volatility: sample standard deviation of simple returns
+// over the last `window` last-price changes
+#[derive(Debug, Clone)]
+struct VolatilityEstimator {
+    window: usize,
+    last_price: Option<f64>,
+    returns: VecDeque<f64>,
+}
+
+impl VolatilityEstimator {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            last_price: None,
+            returns: VecDeque::with_capacity(window + 1),
+        }
+    }
+
+    fn update(&mut self, price: f64) {
+        if price <= 0.0 {
+            return;
+        }
+        if let Some(previous) = self.last_price {
+            self.returns.push_back((price - previous) / previous);
+            if self.returns.len() > self.window {
+                self.returns.pop_front();
+            }
+        }
+        self.last_price = Some(price);
+    }
+
+    // Per-tick volatility as a fraction; zero until two returns are seen
+    fn volatility(&self) -> f64 {
+        let n = self.returns.len();
+        if n < 2 {
+            return 0.0;
+        }
+        let mean = self.returns.iter().sum::<f64>() / n as f64;
+        let variance =
+            self.returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        variance.sqrt()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct BacktestConfig {
     enable_market_maker: bool,
@@ -50,12 +193,24 @@ struct BacktestConfig {
     enable_latency_simulation: bool,
     #[allow(dead_code)]
     base_latency_us: f64,
+    latency_distribution: LatencyDistribution,
+    latency_std_us: f64,
+    // Seeds every random draw in the run; None seeds from entropy
+    seed: Option<u64>,
     market_impact_factor: f64,
     aggressive_market_making: bool,
     cross_spread_probability: f64,
     order_book_depth: usize,
     base_depth_size: f64,
     depth_decay_factor: f64,
+    // A negative maker fee is a rebate paid on passive fills
+    fees: FeeSchedule,
+    // Per-exchange cap on market maker quote submissions
+    max_orders_per_sec: Option<u32>,
+    // Passive MM quotes sit further from the touch by a factor of
+    // 1 + volatility_bps * volatility_adjustment
+    volatility_window: usize,
+    volatility_adjustment: f64,
 }
 
 impl Default for BacktestConfig {
@@ -69,13 +224,221 @@ impl Default for BacktestConfig {
             enable_market_impact: true,
             enable_latency_simulation: true,
             base_latency_us: 100.0,
+            // The old fixed +/-50us uniform jitter
+            latency_distribution: LatencyDistribution::Uniform,
+            latency_std_us: 50.0 / 3f64.sqrt(),
+            seed: None,
             market_impact_factor: 0.0001,
             aggressive_market_making: false,
             cross_spread_probability: 0.3,
             order_book_depth: 10,
             base_depth_size: 0.5,
             depth_decay_factor: 0.8,
+            fees: FeeSchedule::new(0.001, 0.002),
+            max_orders_per_sec: None,
+            volatility_window: 20,
+            volatility_adjustment: 0.5,
+        }
+    }
+}
+
+impl BacktestConfig {
+    fn builder() -> BacktestConfigBuilder {
+        BacktestConfigBuilder::default()
+    }
+
+    fn validate(&self) -> Result<()> {
+        let fields = [
+            ("initial_base_inventory", self.initial_base_inventory),
+            ("initial_quote_inventory", self.initial_quote_inventory),
+            ("base_latency_us", self.base_latency_us),
+            ("latency_std_us", self.latency_std_us),
+            ("market_impact_factor", self.market_impact_factor),
+            ("base_depth_size", self.base_depth_size),
+            ("volatility_adjustment", self.volatility_adjustment),
+        ];
+        for (name, value) in fields {
+            if !value.is_finite() || value < 0.0 {
+                return Err(Error::InvalidConfig(format!(
+                    "{name} must be finite and non-negative, got {value}"
+                )));
+            }
+        }
+
+        for (name, rate) in [
+            ("maker_fee", self.fees.maker_fee),
+            ("taker_fee", self.fees.taker_fee),
+        ] {
+            if !rate.is_finite() || rate.abs() >= 1.0 {
+                return Err(Error::InvalidConfig(format!(
+                    "{name} must be a fraction in (-1, 1), got {rate}"
+                )));
+            }
+        }
+
+        if self.num_exchanges == 0 {
+            return Err(Error::InvalidConfig(
+                "num_exchanges must be at least 1".to_string(),
+            ));
+        }
+        if self.max_orders_per_sec == Some(0) {
+            return Err(Error::InvalidConfig(
+                "max_orders_per_sec must be at least 1".to_string(),
+            ));
+        }
+        if self.volatility_window < 2 {
+            return Err(Error::InvalidConfig(
+                "volatility_window must be at least 2".to_string(),
+            ));
+        }
+        if self.order_book_depth == 0 {
+            return Err(Error::InvalidConfig(
+                "order_book_depth must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.cross_spread_probability) {
+            return Err(Error::InvalidConfig(format!(
+                "cross_spread_probability must be in [0, 1], got {}",
+                self.cross_spread_probability
+            )));
         }
+        if !(self.depth_decay_factor > 0.0 && self.depth_decay_factor <= 1.0) {
+            return Err(Error::InvalidConfig(format!(
+                "depth_decay_factor must be in (0, 1], got {}",
+                self.depth_decay_factor
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct BacktestConfigBuilder {
+    config: BacktestConfig,
+}
+
+// Not every setter is driven from the command line yet
+#[allow(dead_code)]
+impl BacktestConfigBuilder {
+    fn enable_market_maker(mut self, value: bool) -> Self {
+        self.config.enable_market_maker = value;
+        self
+    }
+
+    fn enable_sor(mut self, value: bool) -> Self {
+        self.config.enable_sor = value;
+        self
+    }
+
+    fn num_exchanges(mut self, value: usize) -> Self {
+        self.config.num_exchanges = value;
+        self
+    }
+
+    fn initial_base_inventory(mut self, value: f64) -> Self {
+        self.config.initial_base_inventory = value;
+        self
+    }
+
+    fn initial_quote_inventory(mut self, value: f64) -> Self {
+        self.config.initial_quote_inventory = value;
+        self
+    }
+
+    fn enable_market_impact(mut self, value: bool) -> Self {
+        self.config.enable_market_impact = value;
+        self
+    }
+
+    fn enable_latency_simulation(mut self, value: bool) -> Self {
+        self.config.enable_latency_simulation = value;
+        self
+    }
+
+    fn base_latency_us(mut self, value: f64) -> Self {
+        self.config.base_latency_us = value;
+        self
+    }
+
+    fn latency_distribution(mut self, value: LatencyDistribution) -> Self {
+        self.config.latency_distribution = value;
+        self
+    }
+
+    fn latency_std_us(mut self, value: f64) -> Self {
+        self.config.latency_std_us = value;
+        self
+    }
+
+    fn seed(mut self, value: u64) -> Self {
+        self.config.seed = Some(value);
+        self
+    }
+
+    fn market_impact_factor(mut self, value: f64) -> Self {
+        self.config.market_impact_factor = value;
+        self
+    }
+
+    fn aggressive_market_making(mut self, value: bool) -> Self {
+        self.config.aggressive_market_making = value;
+        self
+    }
+
+    fn cross_spread_probability(mut self, value: f64) -> Self {
+        self.config.cross_spread_probability = value;
+        self
+    }
+
+    fn order_book_depth(mut self, value: usize) -> Self {
+        self.config.order_book_depth = value;
+        self
+    }
+
+    fn base_depth_size(mut self, value: f64) -> Self {
+        self.config.base_depth_size = value;
+        self
+    }
+
+    fn depth_decay_factor(mut self, value: f64) -> Self {
+        self.config.depth_decay_factor = value;
+        self
+    }
+
+    fn fees(mut self, value: FeeSchedule) -> Self {
+        self.config.fees = value;
+        self
+    }
+
+    fn maker_fee(mut self, value: f64) -> Self {
+        self.config.fees.maker_fee = value;
+        self
+    }
+
+    fn taker_fee(mut self, value: f64) -> Self {
+        self.config.fees.taker_fee = value;
+        self
+    }
+
+    fn max_orders_per_sec(mut self, value: u32) -> Self {
+        self.config.max_orders_per_sec = Some(value);
+        self
+    }
+
+    fn volatility_window(mut self, value: usize) -> Self {
+        self.config.volatility_window = value;
+        self
+    }
+
+    fn volatility_adjustment(mut self, value: f64) -> Self {
+        self.config.volatility_adjustment = value;
+        self
+    }
+
+    fn build(self) -> Result<BacktestConfig> {
+        self.config.validate()?;
+        Ok(self.config)
     }
 }
 
@@ -90,9 +453,20 @@ struct PerformanceMetrics {
     max_drawdown: f64,
     win_rate: f64,
     avg_trade_size: f64,
-    total_fees_paid: f64,
+    // Net of rebates, accrued exactly like the balances it is deducted from
+    total_fees_paid: Decimal,
     market_maker_trades: usize,
     market_trades: usize,
+    rate_limited_quotes: usize,
+    // MM quotes left resting after submission, and the quantity queued
+    // ahead of them at that moment
+    resting_quotes: usize,
+    queue_ahead_total: Qty,
+    // Distance of passive MM quotes from the touch
+    passive_quotes: usize,
+    quote_offset_total: f64,
+    max_quote_offset: f64,
+    trade_flow: TradeFlowSummary,
     final_base_inventory: f64,
     final_quote_inventory: f64,
 }
@@ -100,6 +474,8 @@ struct PerformanceMetrics {
 struct BacktestEngine {
     config: BacktestConfig,
     exchange_books: Vec<OrderBook>,
+    // Lots per base unit in every exchange book
+    quantity_scale: u64,
     current_timestamp: i64,
     last_market_price: f64,
     metrics: PerformanceMetrics,
@@ -108,16 +484,27 @@ struct BacktestEngine {
     trade_results: Vec<f64>,
     market_depths: Vec<MarketDepth>,
     next_order_id: u32,
-    base_inventory: f64,
-    quote_inventory: f64,
+    // Ledger balances are exact decimals; fills and fees never drift
+    base_inventory: Decimal,
+    quote_inventory: Decimal,
+    // Orders placed by the market maker, so passive fills are attributed
+    mm_order_ids: HashSet<u32>,
+    volatility: VolatilityEstimator,
+    rng: StdRng,
 }
 
 impl BacktestEngine {
     fn new(config: BacktestConfig) -> Self {
         let mut exchange_books = Vec::new();
 
+        let quantity_scale = QUANTITY_SCALE;
         for _ in 0..config.num_exchanges {
-            exchange_books.push(OrderBook::new());
+            let book = OrderBook::new().with_quantity_scale(quantity_scale);
+            let book = match config.max_orders_per_sec {
+                Some(max_per_sec) => book.with_rate_limit(max_per_sec),
+                None => book,
+            };
+            exchange_books.push(book);
         }
 
         let mut market_depths = Vec::new();
@@ -126,10 +513,16 @@ impl BacktestEngine {
         }
 
         Self {
-            base_inventory: config.initial_base_inventory,
-            quote_inventory: config.initial_quote_inventory,
+            base_inventory: to_decimal(config.initial_base_inventory),
+            quote_inventory: to_decimal(config.initial_quote_inventory),
+            volatility: VolatilityEstimator::new(config.volatility_window),
+            rng: match config.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
             config,
             exchange_books,
+            quantity_scale,
             current_timestamp: 0,
             last_market_price: 0.0,
             metrics: PerformanceMetrics {
@@ -141,9 +534,16 @@ impl BacktestEngine {
                 max_drawdown: 0.0,
                 win_rate: 0.0,
                 avg_trade_size: 0.0,
-                total_fees_paid: 0.0,
+                total_fees_paid: Decimal::ZERO,
                 market_maker_trades: 0,
                 market_trades: 0,
+                rate_limited_quotes: 0,
+                resting_quotes: 0,
+                queue_ahead_total: Qty::ZERO,
+                passive_quotes: 0,
+                quote_offset_total: 0.0,
+                max_quote_offset: 0.0,
+                trade_flow: TradeFlowSummary::default(),
                 final_base_inventory: 0.0,
                 final_quote_inventory: 0.0,
             },
@@ -152,6 +552,7 @@ impl BacktestEngine {
             trade_results: Vec::new(),
             market_depths,
             next_order_id: 1000,
+            mm_order_ids: HashSet::new(),
         }
     }
 
@@ -217,24 +618,32 @@ impl BacktestEngine {
     }
 
     #[allow(dead_code)]
-    fn simulate_latency(&self) -> u64 {
+    fn simulate_latency(&mut self) -> u64 {
         if !self.config.enable_latency_simulation {
             return 0;
         }
 
-        let mut rng = rand::thread_rng();
-        let variation = (rng.gen::<f64>() - 0.5) * 2.0 * 50.0;
-        ((self.config.base_latency_us + variation).max(0.0)) as u64
+        self.config.latency_distribution.sample(
+            self.config.base_latency_us,
+            self.config.latency_std_us,
+            &mut self.rng,
+        ) as u64
     }
 
-    fn calculate_fees(&self, price: f64, quantity: f64, is_maker: bool) -> f64 {
-        let fee_rate = if is_maker { 0.001 } else { 0.002 };
-        price * quantity * fee_rate
+    // Signed: negative when the venue pays a rebate
+    fn calculate_fees(&self, notional: Decimal, is_maker: bool) -> Decimal {
+        let fee_rate = if is_maker {
+            self.config.fees.maker_fee
+        } else {
+            self.config.fees.taker_fee
+        };
+        notional * to_decimal(fee_rate)
     }
 
-    fn process_market_data(&mut self, data: &MarketDataPoint) {
+    fn process_market_data(&mut self, data: &MarketTick) {
         self.current_timestamp = data.timestamp;
         self.last_market_price = data.last_price;
+        self.volatility.update(data.last_price);
 
         // First, simulate market depths
         for idx in 0..self.exchange_books.len() {
@@ -243,19 +652,25 @@ impl BacktestEngine {
 
         // Then process orders for each exchange
         for idx in 0..self.exchange_books.len() {
+            let book = &self.exchange_books[idx];
+            if book.get_best_bid().is_none() && book.get_best_ask().is_none() {
+                self.seed_book(idx);
+                continue;
+            }
+
             let mut orders_to_add = Vec::new();
 
             // Collect bid orders
             for level in self.market_depths[idx].bids.clone() {
                 let order_id = self.get_next_order_id();
-                let quantity = (level.quantity * 100.0) as u32;
+                let quantity = Qty::from_base(level.quantity, self.quantity_scale);
                 orders_to_add.push((order_id, level.price, quantity, true));
             }
 
             // Collect ask orders
             for level in self.market_depths[idx].asks.clone() {
                 let order_id = self.get_next_order_id();
-                let quantity = (level.quantity * 100.0) as u32;
+                let quantity = Qty::from_base(level.quantity, self.quantity_scale);
                 orders_to_add.push((order_id, level.price, quantity, false));
             }
 
@@ -269,33 +684,118 @@ impl BacktestEngine {
         self.simulate_market_orders(data);
     }
 
-    fn simulate_market_orders(&mut self, data: &MarketDataPoint) {
-        let mut rng = rand::thread_rng();
+    // Bulk-load an empty book straight from the simulated depth
+    fn seed_book(&mut self, exchange_idx: usize) {
+        let scale = self.quantity_scale;
+        let to_levels = |levels: &[MarketLevel]| -> DepthLevels {
+            levels
+                .iter()
+                .map(|level| (level.price, Qty::from_base(level.quantity, scale)))
+                .filter(|&(_, quantity)| !quantity.is_zero())
+                .collect()
+        };
+        let depth = &self.market_depths[exchange_idx];
+        let bids = to_levels(&depth.bids);
+        let asks = to_levels(&depth.asks);
+        self.load_levels(exchange_idx, &bids, &asks);
+    }
+
+    // Replace a book with one holding exactly these levels
+    fn load_levels(&mut self, exchange_idx: usize, bids: &[(f64, Qty)], asks: &[(f64, Qty)]) {
+        match OrderBook::from_levels(bids, asks) {
+            Ok(book) => {
+                let mut book = book.with_quantity_scale(self.quantity_scale);
+                // Trade ids must stay unique in the TRADE records
+                book.set_trade_id_counter(self.exchange_books[exchange_idx].trade_id_counter());
+                self.exchange_books[exchange_idx] = match self.config.max_orders_per_sec {
+                    Some(max_per_sec) => book.with_rate_limit(max_per_sec),
+                    None => book,
+                };
+                // from_levels numbers its orders from 1; keep engine ids clear
+                let seeded = (bids.len() + asks.len()) as u32;
+                self.next_order_id = self.next_order_id.max(seeded + 1);
+            }
+            Err(e) => warn!(exchange_idx, error = %e, "Skipping book load"),
+        }
+    }
+
+    // Snapshot replay: every venue's book is replaced by the observed one
+    // instead of synthesized depth, then the usual order flow runs on top
+    fn process_snapshot(&mut self, sequence: i64, snapshot: &BookSnapshot) {
+        let (bids, asks) = snapshot_levels(snapshot, self.quantity_scale);
+        let scale = self.quantity_scale;
+        let (Some(&(bid, bid_size)), Some(&(ask, ask_size))) = (bids.first(), asks.first()) else {
+            warn!(sequence, "Skipping one-sided snapshot");
+            return;
+        };
+
+        let to_market_levels = |levels: &DepthLevels| -> Vec<MarketLevel> {
+            levels
+                .iter()
+                .map(|&(price, quantity)| MarketLevel {
+                    price,
+                    quantity: quantity.to_base_f64(scale),
+                })
+                .collect()
+        };
+        for idx in 0..self.exchange_books.len() {
+            self.market_depths[idx] = MarketDepth {
+                bids: to_market_levels(&bids),
+                asks: to_market_levels(&asks),
+            };
+            self.load_levels(idx, &bids, &asks);
+            debug!(
+                sequence,
+                exchange_idx = idx,
+                bid_levels = bids.len(),
+                ask_levels = asks.len(),
+                best_bid = bid,
+                best_ask = ask,
+                "Loaded book snapshot"
+            );
+        }
+
+        let midpoint = (bid + ask) / 2.0;
+        let tick = MarketTick {
+            // Snapshots carry no clock; one second per snapshot
+            timestamp: sequence,
+            symbol: String::new(),
+            bid,
+            ask,
+            bid_size: bid_size.to_base_f64(scale),
+            ask_size: ask_size.to_base_f64(scale),
+            last_price: midpoint,
+            // Nor traded volume, so simulated flow runs at its maximum rate
+            volume: 500.0,
+        };
+        self.current_timestamp = tick.timestamp;
+        self.last_market_price = tick.last_price;
+        self.volatility.update(tick.last_price);
+        self.simulate_market_orders(&tick);
+    }
+
+    fn simulate_market_orders(&mut self, data: &MarketTick) {
         let market_activity = data.volume / 1000.0;
-        let should_generate = rng.gen::<f64>() < market_activity.min(0.5);
+        let should_generate = self.rng.gen::<f64>() < market_activity.min(0.5);
 
         if should_generate && self.config.enable_market_maker {
             for idx in 0..self.exchange_books.len() {
-                let is_buy = rng.gen::<bool>();
-                let quantity = 0.01 + rng.gen::<f64>() * 0.1;
-                let quantity_units = (quantity * 100.0) as u32;
+                let is_buy = self.rng.gen::<bool>();
+                let quantity = 0.01 + self.rng.gen::<f64>() * 0.1;
+                let quantity_units = Qty::from_base(quantity, self.quantity_scale);
 
                 let order_id = self.get_next_order_id();
+                let (trades, _) =
+                    self.exchange_books[idx].add_market_order(order_id, quantity_units, is_buy);
 
-                let trades = if is_buy {
-                    self.exchange_books[idx].add_order(order_id, f64::MAX, quantity_units, true)
-                } else {
-                    self.exchange_books[idx].add_order(order_id, 0.01, quantity_units, false)
-                };
-
-                self.process_trades(&trades, idx, false);
+                self.process_trades(&trades, idx);
             }
 
             self.generate_market_maker_quotes(data);
         }
     }
 
-    fn generate_market_maker_quotes(&mut self, data: &MarketDataPoint) {
+    fn generate_market_maker_quotes(&mut self, data: &MarketTick) {
         if !self.config.enable_market_maker {
             return;
         }
@@ -307,86 +807,138 @@ impl BacktestEngine {
             return;
         }
 
-        let mut rng = rand::thread_rng();
+        // Back off from the touch as realized volatility rises
+        let volatility_bps = self.volatility.volatility() * 10_000.0;
+        let passive_offset =
+            spread * 0.1 * (1.0 + volatility_bps * self.config.volatility_adjustment);
 
         for idx in 0..self.exchange_books.len() {
             let cross_spread = self.config.aggressive_market_making
-                && rng.gen::<f64>() < self.config.cross_spread_probability;
+                && self.rng.gen::<f64>() < self.config.cross_spread_probability;
 
             let buy_price = if cross_spread {
                 data.bid + spread * 0.25
             } else {
-                data.bid - spread * 0.1
+                data.bid - passive_offset
             };
 
             let sell_price = if cross_spread {
                 data.ask - spread * 0.25
             } else {
-                data.ask + spread * 0.1
+                data.ask + passive_offset
             };
 
-            let quote_size = 0.05 + rng.gen::<f64>() * 0.15;
-            let quote_units = (quote_size * 100.0) as u32;
+            if !cross_spread {
+                self.metrics.passive_quotes += 1;
+                self.metrics.quote_offset_total += passive_offset;
+                self.metrics.max_quote_offset = self.metrics.max_quote_offset.max(passive_offset);
+            }
+
+            let quote_size = 0.05 + self.rng.gen::<f64>() * 0.15;
+            let quote_units = Qty::from_base(quote_size, self.quantity_scale);
 
             // Collect order IDs first
             let buy_order_id = self.get_next_order_id();
             let sell_order_id = self.get_next_order_id();
+            self.mm_order_ids.insert(buy_order_id);
+            self.mm_order_ids.insert(sell_order_id);
 
             // Add buy order and process trades
-            let buy_trades =
-                self.exchange_books[idx].add_order(buy_order_id, buy_price, quote_units, true);
-            self.process_trades(&buy_trades, idx, true);
+            self.submit_mm_quote(idx, buy_order_id, buy_price, quote_units, true);
 
             // Add sell order and process trades
-            let sell_trades =
-                self.exchange_books[idx].add_order(sell_order_id, sell_price, quote_units, false);
-            self.process_trades(&sell_trades, idx, true);
+            self.submit_mm_quote(idx, sell_order_id, sell_price, quote_units, false);
+        }
+    }
+
+    // Quotes go through the checked path so the exchange rate limit applies,
+    // clocked by the market data rather than the wall clock
+    fn submit_mm_quote(
+        &mut self,
+        exchange_idx: usize,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy: bool,
+    ) {
+        let now = Duration::from_secs(self.current_timestamp.max(0) as u64);
+        match self.exchange_books[exchange_idx]
+            .try_add_order_at(now, order_id, price, quantity, is_buy)
+        {
+            Ok(trades) => {
+                self.process_trades(&trades, exchange_idx);
+                if let Some(ahead) = self.exchange_books[exchange_idx].queue_ahead(order_id) {
+                    self.metrics.resting_quotes += 1;
+                    self.metrics.queue_ahead_total += ahead;
+                }
+            }
+            Err(e) => {
+                self.mm_order_ids.remove(&order_id);
+                if matches!(e, Error::RateLimited { .. }) {
+                    self.metrics.rate_limited_quotes += 1;
+                } else {
+                    warn!(order_id, price, %quantity, error = %e, "Market maker quote rejected");
+                }
+            }
+        }
+    }
+
+    // Book one market maker fill: the MM pays the taker fee when its order
+    // was the aggressor and earns the maker fee (or rebate) when it rested
+    fn settle_mm_fill(&mut self, is_buy: bool, trade: &Trade, is_maker: bool) {
+        let quantity = trade.quantity.to_base(self.quantity_scale);
+        let notional = to_decimal(trade.price) * quantity;
+
+        if is_buy {
+            self.base_inventory += quantity;
+            self.quote_inventory -= notional;
+        } else {
+            self.base_inventory -= quantity;
+            self.quote_inventory += notional;
         }
+
+        let fee = self.calculate_fees(notional, is_maker);
+        self.metrics.total_fees_paid += fee;
+        self.quote_inventory -= fee;
     }
 
-    fn process_trades(&mut self, trades: &[Trade], exchange_idx: usize, is_mm_trade: bool) {
+    fn process_trades(&mut self, trades: &[Trade], exchange_idx: usize) {
         for trade in trades {
             self.metrics.total_trades += 1;
-            self.metrics.total_volume += trade.quantity as f64 / 100.0;
+            let base_quantity = trade.quantity.to_base_f64(self.quantity_scale);
+            self.metrics.total_volume += base_quantity;
+            self.metrics.trade_flow.record(trade);
+
+            let aggressor_is_buy = trade.aggressor_is_buy;
+            let mm_bought = self.mm_order_ids.contains(&trade.buy_order_id);
+            let mm_sold = self.mm_order_ids.contains(&trade.sell_order_id);
+            let is_mm_trade = mm_bought || mm_sold;
 
             if is_mm_trade {
                 self.metrics.market_maker_trades += 1;
 
-                let quantity = trade.quantity as f64 / 100.0;
-                let is_buy = trade.buy_order_id > trade.sell_order_id;
-
-                if is_buy {
-                    self.base_inventory += quantity;
-                    self.quote_inventory -= trade.price * quantity;
-                } else {
-                    self.base_inventory -= quantity;
-                    self.quote_inventory += trade.price * quantity;
+                if mm_bought {
+                    let is_maker = trade.maker_order_id == trade.buy_order_id;
+                    self.settle_mm_fill(true, trade, is_maker);
+                }
+                if mm_sold {
+                    let is_maker = trade.maker_order_id == trade.sell_order_id;
+                    self.settle_mm_fill(false, trade, is_maker);
                 }
-
-                let fee = self.calculate_fees(trade.price, quantity, true);
-                self.metrics.total_fees_paid += fee;
-                self.quote_inventory -= fee;
             } else {
                 self.metrics.market_trades += 1;
             }
 
-            let impact = self.apply_market_impact(
-                exchange_idx,
-                trade.buy_order_id > trade.sell_order_id,
-                trade.quantity as f64 / 100.0,
-            );
+            let impact = self.apply_market_impact(exchange_idx, aggressor_is_buy, base_quantity);
 
-            println!(
+            info!(
+                target: RECORD_TARGET,
                 "TRADE,{},{},{:.4},{:.6},{},{},{},{:.6}",
                 self.current_timestamp,
                 exchange_idx,
                 trade.price,
-                trade.quantity as f64 / 100.0,
-                if trade.buy_order_id > trade.sell_order_id {
-                    "BUY"
-                } else {
-                    "SELL"
-                },
+                base_quantity,
+                if aggressor_is_buy { "BUY" } else { "SELL" },
                 if is_mm_trade {
                     "MARKET_MAKER"
                 } else {
@@ -399,13 +951,14 @@ impl BacktestEngine {
     }
 
     fn update_metrics(&mut self) {
-        let base_value = self.base_inventory * self.last_market_price;
+        let last_price = to_decimal(self.last_market_price);
+        let base_value = self.base_inventory * last_price;
         let total_value = base_value + self.quote_inventory;
 
-        let initial_value = self.config.initial_base_inventory * self.last_market_price
-            + self.config.initial_quote_inventory;
+        let initial_value = to_decimal(self.config.initial_base_inventory) * last_price
+            + to_decimal(self.config.initial_quote_inventory);
 
-        let pnl = total_value - initial_value;
+        let pnl = to_f64(total_value - initial_value);
         self.metrics.realized_pnl = pnl;
 
         self.pnl_history.push(pnl);
@@ -424,15 +977,16 @@ impl BacktestEngine {
             self.metrics.max_drawdown = drawdown;
         }
 
-        println!(
+        info!(
+            target: RECORD_TARGET,
             "MM_STATE,{},{},{:.6},{:.2},{:.2},{:.2}",
             self.current_timestamp, 0, self.base_inventory, self.quote_inventory, pnl, 0.0
         );
     }
 
     fn calculate_final_metrics(&mut self) {
-        self.metrics.final_base_inventory = self.base_inventory;
-        self.metrics.final_quote_inventory = self.quote_inventory;
+        self.metrics.final_base_inventory = to_f64(self.base_inventory);
+        self.metrics.final_quote_inventory = to_f64(self.quote_inventory);
 
         if self.metrics.total_trades > 0 {
             self.metrics.avg_trade_size =
@@ -480,13 +1034,44 @@ impl BacktestEngine {
         println!("Average Trade Size: {:.4}", self.metrics.avg_trade_size);
         println!("Market Maker Trades: {}", self.metrics.market_maker_trades);
         println!("Market Trades: {}", self.metrics.market_trades);
+        if self.config.max_orders_per_sec.is_some() {
+            println!("Rate-Limited Quotes: {}", self.metrics.rate_limited_quotes);
+        }
+        if self.metrics.resting_quotes > 0 {
+            println!(
+                "Avg Queue Ahead of Resting Quotes: {:.4}",
+                self.metrics
+                    .queue_ahead_total
+                    .to_base_f64(self.quantity_scale)
+                    / self.metrics.resting_quotes as f64
+            );
+        }
+        if self.metrics.passive_quotes > 0 {
+            println!(
+                "Avg Passive Quote Offset: ${:.4} (max ${:.4})",
+                self.metrics.quote_offset_total / self.metrics.passive_quotes as f64,
+                self.metrics.max_quote_offset
+            );
+        }
+        let flow = &self.metrics.trade_flow;
+        println!(
+            "Buy-Initiated Volume: {:.2}",
+            flow.buy_volume.to_base_f64(self.quantity_scale)
+        );
+        println!(
+            "Sell-Initiated Volume: {:.2}",
+            flow.sell_volume.to_base_f64(self.quantity_scale)
+        );
+        println!("VWAP: ${:.2}", flow.vwap);
         println!("\nP&L METRICS:");
-        println!("Realized P&L: ${:.2}", self.metrics.realized_pnl);
-        println!("Total Fees Paid: ${:.2}", self.metrics.total_fees_paid);
+        // Fees are already deducted from the quote balance, so realized P&L
+        // is net; a negative fee total means rebates outweighed fees
         println!(
-            "Net P&L: ${:.2}",
-            self.metrics.realized_pnl - self.metrics.total_fees_paid
+            "Gross P&L: ${:.2}",
+            self.metrics.realized_pnl + to_f64(self.metrics.total_fees_paid)
         );
+        println!("Total Fees Paid: ${:.2}", self.metrics.total_fees_paid);
+        println!("Net P&L: ${:.2}", self.metrics.realized_pnl);
         println!("Sharpe Ratio: {:.4}", self.metrics.sharpe_ratio);
         println!("Max Drawdown: {:.2}%", self.metrics.max_drawdown * 100.0);
         println!("Win Rate: {:.2}%", self.metrics.win_rate * 100.0);
@@ -496,8 +1081,11 @@ impl BacktestEngine {
         println!("=====================================");
     }
 
-    fn run(&mut self, input_file: Option<&str>) -> io::Result<()> {
-        println!("timestamp,exchange_id,price,quantity,side,maker,taker,impact");
+    fn run(&mut self, input_file: Option<&str>, format: InputFormat) -> Result<()> {
+        info!(
+            target: RECORD_TARGET,
+            "timestamp,exchange_id,price,quantity,side,maker,taker,impact"
+        );
 
         let reader: Box<dyn BufRead> = if let Some(file_path) = input_file {
             Box::new(BufReader::new(File::open(file_path)?))
@@ -505,70 +1093,301 @@ impl BacktestEngine {
             Box::new(io::stdin().lock())
         };
 
+        match format {
+            InputFormat::Ticks => self.replay_ticks(reader)?,
+            InputFormat::Snapshots => self.replay_snapshots(reader)?,
+        }
+
+        self.calculate_final_metrics();
+        self.print_summary();
+
+        Ok(())
+    }
+
+    fn replay_snapshots(&mut self, reader: Box<dyn BufRead>) -> Result<()> {
+        let mut sequence = 0;
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let snapshot: BookSnapshot = serde_json::from_str(&line)?;
+            sequence += 1;
+            self.process_snapshot(sequence, &snapshot);
+            self.update_metrics();
+        }
+        Ok(())
+    }
+
+    fn replay_ticks(&mut self, reader: Box<dyn BufRead>) -> Result<()> {
         let mut csv_reader = csv::ReaderBuilder::new()
             .has_headers(true)
+            .trim(csv::Trim::All)
             .from_reader(reader);
 
         for result in csv_reader.records() {
             let record = result?;
 
-            let data = MarketDataPoint {
-                timestamp: record.get(0).unwrap().parse().unwrap_or(0),
-                symbol: record.get(1).unwrap_or("BTC-USD").to_string(),
-                bid: record.get(2).unwrap().parse().unwrap_or(0.0),
-                ask: record.get(3).unwrap().parse().unwrap_or(0.0),
-                bid_size: record.get(4).unwrap().parse().unwrap_or(0.0),
-                ask_size: record.get(5).unwrap().parse().unwrap_or(0.0),
-                last_price: record.get(6).unwrap().parse().unwrap_or(0.0),
-                volume: record.get(7).unwrap().parse().unwrap_or(0.0),
-            };
+            let data = MarketTick::from_record(&record)?;
 
             if data.bid > 0.0 && data.ask > 0.0 && data.last_price > 0.0 {
                 self.process_market_data(&data);
                 self.update_metrics();
             }
         }
-
-        self.calculate_final_metrics();
-        self.print_summary();
-
         Ok(())
     }
 }
 
-fn main() -> io::Result<()> {
+fn parse_arg(flag: &'static str, value: &str) -> Result<f64> {
+    value.parse().map_err(|_| Error::Parse {
+        field: flag,
+        value: value.to_string(),
+    })
+}
+
+fn main() -> Result<()> {
+    logging::init();
+
     let args: Vec<String> = std::env::args().collect();
 
-    let mut config = BacktestConfig::default();
+    let mut config = BacktestConfig::builder();
 
     let mut i = 1;
     let mut input_file = None;
+    let mut input_format = None;
     while i < args.len() {
         match args[i].as_str() {
-            "--aggressive" => config.aggressive_market_making = true,
-            "--no-mm" => config.enable_market_maker = false,
-            "--no-sor" => config.enable_sor = false,
-            "--exchanges" => {
-                if i + 1 < args.len() {
-                    config.num_exchanges = args[i + 1].parse().unwrap_or(1);
-                    i += 1;
-                }
+            "--aggressive" => config = config.aggressive_market_making(true),
+            "--no-mm" => config = config.enable_market_maker(false),
+            "--no-sor" => config = config.enable_sor(false),
+            "--exchanges" if i + 1 < args.len() => {
+                config = config.num_exchanges(args[i + 1].parse().unwrap_or(1));
+                i += 1;
             }
-            "--no-impact" => config.enable_market_impact = false,
-            "--no-latency" => config.enable_latency_simulation = false,
-            "--file" => {
-                if i + 1 < args.len() {
-                    input_file = Some(args[i + 1].clone());
-                    i += 1;
-                }
+            "--no-impact" => config = config.enable_market_impact(false),
+            "--no-latency" => config = config.enable_latency_simulation(false),
+            "--maker-fee" if i + 1 < args.len() => {
+                config = config.maker_fee(parse_arg("--maker-fee", &args[i + 1])?);
+                i += 1;
+            }
+            "--taker-fee" if i + 1 < args.len() => {
+                config = config.taker_fee(parse_arg("--taker-fee", &args[i + 1])?);
+                i += 1;
+            }
+            "--max-orders-per-sec" if i + 1 < args.len() => {
+                let value = args[i + 1].parse().map_err(|_| Error::Parse {
+                    field: "--max-orders-per-sec",
+                    value: args[i + 1].clone(),
+                })?;
+                config = config.max_orders_per_sec(value);
+                i += 1;
+            }
+            "--vol-window" if i + 1 < args.len() => {
+                let value = args[i + 1].parse().map_err(|_| Error::Parse {
+                    field: "--vol-window",
+                    value: args[i + 1].clone(),
+                })?;
+                config = config.volatility_window(value);
+                i += 1;
+            }
+            "--vol-adjustment" if i + 1 < args.len() => {
+                config = config.volatility_adjustment(parse_arg("--vol-adjustment", &args[i + 1])?);
+                i += 1;
+            }
+            "--latency-dist" if i + 1 < args.len() => {
+                let value =
+                    LatencyDistribution::parse(&args[i + 1]).ok_or_else(|| Error::Parse {
+                        field: "--latency-dist",
+                        value: args[i + 1].clone(),
+                    })?;
+                config = config.latency_distribution(value);
+                i += 1;
+            }
+            "--latency-std" if i + 1 < args.len() => {
+                config = config.latency_std_us(parse_arg("--latency-std", &args[i + 1])?);
+                i += 1;
+            }
+            "--seed" if i + 1 < args.len() => {
+                let value = args[i + 1].parse().map_err(|_| Error::Parse {
+                    field: "--seed",
+                    value: args[i + 1].clone(),
+                })?;
+                config = config.seed(value);
+                i += 1;
+            }
+            "--input-format" if i + 1 < args.len() => {
+                input_format =
+                    Some(
+                        InputFormat::parse(&args[i + 1]).ok_or_else(|| Error::Parse {
+                            field: "--input-format",
+                            value: args[i + 1].clone(),
+                        })?,
+                    );
+                i += 1;
+            }
+            "--file" if i + 1 < args.len() => {
+                input_file = Some(args[i + 1].clone());
+                i += 1;
             }
             _ => {}
         }
         i += 1;
     }
 
-    let mut engine = BacktestEngine::new(config);
-    engine.run(input_file.as_deref())?;
+    let mut engine = BacktestEngine::new(config.build()?);
+    let input_format = input_format.unwrap_or_else(|| {
+        input_file
+            .as_deref()
+            .map_or(InputFormat::Ticks, InputFormat::from_path)
+    });
+    engine.run(input_file.as_deref(), input_format)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_invalid(builder: BacktestConfigBuilder) {
+        let result = builder.build();
+        assert!(
+            matches!(result, Err(Error::InvalidConfig(_))),
+            "expected InvalidConfig, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn defaults_build() {
+        assert!(BacktestConfig::builder().build().is_ok());
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_fields() {
+        let setters: [fn(BacktestConfigBuilder, f64) -> BacktestConfigBuilder; 7] = [
+            BacktestConfigBuilder::initial_base_inventory,
+            BacktestConfigBuilder::initial_quote_inventory,
+            BacktestConfigBuilder::base_latency_us,
+            BacktestConfigBuilder::latency_std_us,
+            BacktestConfigBuilder::market_impact_factor,
+            BacktestConfigBuilder::base_depth_size,
+            BacktestConfigBuilder::volatility_adjustment,
+        ];
+        for set in setters {
+            for value in [-1.0, f64::NAN, f64::INFINITY] {
+                assert_invalid(set(BacktestConfig::builder(), value));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_fees_of_a_whole_notional_or_more() {
+        assert_invalid(BacktestConfig::builder().maker_fee(1.0));
+        assert_invalid(BacktestConfig::builder().maker_fee(-1.0));
+        assert_invalid(BacktestConfig::builder().taker_fee(f64::NAN));
+        // A maker rebate is allowed
+        assert!(BacktestConfig::builder().maker_fee(-0.0002).build().is_ok());
+    }
+
+    #[test]
+    fn rejects_zero_exchanges() {
+        assert_invalid(BacktestConfig::builder().num_exchanges(0));
+    }
+
+    #[test]
+    fn rejects_a_zero_rate_limit() {
+        assert_invalid(BacktestConfig::builder().max_orders_per_sec(0));
+    }
+
+    #[test]
+    fn rejects_a_volatility_window_below_two() {
+        assert_invalid(BacktestConfig::builder().volatility_window(1));
+    }
+
+    #[test]
+    fn rejects_zero_order_book_depth() {
+        assert_invalid(BacktestConfig::builder().order_book_depth(0));
+    }
+
+    #[test]
+    fn rejects_cross_spread_probability_outside_unit_range() {
+        assert_invalid(BacktestConfig::builder().cross_spread_probability(-0.1));
+        assert_invalid(BacktestConfig::builder().cross_spread_probability(1.1));
+        assert_invalid(BacktestConfig::builder().cross_spread_probability(f64::NAN));
+    }
+
+    #[test]
+    fn rejects_depth_decay_factor_outside_its_range() {
+        assert_invalid(BacktestConfig::builder().depth_decay_factor(0.0));
+        assert_invalid(BacktestConfig::builder().depth_decay_factor(1.5));
+        assert!(BacktestConfig::builder()
+            .depth_decay_factor(1.0)
+            .build()
+            .is_ok());
+    }
+
+    // One exchange, no impact, a 10 bps maker rebate and a 20 bps taker fee
+    fn rebate_engine() -> BacktestEngine {
+        let config = BacktestConfig::builder()
+            .enable_market_impact(false)
+            .maker_fee(-0.001)
+            .taker_fee(0.002)
+            .seed(1)
+            .build()
+            .unwrap();
+        BacktestEngine::new(config)
+    }
+
+    fn mm_quote(engine: &mut BacktestEngine, price: f64, lots: u64, is_buy: bool) {
+        let order_id = engine.get_next_order_id();
+        engine.mm_order_ids.insert(order_id);
+        engine.submit_mm_quote(0, order_id, price, Qty(lots), is_buy);
+    }
+
+    #[test]
+    fn passive_fills_earn_the_maker_rebate() {
+        let mut engine = rebate_engine();
+        // 1 BTC bid at 100, then a market sell hits it
+        mm_quote(&mut engine, 100.0, 100, true);
+        let trades = engine.exchange_books[0].add_order(1, 100.0, Qty(100), false);
+        engine.process_trades(&trades, 0);
+
+        assert_eq!(engine.metrics.market_maker_trades, 1);
+        assert_eq!(engine.base_inventory, to_decimal(2.0));
+        // Paid 100 for the coin and was paid 0.10 to provide it
+        assert_eq!(engine.quote_inventory, to_decimal(9_900.1));
+        assert_eq!(engine.metrics.total_fees_paid, to_decimal(-0.1));
+    }
+
+    #[test]
+    fn aggressive_fills_pay_the_taker_fee() {
+        let mut engine = rebate_engine();
+        engine.exchange_books[0].add_order(1, 100.0, Qty(100), false);
+        // The MM's bid lifts the resting ask
+        mm_quote(&mut engine, 100.0, 100, true);
+
+        assert_eq!(engine.metrics.market_maker_trades, 1);
+        assert_eq!(engine.quote_inventory, to_decimal(9_899.8));
+        assert_eq!(engine.metrics.total_fees_paid, to_decimal(0.2));
+    }
+
+    #[test]
+    fn fees_and_balances_accrue_exactly_over_many_fills() {
+        let mut engine = rebate_engine();
+        // A thousand 0.01 BTC passive buys at 100.01
+        // Market order ids stay clear of the MM's, which start at 1000
+        for order_id in 1_000_000..1_001_000 {
+            mm_quote(&mut engine, 100.01, 1, true);
+            let trades = engine.exchange_books[0].add_order(order_id, 100.01, Qty(1), false);
+            engine.process_trades(&trades, 0);
+        }
+
+        assert_eq!(engine.metrics.market_maker_trades, 1_000);
+        assert_eq!(engine.base_inventory, Decimal::from(11));
+        // 1.0001 paid per fill, 0.0010001 rebated
+        assert_eq!(engine.metrics.total_fees_paid, to_decimal(-1.0001));
+        assert_eq!(engine.quote_inventory, to_decimal(9_000.900_1));
+    }
+}
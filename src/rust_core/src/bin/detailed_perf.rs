@@ -3,11 +3,106 @@
 // This tool may produce misleading results due to lack of proper warmup,
 // statistical analysis, and outlier detection.
 
+use rust_core::money::Qty;
 use rust_core::order_book::OrderBook;
+use rust_core::shared_book::SharedOrderBook;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 
-fn main() {
+struct PerfConfig {
+    iterations: usize,
+    query_iterations: usize,
+    samples_out: Option<String>,
+}
+
+fn parse_count(flag: &str, value: &str) -> Result<usize, Box<dyn Error>> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{flag} expects a positive integer, got `{value}`").into()),
+    }
+}
+
+fn parse_args() -> Result<PerfConfig, Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let mut config = PerfConfig {
+        iterations: 100,
+        query_iterations: 10000,
+        samples_out: None,
+    };
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--iterations" if i + 1 < args.len() => {
+                config.iterations = parse_count("--iterations", &args[i + 1])?;
+                i += 1;
+            }
+            "--query-iterations" if i + 1 < args.len() => {
+                config.query_iterations = parse_count("--query-iterations", &args[i + 1])?;
+                i += 1;
+            }
+            "--samples-out" if i + 1 < args.len() => {
+                config.samples_out = Some(args[i + 1].clone());
+                i += 1;
+            }
+            other => {
+                return Err(format!(
+                    "unknown argument `{other}`\nUsage: detailed_perf [--iterations <n>] \
+                     [--query-iterations <n>] [--samples-out <samples.csv>]"
+                )
+                .into())
+            }
+        }
+        i += 1;
+    }
+    Ok(config)
+}
+
+// Wall-clock time of each of `iterations` runs
+fn time_each(iterations: usize, mut run: impl FnMut()) -> Vec<Duration> {
+    (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .collect()
+}
+
+// One row per sample: operation,sample,latency_ns. Returns the number of rows
+// written for each operation so the caller can check nothing was dropped.
+fn write_samples(
+    path: &str,
+    results: &[(&str, Vec<Duration>)],
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "operation,sample,latency_ns")?;
+
+    let mut counts = Vec::with_capacity(results.len());
+    for (name, timings) in results {
+        for (sample, timing) in timings.iter().enumerate() {
+            writeln!(writer, "{name},{sample},{}", timing.as_nanos())?;
+        }
+        counts.push(timings.len());
+    }
+    writer.flush()?;
+    Ok(counts)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let config = parse_args()?;
+
     println!("=== Rust Detailed Performance Analysis ===\n");
+    println!(
+        "Iterations: {} (best price queries: {})",
+        config.iterations, config.query_iterations
+    );
 
     // Warm up
     for _ in 0..10 {
@@ -15,36 +110,46 @@ fn main() {
     }
 
     // Test 1: Order insertion performance
-    let mut timings = Vec::new();
-    for _ in 0..100 {
-        let start = Instant::now();
+    let insertion_timings = time_each(config.iterations, || {
         let mut book = OrderBook::new();
         for i in 0..1000 {
-            book.add_order(i, 100.0 + (i % 20) as f64, 10, i.is_multiple_of(2));
+            book.add_order(i, 100.0 + (i % 20) as f64, Qty(10), i.is_multiple_of(2));
         }
-        timings.push(start.elapsed());
-    }
-    print_stats("Order Insertion (1000 orders)", &timings);
+    });
+    print_stats("Order Insertion (1000 orders)", &insertion_timings);
 
     // Test 2: Matching engine performance
-    timings.clear();
-    for _ in 0..100 {
-        let start = Instant::now();
-        run_matching_engine_scenario();
-        timings.push(start.elapsed());
-    }
-    print_stats("Matching Engine Scenario", &timings);
+    let matching_timings = time_each(config.iterations, run_matching_engine_scenario);
+    print_stats("Matching Engine Scenario", &matching_timings);
 
     // Test 3: Best price queries
     let book = setup_book();
-    timings.clear();
-    for _ in 0..10000 {
-        let start = Instant::now();
+    let query_timings = time_each(config.query_iterations, || {
         let _ = book.get_best_bid();
         let _ = book.get_best_ask();
-        timings.push(start.elapsed());
+    });
+    print_stats("Best Price Queries", &query_timings);
+
+    if let Some(path) = &config.samples_out {
+        let results = [
+            ("order_insertion", insertion_timings),
+            ("matching_engine", matching_timings),
+            ("best_price_query", query_timings),
+        ];
+        let counts = write_samples(path, &results)?;
+        let expected = [
+            config.iterations,
+            config.iterations,
+            config.query_iterations,
+        ];
+        if counts != expected {
+            return Err(format!("wrote {counts:?} samples, expected {expected:?}").into());
+        }
+        println!(
+            "\nWrote {} samples to: {path}",
+            counts.iter().sum::<usize>()
+        );
     }
-    print_stats("Best Price Queries", &timings);
 
     // Test 4: Memory allocation patterns
     println!("\n--- Memory Allocation Test ---");
@@ -53,7 +158,7 @@ fn main() {
     for i in 0..100 {
         let mut book = OrderBook::new();
         for j in 0..100 {
-            book.add_order(i * 100 + j, 100.0 + j as f64, 10, j.is_multiple_of(2));
+            book.add_order(i * 100 + j, 100.0 + j as f64, Qty(10), j.is_multiple_of(2));
         }
         books.push(book);
     }
@@ -66,13 +171,70 @@ fn main() {
     let start = Instant::now();
     drop(books);
     println!("Deallocated all books in {:?}", start.elapsed());
+
+    // Test 5: Concurrent readers against a single writer
+    run_shared_book_scenario(config.iterations * 100);
+
+    Ok(())
+}
+
+// Readers spin on the published view while the writer keeps a two-sided
+// book moving; every view they observe must be uncrossed and newer than the
+// last one they saw
+fn run_shared_book_scenario(writes: usize) {
+    println!("\n--- Shared Book Concurrent Reads ---");
+    let mut shared = SharedOrderBook::new(OrderBook::new(), 5);
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let reader = shared.reader();
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let mut reads = 0u64;
+                let mut last_sequence = 0;
+                while !done.load(Ordering::Acquire) {
+                    let view = reader.load();
+                    assert!(view.sequence >= last_sequence, "view went backwards");
+                    if let (Some(bid), Some(ask)) = (view.best_bid, view.best_ask) {
+                        assert!(bid < ask, "reader saw a crossed book");
+                    }
+                    last_sequence = view.sequence;
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    for i in 0..writes as u32 {
+        shared.write(|book| {
+            let offset = (i % 10) as f64;
+            book.add_order(i * 2, 100.0 - offset, Qty(10), true);
+            book.add_order(i * 2 + 1, 101.0 + offset, Qty(10), false);
+            // Sweep the touch now and then so the top of book changes
+            if i % 50 == 49 {
+                book.add_order(u32::MAX - i, 101.0, Qty(10_000), true);
+            }
+        });
+    }
+    let elapsed = start.elapsed();
+    done.store(true, Ordering::Release);
+
+    let reads: u64 = readers.into_iter().map(|r| r.join().unwrap()).sum();
+    println!(
+        "{writes} write batches in {elapsed:?} ({:?} per batch), {reads} reads across 4 threads",
+        elapsed / writes as u32
+    );
+    println!("Last published sequence: {}", shared.sequence());
 }
 
 fn setup_book() -> OrderBook {
     let mut book = OrderBook::new();
     for i in 0..100 {
-        book.add_order(i * 2, 100.0 + i as f64, 100, true);
-        book.add_order(i * 2 + 1, 110.0 + i as f64, 100, false);
+        book.add_order(i * 2, 100.0 + i as f64, Qty(100), true);
+        book.add_order(i * 2 + 1, 110.0 + i as f64, Qty(100), false);
     }
     book
 }
@@ -82,14 +244,14 @@ fn run_matching_engine_scenario() {
 
     // Add initial orders
     for i in 0..10 {
-        book.add_order(i * 2, 100.0 + i as f64, 100, true);
-        book.add_order(i * 2 + 1, 110.0 + i as f64, 100, false);
+        book.add_order(i * 2, 100.0 + i as f64, Qty(100), true);
+        book.add_order(i * 2 + 1, 110.0 + i as f64, Qty(100), false);
     }
 
     // Add crossing orders
     for i in 0..50 {
-        let _ = book.add_order(1000 + i, 109.0, 50, true);
-        let _ = book.add_order(2000 + i, 101.0, 50, false);
+        let _ = book.add_order(1000 + i, 109.0, Qty(50), true);
+        let _ = book.add_order(2000 + i, 101.0, Qty(50), false);
     }
 }
 
@@ -115,3 +277,39 @@ fn print_stats(name: &str, timings: &[Duration]) {
     println!("P95: {p95:?}");
     println!("P99: {p99:?}");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn samples_file_holds_one_row_per_iteration() {
+        let results = [
+            (
+                "matching_engine",
+                time_each(7, run_matching_engine_scenario),
+            ),
+            ("best_price_query", time_each(13, || {})),
+        ];
+        let path = env::temp_dir().join(format!("detailed_perf_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let counts = write_samples(path, &results).unwrap();
+        let written = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(counts, [7, 13]);
+        let mut lines = written.lines();
+        assert_eq!(lines.next(), Some("operation,sample,latency_ns"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), 20);
+        for (name, expected) in [("matching_engine", 7), ("best_price_query", 13)] {
+            let samples: Vec<usize> = rows
+                .iter()
+                .filter(|row| row[0] == name)
+                .map(|row| row[1].parse().unwrap())
+                .collect();
+            assert_eq!(samples, (0..expected).collect::<Vec<_>>());
+        }
+    }
+}
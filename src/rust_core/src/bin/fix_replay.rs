@@ -0,0 +1,105 @@
+use rust_core::fix::{parse_fix_message, FixOutcome, FixSession};
+use rust_core::order_book::OrderBook;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::time::Instant;
+
+// Replays a FIX log (one message per line) into an order book, the FIX
+// counterpart of replay_tool's CSV input
+fn main() -> Result<(), Box<dyn Error>> {
+    println!("=== FIX Log Replay Tool ===");
+
+    let args: Vec<String> = env::args().collect();
+    let mut log_path = "../../test_data/sample_orders.fix".to_string();
+    let mut quiet = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--quiet" => quiet = true,
+            path => log_path = path.to_string(),
+        }
+        i += 1;
+    }
+
+    println!("\nReading FIX messages from: {log_path}");
+    let reader = BufReader::new(File::open(&log_path)?);
+
+    let mut book = OrderBook::new();
+    let mut session = FixSession::new();
+    let mut messages = 0;
+    let mut skipped = 0;
+    let mut total_trades = 0;
+
+    let start_time = Instant::now();
+
+    for (line_number, line) in (1..).zip(reader.lines()) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        messages += 1;
+
+        let order = match parse_fix_message(&line) {
+            Ok(order) => order,
+            Err(e) => {
+                // Unsupported message types (heartbeats, logons, ...) are
+                // expected in real logs
+                eprintln!("Skipping line {line_number}: {e}");
+                skipped += 1;
+                continue;
+            }
+        };
+
+        match session.apply(&mut book, order) {
+            Ok(FixOutcome::Added { order_id, trades }) => {
+                total_trades += trades.len();
+                if !quiet {
+                    println!("Order #{order_id} added, {} trade(s)", trades.len());
+                    for trade in &trades {
+                        println!(
+                            "  Trade #{}: {} @ ${:.2} (buy #{}, sell #{})",
+                            trade.trade_id,
+                            trade.quantity,
+                            trade.price,
+                            trade.buy_order_id,
+                            trade.sell_order_id
+                        );
+                    }
+                }
+            }
+            Ok(FixOutcome::Cancelled { order_id }) => {
+                if !quiet {
+                    println!("Order #{order_id} cancelled");
+                }
+            }
+            Ok(FixOutcome::UnknownOrder(cl_ord_id)) => {
+                eprintln!("Line {line_number}: cancel for unknown or inactive order {cl_ord_id}");
+            }
+            Err(e) => {
+                eprintln!("Skipping line {line_number}: {e}");
+                skipped += 1;
+            }
+        }
+    }
+
+    let duration = start_time.elapsed();
+
+    println!("\n=== Replay Summary ===");
+    println!("Messages read: {messages}");
+    println!("Messages skipped: {skipped}");
+    println!("Total trades generated: {total_trades}");
+    match book.get_best_bid() {
+        Some(bid) => println!("Best Bid: ${bid:.2}"),
+        None => println!("Best Bid: None"),
+    }
+    match book.get_best_ask() {
+        Some(ask) => println!("Best Ask: ${ask:.2}"),
+        None => println!("Best Ask: None"),
+    }
+    println!("Processing time: {} microseconds", duration.as_micros());
+
+    Ok(())
+}
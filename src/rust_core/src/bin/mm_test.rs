@@ -1,5 +1,6 @@
 use rand::prelude::*;
-use rust_core::market_maker::{MarketMaker, MarketMakerParameters};
+use rust_core::market_maker::{HeartbeatState, MarketMaker, MarketMakerParameters};
+use rust_core::money::Qty;
 use rust_core::order_book::OrderBook;
 use rust_core::smart_order_router::{
     Exchange, ExchangeID, ExchangeMetrics, FeeSchedule, SmartOrderRouter,
@@ -60,77 +61,82 @@ fn setup_market_data(
     kraken: &mut MockExchange,
 ) {
     // Clear existing orders
-    *binance.get_order_book_mut() = OrderBook::new();
-    *coinbase.get_order_book_mut() = OrderBook::new();
-    *kraken.get_order_book_mut() = OrderBook::new();
+    binance.get_order_book_mut().clear();
+    coinbase.get_order_book_mut().clear();
+    kraken.get_order_book_mut().clear();
 
     // Binance: Tight spread
     binance
         .get_order_book_mut()
-        .add_order(1, 45000.00, 10, true); // Buy
-    binance.get_order_book_mut().add_order(2, 44999.50, 5, true); // Buy
+        .add_order(1, 45000.00, Qty(10), true); // Buy
     binance
         .get_order_book_mut()
-        .add_order(3, 45001.00, 8, false); // Sell
+        .add_order(2, 44999.50, Qty(5), true); // Buy
     binance
         .get_order_book_mut()
-        .add_order(4, 45001.50, 12, false); // Sell
+        .add_order(3, 45001.00, Qty(8), false); // Sell
+    binance
+        .get_order_book_mut()
+        .add_order(4, 45001.50, Qty(12), false); // Sell
 
     // Coinbase: Wider spread
     coinbase
         .get_order_book_mut()
-        .add_order(5, 44999.00, 7, true); // Buy
+        .add_order(5, 44999.00, Qty(7), true); // Buy
     coinbase
         .get_order_book_mut()
-        .add_order(6, 44998.00, 3, true); // Buy
+        .add_order(6, 44998.00, Qty(3), true); // Buy
     coinbase
         .get_order_book_mut()
-        .add_order(7, 45002.00, 6, false); // Sell
+        .add_order(7, 45002.00, Qty(6), false); // Sell
     coinbase
         .get_order_book_mut()
-        .add_order(8, 45003.00, 9, false); // Sell
+        .add_order(8, 45003.00, Qty(9), false); // Sell
 
     // Kraken: Different prices
-    kraken.get_order_book_mut().add_order(9, 45000.50, 15, true); // Buy
-    kraken.get_order_book_mut().add_order(10, 45000.00, 5, true); // Buy
     kraken
         .get_order_book_mut()
-        .add_order(11, 45002.50, 10, false); // Sell
+        .add_order(9, 45000.50, Qty(15), true); // Buy
+    kraken
+        .get_order_book_mut()
+        .add_order(10, 45000.00, Qty(5), true); // Buy
     kraken
         .get_order_book_mut()
-        .add_order(12, 45003.50, 8, false); // Sell
+        .add_order(11, 45002.50, Qty(10), false); // Sell
+    kraken
+        .get_order_book_mut()
+        .add_order(12, 45003.50, Qty(8), false); // Sell
 }
 
-#[allow(dead_code)]
-fn simulate_market_movement(exchange: &mut MockExchange, rng: &mut ThreadRng) {
+fn simulate_market_movement(exchange: &mut dyn Exchange, rng: &mut ThreadRng) {
     // Add some randomness to the market
     let price_change = rng.gen_range(-5.0..5.0);
     let size_change = rng.gen_range(-2..3);
 
-    // Update best bid/ask
+    // Re-quote orders 1 (bid) and 3 (ask) around their old prices
     let book = exchange.get_order_book_mut();
-    if let Some(best_bid) = book.get_best_bid() {
-        book.cancel_order(1); // Cancel old best bid
+    if let Some(old_bid) = book.cancel_order(1) {
         book.add_order(
             1,
-            best_bid + price_change,
-            (10 + size_change).max(1) as u32,
+            old_bid.price + price_change,
+            Qty((10 + size_change).max(1) as u64),
             true,
         );
     }
 
-    if let Some(best_ask) = book.get_best_ask() {
-        book.cancel_order(3); // Cancel old best ask
+    if let Some(old_ask) = book.cancel_order(3) {
         book.add_order(
             3,
-            best_ask + price_change,
-            (8 + size_change).max(1) as u32,
+            old_ask.price + price_change,
+            Qty((8 + size_change).max(1) as u64),
             false,
         );
     }
 }
 
 fn main() {
+    rust_core::logging::init();
+
     println!("=== Market Maker Test (Rust) ===");
 
     // Create mock exchanges
@@ -161,14 +167,27 @@ fn main() {
     sor.add_exchange(Box::new(coinbase), FeeSchedule::new(0.0005, 0.0015));
     sor.add_exchange(Box::new(kraken), FeeSchedule::new(0.0002, 0.0012));
 
+    // The router owns the venues now, but their books can still be moved
+    // through it until the market maker borrows it
+    let mut rng = thread_rng();
+    if let Some(binance) = sor.get_exchange_mut(ExchangeID::Binance) {
+        simulate_market_movement(binance, &mut rng);
+        let book = binance.get_order_book();
+        println!(
+            "Binance after a simulated move: Bid ${:.2} / Ask ${:.2}",
+            book.get_best_bid().unwrap_or(0.0),
+            book.get_best_ask().unwrap_or(0.0)
+        );
+    }
+
     // Create Market Maker with custom parameters
-    let params = MarketMakerParameters {
-        base_spread_bps: 20.0,      // 0.20% spread
-        base_quote_size: 0.5,       // 0.5 BTC per quote
-        target_base_inventory: 5.0, // Target 5 BTC
-        inventory_skew_factor: 0.2, // 20% skew adjustment
-        ..Default::default()
-    };
+    let params = MarketMakerParameters::builder()
+        .base_spread_bps(20.0) // 0.20% spread
+        .base_quote_size(0.5) // 0.5 BTC per quote
+        .target_base_inventory(5.0) // Target 5 BTC
+        .inventory_skew_factor(0.2) // 20% skew adjustment
+        .build()
+        .expect("valid market maker parameters");
 
     let mut mm = MarketMaker::new(&sor, params);
 
@@ -186,7 +205,7 @@ fn main() {
         println!("  Price: ${:.2}", quotes.buy_quote.price);
         println!(
             "  Size: {:.2} BTC",
-            quotes.buy_quote.quantity as f64 / 100.0
+            mm.get_parameters().from_units(quotes.buy_quote.quantity)
         );
         println!("  Exchange: {}", quotes.buy_quote.target_exchange);
 
@@ -194,7 +213,7 @@ fn main() {
         println!("  Price: ${:.2}", quotes.sell_quote.price);
         println!(
             "  Size: {:.2} BTC",
-            quotes.sell_quote.quantity as f64 / 100.0
+            mm.get_parameters().from_units(quotes.sell_quote.quantity)
         );
         println!("  Exchange: {}", quotes.sell_quote.target_exchange);
 
@@ -226,12 +245,14 @@ fn main() {
             println!(
                 "  Buy: ${:.2} for {:.2} BTC",
                 new_quotes.buy_quote.price,
-                new_quotes.buy_quote.quantity as f64 / 100.0
+                mm.get_parameters()
+                    .from_units(new_quotes.buy_quote.quantity)
             );
             println!(
                 "  Sell: ${:.2} for {:.2} BTC",
                 new_quotes.sell_quote.price,
-                new_quotes.sell_quote.quantity as f64 / 100.0
+                mm.get_parameters()
+                    .from_units(new_quotes.sell_quote.quantity)
             );
             println!(
                 "  Inventory imbalance: {:.1}%",
@@ -244,12 +265,9 @@ fn main() {
     println!("\n4. Simulating Trading Session");
     println!("{}", "=".repeat(50));
 
-    let mut rng = thread_rng();
-
-    // Need mutable access to exchanges for market simulation
-    // In a real system, this would be handled differently
-    println!("(Note: Market simulation skipped in Rust version due to ownership constraints)");
-    println!("(In production, exchanges would have separate update mechanisms)");
+    // The market maker holds a shared borrow of the router for the whole
+    // session, so books can't be moved through get_exchange_mut here
+    println!("(Note: Books stay fixed while the market maker borrows the router)");
 
     for i in 0..10 {
         // Generate new quotes
@@ -267,7 +285,7 @@ fn main() {
                     println!(
                         "Trade {}: Bought {:.2} BTC @ ${:.2}",
                         i + 1,
-                        quotes.buy_quote.quantity as f64 / 100.0,
+                        mm.get_parameters().from_units(quotes.buy_quote.quantity),
                         quotes.buy_quote.price
                     );
                 } else {
@@ -280,7 +298,7 @@ fn main() {
                     println!(
                         "Trade {}: Sold {:.2} BTC @ ${:.2}",
                         i + 1,
-                        quotes.sell_quote.quantity as f64 / 100.0,
+                        mm.get_parameters().from_units(quotes.sell_quote.quantity),
                         quotes.sell_quote.price
                     );
                 }
@@ -314,7 +332,8 @@ fn main() {
     println!("\nSimulating large inventory imbalance...");
     if let Some(quotes) = mm.update_quotes() {
         for _i in 0..5 {
-            mm.on_quote_filled(&quotes.buy_quote, quotes.buy_quote.price, 100); // Buy 1 BTC each time
+            mm.on_quote_filled(&quotes.buy_quote, quotes.buy_quote.price, Qty(100));
+            // Buy 1 BTC each time
         }
 
         let pos = mm.get_inventory_position();
@@ -339,18 +358,135 @@ fn main() {
             println!(
                 "  Buy: ${:.2} (smaller size: {:.2} BTC)",
                 new_quotes.buy_quote.price,
-                new_quotes.buy_quote.quantity as f64 / 100.0
+                mm.get_parameters()
+                    .from_units(new_quotes.buy_quote.quantity)
             );
             println!(
                 "  Sell: ${:.2} (larger size: {:.2} BTC)",
                 new_quotes.sell_quote.price,
-                new_quotes.sell_quote.quantity as f64 / 100.0
+                mm.get_parameters()
+                    .from_units(new_quotes.sell_quote.quantity)
             );
         }
     }
 
-    // Test 7: Rust-specific features
-    println!("\n7. Rust-Specific Features");
+    // Test 7: Instrument with a different lot size
+    println!("\n7. Alternate Lot Size (0.001 BTC units)");
+    println!("{}", "=".repeat(50));
+
+    let fine_params = MarketMakerParameters::builder()
+        .units_per_base(1000)
+        .base_quote_size(0.125)
+        .build()
+        .expect("valid market maker parameters");
+    let mut fine_mm = MarketMaker::new(&sor, fine_params);
+    fine_mm.initialize(starting_btc, starting_usd);
+
+    if let Some(quotes) = fine_mm.update_quotes() {
+        let params = fine_mm.get_parameters();
+        println!(
+            "Buy quote: {} units = {:.3} BTC",
+            quotes.buy_quote.quantity,
+            params.from_units(quotes.buy_quote.quantity)
+        );
+
+        // Round trip at one price must leave inventory where it started
+        let price = quotes.buy_quote.price;
+        let quantity = quotes.buy_quote.quantity;
+        fine_mm.on_quote_filled(&quotes.buy_quote, price, quantity);
+        fine_mm.on_quote_filled(&quotes.sell_quote, price, quantity);
+
+        let pos = fine_mm.get_inventory_position();
+        println!(
+            "After buying and selling {quantity} units @ ${price:.2}: {:.3} BTC, ${:.2}",
+            pos.base_inventory, pos.quote_inventory
+        );
+        println!(
+            "Inventory restored: {}",
+            if pos.base_inventory == starting_btc && pos.quote_inventory == starting_usd {
+                "YES"
+            } else {
+                "NO"
+            }
+        );
+    }
+
+    // Test 8: Cancel-on-disconnect
+    println!("\n8. Cancel-on-Disconnect");
+    println!("{}", "=".repeat(50));
+
+    fine_mm.set_heartbeat_timeout(Some(Duration::from_millis(500)));
+    fine_mm.update_quotes();
+    let last_update = fine_mm.elapsed();
+
+    let state = fine_mm.check_heartbeat(last_update + Duration::from_millis(200));
+    println!(
+        "200ms after the last update: {state:?}, quotes outstanding: {}",
+        fine_mm.outstanding_quotes().is_some()
+    );
+
+    // Simulate the strategy going quiet past the deadline
+    let state = fine_mm.check_heartbeat(last_update + Duration::from_secs(1));
+    println!(
+        "1s after the last update: {state:?}, quotes outstanding: {}, pulled: {}",
+        fine_mm.outstanding_quotes().is_some(),
+        fine_mm.quotes_pulled()
+    );
+    println!(
+        "Quotes pulled on disconnect: {}",
+        if state == HeartbeatState::Tripped && fine_mm.outstanding_quotes().is_none() {
+            "YES"
+        } else {
+            "NO"
+        }
+    );
+
+    fine_mm.update_quotes();
+    println!(
+        "After the next quote update: {:?}",
+        fine_mm.heartbeat_state()
+    );
+
+    // Test 9: Re-quote cooldown
+    println!("\n9. Re-quote Cooldown");
+    println!("{}", "=".repeat(50));
+
+    let calm_params = MarketMakerParameters::builder()
+        .requote_threshold_bps(2.0)
+        .requote_interval_ms(60_000)
+        .build()
+        .expect("valid market maker parameters");
+    let mut calm_mm = MarketMaker::new(&sor, calm_params.clone());
+    calm_mm.initialize(starting_btc, starting_usd);
+
+    let first = calm_mm.update_quotes();
+    let second = calm_mm.update_quotes();
+    if let (Some(first), Some(second)) = (&first, &second) {
+        println!(
+            "Unchanged market: bid ${:.2} -> ${:.2}, reused: {}",
+            first.buy_quote.price,
+            second.buy_quote.price,
+            calm_mm.quotes_reused()
+        );
+    }
+
+    let age = Duration::from_secs(1);
+    let mid = 45000.0;
+    println!(
+        "Re-quote after a 0.5bp move: {}",
+        calm_params.should_requote(mid, mid * 1.00005, age)
+    );
+    println!(
+        "Re-quote after a 10bp move: {}",
+        calm_params.should_requote(mid, mid * 1.001, age)
+    );
+    println!(
+        "Re-quote once the interval passes: {}",
+        calm_params.should_requote(mid, mid, Duration::from_secs(60))
+    );
+
+    // Test 10: Rust-specific features
+    println!("\n10. Rust-Specific Features");
     println!("{}", "=".repeat(50));
     println!("The Rust implementation showcases:");
     println!("  - Lifetime annotations ('a) for safe references to SOR");
@@ -1,4 +1,5 @@
 // Profiling harness for the matching engine
+use rust_core::money::Qty;
 use rust_core::order_book::OrderBook;
 
 fn main() {
@@ -22,23 +23,23 @@ fn run_matching_engine_scenario() {
 
     // Add initial orders (same as benchmark)
     for i in 0..10 {
-        book.add_order(i * 2, 100.0 + i as f64, 100, true); // Bids
-        book.add_order(i * 2 + 1, 110.0 + i as f64, 100, false); // Asks
+        book.add_order(i * 2, 100.0 + i as f64, Qty(100), true); // Bids
+        book.add_order(i * 2 + 1, 110.0 + i as f64, Qty(100), false); // Asks
     }
 
     // Add crossing orders to trigger matches
     for i in 0..50 {
-        let trades = book.add_order(1000 + i, 109.0, 50, true); // Buy order that crosses
-                                                                // Force some work to happen
+        let trades = book.add_order(1000 + i, 109.0, Qty(50), true); // Buy order that crosses
+                                                                     // Force some work to happen
         drop(trades);
 
-        let trades = book.add_order(2000 + i, 101.0, 50, false); // Sell order that crosses
+        let trades = book.add_order(2000 + i, 101.0, Qty(50), false); // Sell order that crosses
         drop(trades);
     }
 
     // Add more regular orders
     for i in 0..100 {
-        book.add_order(3000 + i, 95.0 + (i % 10) as f64, 100, true);
-        book.add_order(4000 + i, 115.0 + (i % 10) as f64, 100, false);
+        book.add_order(3000 + i, 95.0 + (i % 10) as f64, Qty(100), true);
+        book.add_order(4000 + i, 115.0 + (i % 10) as f64, Qty(100), false);
     }
 }
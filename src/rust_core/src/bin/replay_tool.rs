@@ -1,15 +1,62 @@
 use csv::Reader;
-use rust_core::order_book::{OrderBook, Trade};
+use rust_core::market_data::{OrderAction, OrderEvent};
+use rust_core::money::Qty;
+use rust_core::order_book::{OrderBook, Trade, TradeFlowSummary};
 use std::env;
 use std::error::Error;
 use std::fs::File;
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, serde::Deserialize)]
-struct MarketOrder {
-    is_buy: u8,
+// Per-order narration, suppressed by --quiet
+macro_rules! narrate {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+// Row written by --trades-out
+#[derive(Debug, serde::Serialize)]
+struct TradeRecord {
+    trade_id: u32,
     price: f64,
-    quantity: u32,
+    quantity: Qty,
+    buy_order_id: u32,
+    sell_order_id: u32,
+    aggressor: &'static str,
+    timestamp: u64,
+}
+
+// Replay pacing: as fast as possible, or real inter-arrival gaps scaled by
+// a speed multiplier (2.0 = twice real time)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReplaySpeed {
+    Max,
+    Multiplier(f64),
+}
+
+impl ReplaySpeed {
+    fn parse(value: &str) -> Option<Self> {
+        if value.eq_ignore_ascii_case("max") {
+            return Some(ReplaySpeed::Max);
+        }
+        match value.parse::<f64>() {
+            Ok(speed) if speed > 0.0 && speed.is_finite() => Some(ReplaySpeed::Multiplier(speed)),
+            _ => None,
+        }
+    }
+
+    fn delay(&self, previous_ms: u64, current_ms: u64) -> Option<Duration> {
+        match self {
+            ReplaySpeed::Max => None,
+            ReplaySpeed::Multiplier(speed) => {
+                let delta_ms = current_ms.saturating_sub(previous_ms) as f64;
+                Some(Duration::from_secs_f64(delta_ms / 1000.0 / speed))
+            }
+        }
+    }
 }
 
 fn print_trades(trades: &Vec<Trade>) {
@@ -21,81 +68,249 @@ fn print_trades(trades: &Vec<Trade>) {
     }
 }
 
+fn print_book_state(book: &OrderBook) {
+    print!("Book State - Best Bid: ");
+    if let Some(best_bid) = book.get_best_bid() {
+        print!(
+            "${:.2} (Qty: {})",
+            best_bid,
+            book.get_bid_quantity_at(best_bid)
+        );
+    } else {
+        print!("None");
+    }
+
+    print!(", Best Ask: ");
+    if let Some(best_ask) = book.get_best_ask() {
+        println!(
+            "${:.2} (Qty: {})",
+            best_ask,
+            book.get_ask_quantity_at(best_ask)
+        );
+    } else {
+        println!("None");
+    }
+}
+
+// Narrate and optionally persist the trades produced by one incoming order,
+// returning how many there were
+fn report_trades(
+    trades: &Vec<Trade>,
+    flow: &mut TradeFlowSummary,
+    trade_writer: Option<&mut csv::Writer<File>>,
+    quiet: bool,
+) -> Result<usize, Box<dyn Error>> {
+    if !trades.is_empty() && !quiet {
+        println!("Generated {} trade(s):", trades.len());
+        print_trades(trades);
+    }
+
+    for trade in trades {
+        flow.record(trade);
+    }
+
+    if let Some(writer) = trade_writer {
+        for trade in trades {
+            writer.serialize(TradeRecord {
+                trade_id: trade.trade_id,
+                price: trade.price,
+                quantity: trade.quantity,
+                buy_order_id: trade.buy_order_id,
+                sell_order_id: trade.sell_order_id,
+                aggressor: if trade.aggressor_is_buy {
+                    "BUY"
+                } else {
+                    "SELL"
+                },
+                timestamp: trade.timestamp,
+            })?;
+        }
+    }
+
+    Ok(trades.len())
+}
+
+// The book and running totals of one replay
+struct ReplayState {
+    book: OrderBook,
+    total_trades: usize,
+    flow: TradeFlowSummary,
+    next_order_id: u32,
+    quiet: bool,
+}
+
+impl ReplayState {
+    fn new(quiet: bool) -> Self {
+        ReplayState {
+            book: OrderBook::new(),
+            total_trades: 0,
+            flow: TradeFlowSummary::default(),
+            next_order_id: 1,
+            quiet,
+        }
+    }
+
+    // Apply one recorded row, returning the number of trades it produced.
+    // Malformed rows are skipped and rejected orders narrated.
+    fn apply(
+        &mut self,
+        row: usize,
+        order: &OrderEvent,
+        trade_writer: Option<&mut csv::Writer<File>>,
+    ) -> Result<usize, Box<dyn Error>> {
+        match order.action {
+            OrderAction::Add => {
+                let (Some(is_buy), Some(price), Some(quantity)) =
+                    (order.is_buy_side, order.price, order.quantity)
+                else {
+                    eprintln!("Skipping add row #{row}: is_buy, price and quantity are required");
+                    return Ok(0);
+                };
+
+                // Explicit ids are kept; otherwise allocate past the highest seen
+                let order_id = order.order_id.unwrap_or(self.next_order_id);
+                self.next_order_id = self.next_order_id.max(order_id.saturating_add(1));
+
+                narrate!(
+                    self.quiet,
+                    "\nOrder #{}: {} {} @ ${:.2}",
+                    order_id,
+                    if is_buy { "BUY" } else { "SELL" },
+                    quantity,
+                    price
+                );
+
+                let trades = match self.book.try_add_order(order_id, price, quantity, is_buy) {
+                    Ok(trades) => trades,
+                    Err(e) => {
+                        narrate!(self.quiet, "Order rejected: {e}");
+                        return Ok(0);
+                    }
+                };
+
+                let count = report_trades(&trades, &mut self.flow, trade_writer, self.quiet)?;
+                if trades.is_empty() {
+                    narrate!(self.quiet, "Order added to book (no trades).");
+                }
+                Ok(count)
+            }
+            OrderAction::Cancel => {
+                let Some(order_id) = order.order_id else {
+                    eprintln!("Skipping cancel row #{row}: order_id is required");
+                    return Ok(0);
+                };
+
+                narrate!(self.quiet, "\nCancel Order #{order_id}");
+                match self.book.try_cancel_order(order_id) {
+                    Ok(_) => narrate!(self.quiet, "Order cancelled."),
+                    Err(e) => narrate!(self.quiet, "Cancel rejected: {e}"),
+                }
+                Ok(0)
+            }
+            OrderAction::Modify => {
+                let (Some(order_id), Some(price), Some(quantity)) =
+                    (order.order_id, order.price, order.quantity)
+                else {
+                    eprintln!(
+                        "Skipping modify row #{row}: order_id, price and quantity are required"
+                    );
+                    return Ok(0);
+                };
+
+                narrate!(
+                    self.quiet,
+                    "\nModify Order #{order_id}: {quantity} @ ${price:.2}"
+                );
+
+                // Amend in place under the same id; a size cut at the same
+                // price keeps the order's queue position
+                let trades = match self.book.try_modify_order(order_id, price, quantity) {
+                    Ok(trades) => trades,
+                    Err(e) => {
+                        narrate!(self.quiet, "Modify rejected: {e}");
+                        return Ok(0);
+                    }
+                };
+                let count = report_trades(&trades, &mut self.flow, trade_writer, self.quiet)?;
+                if trades.is_empty() {
+                    narrate!(self.quiet, "Order amended (no trades).");
+                }
+                Ok(count)
+            }
+        }
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     println!("=== Order Book Replay Tool ===");
 
-    // Determine the CSV file path
+    // Determine the CSV file path and replay options
     let args: Vec<String> = env::args().collect();
-    let csv_path = if args.len() > 1 {
-        args[1].clone()
-    } else {
-        "../../market_data.csv".to_string()
-    };
+    let mut csv_path = "../../market_data.csv".to_string();
+    let mut speed = ReplaySpeed::Max;
+    let mut trades_out = None;
+    let mut quiet = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--speed" if i + 1 < args.len() => {
+                speed = ReplaySpeed::parse(&args[i + 1])
+                    .ok_or_else(|| format!("invalid --speed value: {}", args[i + 1]))?;
+                i += 1;
+            }
+            "--trades-out" if i + 1 < args.len() => {
+                trades_out = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--quiet" => quiet = true,
+            path => csv_path = path.to_string(),
+        }
+        i += 1;
+    }
 
     // Read market data from CSV
     println!("\nReading market data from: {csv_path}");
     let file = File::open(&csv_path)?;
     let mut reader = Reader::from_reader(file);
 
-    let mut orders: Vec<MarketOrder> = Vec::new();
+    let mut orders: Vec<OrderEvent> = Vec::new();
     for result in reader.deserialize() {
-        let order: MarketOrder = result?;
+        let order: OrderEvent = result?;
         orders.push(order);
     }
 
     println!("Loaded {} orders from file.", orders.len());
 
+    let mut trade_writer = match &trades_out {
+        Some(path) => Some(csv::Writer::from_path(path)?),
+        None => None,
+    };
+
     // Create order book and replay orders
-    let mut book = OrderBook::new();
-    let mut total_trades = 0;
-    let mut order_id = 1;
+    let mut state = ReplayState::new(quiet);
 
     println!("\n--- Replaying Market Data ---");
 
     let start_time = Instant::now();
+    let mut last_timestamp = None;
 
-    for order in &orders {
-        let is_buy = order.is_buy == 1;
-        println!(
-            "\nOrder #{}: {} {} @ ${:.2}",
-            order_id,
-            if is_buy { "BUY" } else { "SELL" },
-            order.quantity,
-            order.price
-        );
-
-        let trades = book.add_order(order_id, order.price, order.quantity, is_buy);
-        order_id += 1;
-
-        if !trades.is_empty() {
-            println!("Generated {} trade(s):", trades.len());
-            print_trades(&trades);
-            total_trades += trades.len();
-        } else {
-            println!("Order added to book (no trades).");
+    for (row, order) in (1..).zip(orders.iter()) {
+        // Sleep to reproduce the recorded inter-arrival time
+        if let (Some(previous), Some(current)) = (last_timestamp, order.timestamp) {
+            if let Some(delay) = speed.delay(previous, current) {
+                thread::sleep(delay);
+            }
         }
-
-        // Print current book state
-        print!("Book State - Best Bid: ");
-        if let Some(best_bid) = book.get_best_bid() {
-            print!(
-                "${:.2} (Qty: {})",
-                best_bid,
-                book.get_bid_quantity_at(best_bid)
-            );
-        } else {
-            print!("None");
+        if order.timestamp.is_some() {
+            last_timestamp = order.timestamp;
         }
 
-        print!(", Best Ask: ");
-        if let Some(best_ask) = book.get_best_ask() {
-            println!(
-                "${:.2} (Qty: {})",
-                best_ask,
-                book.get_ask_quantity_at(best_ask)
-            );
-        } else {
-            println!("None");
+        state.total_trades += state.apply(row, order, trade_writer.as_mut())?;
+
+        // Print current book state
+        if !quiet {
+            print_book_state(&state.book);
         }
     }
 
@@ -104,7 +319,17 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Print summary
     println!("\n=== Replay Summary ===");
     println!("Total orders processed: {}", orders.len());
-    println!("Total trades generated: {total_trades}");
+    println!("Total trades generated: {}", state.total_trades);
+    if state.flow.trade_count > 0 {
+        println!(
+            "Buy-initiated volume: {}, sell-initiated volume: {}, VWAP: ${:.2}",
+            state.flow.buy_volume, state.flow.sell_volume, state.flow.vwap
+        );
+    }
+    if let (Some(path), Some(mut writer)) = (trades_out, trade_writer) {
+        writer.flush()?;
+        println!("Trades written to: {path}");
+    }
     println!("Processing time: {} microseconds", duration.as_micros());
     println!(
         "Average time per order: {:.2} microseconds",
@@ -113,3 +338,105 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(state: &mut ReplayState, events: &[OrderEvent]) -> Vec<usize> {
+        (1..)
+            .zip(events)
+            .map(|(row, event)| state.apply(row, event, None).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn adds_allocate_ids_past_the_highest_seen() {
+        let mut state = ReplayState::new(true);
+        replay(
+            &mut state,
+            &[
+                OrderEvent::add(true, 99.0, Qty(10)),
+                OrderEvent::add(true, 98.0, Qty(10)).with_order_id(7),
+                OrderEvent::add(false, 101.0, Qty(10)),
+            ],
+        );
+
+        assert_eq!(state.book.get_bid_orders_at(99.0), vec![1]);
+        assert_eq!(state.book.get_bid_orders_at(98.0), vec![7]);
+        assert_eq!(state.book.get_ask_orders_at(101.0), vec![8]);
+        assert_eq!(state.next_order_id, 9);
+    }
+
+    #[test]
+    fn highest_explicit_id_does_not_overflow() {
+        let mut state = ReplayState::new(true);
+        replay(
+            &mut state,
+            &[OrderEvent::add(true, 99.0, Qty(10)).with_order_id(u32::MAX)],
+        );
+        assert!(state.book.contains_order(u32::MAX));
+        assert_eq!(state.next_order_id, u32::MAX);
+    }
+
+    #[test]
+    fn crossing_adds_report_their_trades() {
+        let mut state = ReplayState::new(true);
+        let counts = replay(
+            &mut state,
+            &[
+                OrderEvent::add(false, 100.0, Qty(5)),
+                OrderEvent::add(false, 100.5, Qty(5)),
+                OrderEvent::add(true, 100.5, Qty(8)),
+            ],
+        );
+
+        assert_eq!(counts, vec![0, 0, 2]);
+        assert_eq!(state.flow.buy_volume, Qty(8));
+        assert_eq!(state.book.get_ask_quantity_at(100.5), Qty(2));
+    }
+
+    #[test]
+    fn cancels_remove_known_orders_and_skip_unknown_ones() {
+        let mut state = ReplayState::new(true);
+        let counts = replay(
+            &mut state,
+            &[
+                OrderEvent::add(true, 99.0, Qty(10)),
+                OrderEvent::cancel(1),
+                OrderEvent::cancel(1),
+                OrderEvent::cancel(42),
+            ],
+        );
+
+        assert_eq!(counts, vec![0; 4]);
+        assert_eq!(state.book.get_best_bid(), None);
+    }
+
+    #[test]
+    fn modifies_amend_in_place_and_can_trade() {
+        let mut state = ReplayState::new(true);
+        replay(
+            &mut state,
+            &[
+                OrderEvent::add(true, 99.0, Qty(10)),
+                OrderEvent::add(false, 101.0, Qty(4)),
+                OrderEvent::modify(1, 99.0, Qty(6)),
+            ],
+        );
+        assert_eq!(state.book.get_bid_quantity_at(99.0), Qty(6));
+
+        // Repricing through the ask trades under the same id
+        let counts = replay(&mut state, &[OrderEvent::modify(1, 101.0, Qty(6))]);
+        assert_eq!(counts, vec![1]);
+        assert_eq!(state.book.get_best_ask(), None);
+        assert_eq!(state.book.get_bid_orders_at(101.0), vec![1]);
+        assert_eq!(state.book.get_bid_quantity_at(101.0), Qty(2));
+
+        // Unknown ids are narrated and skipped
+        assert_eq!(
+            replay(&mut state, &[OrderEvent::modify(9, 100.0, Qty(1))]),
+            vec![0]
+        );
+    }
+}
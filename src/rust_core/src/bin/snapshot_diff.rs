@@ -0,0 +1,131 @@
+use rust_core::book_diff::{diff_snapshots, SnapshotDiff};
+use rust_core::order_book::BookSnapshot;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+fn load_snapshot(path: &str) -> Result<BookSnapshot, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+fn side_name(is_buy_side: bool) -> &'static str {
+    if is_buy_side {
+        "BID"
+    } else {
+        "ASK"
+    }
+}
+
+fn print_summary(diff: &SnapshotDiff) {
+    if diff.is_empty() {
+        println!("Snapshots match.");
+        return;
+    }
+
+    if !diff.levels.is_empty() {
+        println!("\nPrice levels that differ ({}):", diff.levels.len());
+        for level in &diff.levels {
+            println!(
+                "  {} ${}: {} -> {} ({:+})",
+                side_name(level.is_buy_side),
+                level.price,
+                level.left_quantity,
+                level.right_quantity,
+                level.delta
+            );
+        }
+    }
+
+    if !diff.only_in_left.is_empty() {
+        println!("\nOrders only in left ({}):", diff.only_in_left.len());
+        for order in &diff.only_in_left {
+            println!(
+                "  #{} {} {} @ ${}",
+                order.order_id,
+                side_name(order.is_buy_side),
+                order.quantity,
+                order.price
+            );
+        }
+    }
+
+    if !diff.only_in_right.is_empty() {
+        println!("\nOrders only in right ({}):", diff.only_in_right.len());
+        for order in &diff.only_in_right {
+            println!(
+                "  #{} {} {} @ ${}",
+                order.order_id,
+                side_name(order.is_buy_side),
+                order.quantity,
+                order.price
+            );
+        }
+    }
+
+    if !diff.changed_orders.is_empty() {
+        println!("\nOrders that changed ({}):", diff.changed_orders.len());
+        for change in &diff.changed_orders {
+            println!(
+                "  #{}: {} {} @ ${} -> {} {} @ ${}",
+                change.order_id,
+                side_name(change.left.is_buy_side),
+                change.left.quantity,
+                change.left.price,
+                side_name(change.right.is_buy_side),
+                change.right.quantity,
+                change.right.price
+            );
+        }
+    }
+
+    if let Some((left, right)) = diff.next_trade_id {
+        println!("\nNext trade id: {left} -> {right}");
+    }
+}
+
+// Compare two BookSnapshot JSON files. Exits with status 1 when they differ
+// so the tool can gate scripts.
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let mut paths = Vec::new();
+    let mut json_out = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--json-out" if i + 1 < args.len() => {
+                json_out = Some(args[i + 1].clone());
+                i += 1;
+            }
+            path => paths.push(path.to_string()),
+        }
+        i += 1;
+    }
+
+    let [left_path, right_path] = paths.as_slice() else {
+        eprintln!("Usage: snapshot_diff <left.json> <right.json> [--json-out <diff.json>]");
+        process::exit(2);
+    };
+
+    let left = load_snapshot(left_path)?;
+    let right = load_snapshot(right_path)?;
+    let diff = diff_snapshots(&left, &right)?;
+
+    println!("=== Snapshot Diff ===");
+    println!("Left:  {left_path} ({} orders)", left.orders.len());
+    println!("Right: {right_path} ({} orders)", right.orders.len());
+    print_summary(&diff);
+
+    if let Some(path) = json_out {
+        serde_json::to_writer_pretty(File::create(&path)?, &diff)?;
+        println!("\nJSON diff written to: {path}");
+    }
+
+    if !diff.is_empty() {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -1,7 +1,12 @@
+use rust_core::execution::{TwapExecution, VwapExecution};
+use rust_core::money::Qty;
 use rust_core::order_book::OrderBook;
 use rust_core::smart_order_router::{
-    Exchange, ExchangeID, ExchangeMetrics, FeeSchedule, SmartOrderRouter,
+    Exchange, ExchangeID, ExchangeMetrics, FeeSchedule, QualityWeights, RoutingDecision,
+    RoutingError, SmartOrderRouter, TieBreaker, TieredFeeSchedule,
 };
+use std::collections::HashMap;
+use std::time::Duration;
 
 // Mock exchange implementation
 struct MockExchange {
@@ -10,6 +15,7 @@ struct MockExchange {
     order_book: OrderBook,
     metrics: ExchangeMetrics,
     is_available: bool,
+    min_order_size: Qty,
 }
 
 impl MockExchange {
@@ -20,6 +26,7 @@ impl MockExchange {
             order_book: OrderBook::new(),
             metrics,
             is_available: true,
+            min_order_size: Qty(1),
         }
     }
 }
@@ -48,12 +55,21 @@ impl Exchange for MockExchange {
     fn get_metrics(&self) -> ExchangeMetrics {
         self.metrics.clone()
     }
+
+    fn min_order_size(&self) -> Qty {
+        self.min_order_size
+    }
 }
 
-fn print_routing_decision(
-    decision: &rust_core::smart_order_router::RoutingDecision,
-    order_type: &str,
-) {
+fn print_routing_decision(decision: &Result<RoutingDecision, RoutingError>, order_type: &str) {
+    let decision = match decision {
+        Ok(decision) => decision,
+        Err(e) => {
+            println!("\n{order_type} Routing Failed: {e}");
+            return;
+        }
+    };
+
     println!("\n{order_type} Routing Decision:");
     println!("  Best Exchange: {}", decision.exchange_id);
     println!("  Expected Price: ${:.2}", decision.expected_price);
@@ -94,40 +110,46 @@ fn main() {
     // Binance: Tight spread, high liquidity
     binance
         .get_order_book_mut()
-        .add_order(1, 45000.00, 10, true); // Buy
-    binance.get_order_book_mut().add_order(2, 44999.50, 5, true); // Buy
+        .add_order(1, 45000.00, Qty(10), true); // Buy
+    binance
+        .get_order_book_mut()
+        .add_order(2, 44999.50, Qty(5), true); // Buy
     binance
         .get_order_book_mut()
-        .add_order(3, 45001.00, 8, false); // Sell
+        .add_order(3, 45001.00, Qty(8), false); // Sell
     binance
         .get_order_book_mut()
-        .add_order(4, 45001.50, 12, false); // Sell
+        .add_order(4, 45001.50, Qty(12), false); // Sell
     println!("  Binance: Bid $45000.00, Ask $45001.00 (Spread: $1.00)");
 
     // Coinbase: Wider spread, medium liquidity
     coinbase
         .get_order_book_mut()
-        .add_order(5, 44999.00, 7, true); // Buy
+        .add_order(5, 44999.00, Qty(7), true); // Buy
     coinbase
         .get_order_book_mut()
-        .add_order(6, 44998.00, 3, true); // Buy
+        .add_order(6, 44998.00, Qty(3), true); // Buy
     coinbase
         .get_order_book_mut()
-        .add_order(7, 45002.00, 6, false); // Sell
+        .add_order(7, 45002.00, Qty(6), false); // Sell
     coinbase
         .get_order_book_mut()
-        .add_order(8, 45003.00, 9, false); // Sell
+        .add_order(8, 45003.00, Qty(9), false); // Sell
     println!("  Coinbase: Bid $44999.00, Ask $45002.00 (Spread: $3.00)");
 
     // Kraken: Best bid, higher ask
-    kraken.get_order_book_mut().add_order(9, 45000.50, 15, true); // Buy (best bid)
-    kraken.get_order_book_mut().add_order(10, 45000.00, 5, true); // Buy
     kraken
         .get_order_book_mut()
-        .add_order(11, 45002.50, 10, false); // Sell
+        .add_order(9, 45000.50, Qty(15), true); // Buy (best bid)
     kraken
         .get_order_book_mut()
-        .add_order(12, 45003.50, 8, false); // Sell
+        .add_order(10, 45000.00, Qty(5), true); // Buy
+    kraken
+        .get_order_book_mut()
+        .add_order(11, 45002.50, Qty(10), false); // Sell
+    kraken
+        .get_order_book_mut()
+        .add_order(12, 45003.50, Qty(8), false); // Sell
     println!("  Kraken: Bid $45000.50, Ask $45002.50 (Spread: $2.00)");
 
     // Create Smart Order Router
@@ -142,31 +164,41 @@ fn main() {
     println!("\n2. Testing Buy Order Routing");
     println!("   Order: BUY 5 BTC at market");
 
-    let buy_decision = sor.route_order(101, 50000.0, 5, true);
+    let buy_decision = sor.route_order(101, 50000.0, Qty(5), true);
     print_routing_decision(&buy_decision, "Buy");
 
     // Test 2: Route a market sell order
     println!("\n3. Testing Sell Order Routing");
     println!("   Order: SELL 5 BTC at market");
 
-    let sell_decision = sor.route_order(102, 40000.0, 5, false);
+    let sell_decision = sor.route_order(102, 40000.0, Qty(5), false);
     print_routing_decision(&sell_decision, "Sell");
 
     // Test 3: Route a large order that needs splitting
     println!("\n4. Testing Large Order Splitting");
     println!("   Order: BUY 20 BTC at market");
 
-    let splits = sor.route_order_split(103, 50000.0, 20, true);
-    println!("\n   Order split across {} exchanges:", splits.len());
-    let mut total_cost = 0.0;
-    for split in &splits {
+    let execution = sor.route_order_split_execution(103, 50000.0, Qty(20), true);
+    println!(
+        "\n   Order split into {} child orders:",
+        execution.splits.len()
+    );
+    for split in &execution.splits {
         println!(
             "   - {}: {} BTC @ ${:.2} (Fee: ${:.2})",
             split.exchange_id, split.quantity, split.expected_price, split.expected_fee
         );
-        total_cost += (split.expected_price * split.quantity as f64) + split.expected_fee;
     }
-    println!("   Total Cost: ${total_cost:.2}");
+    println!(
+        "   Filled: {} BTC, Unfilled: {} BTC",
+        execution.filled_quantity, execution.unfilled_quantity
+    );
+    println!("   Average Price: ${:.2}", execution.average_price);
+    if let Some(worst) = execution.worst_price {
+        println!("   Worst Fill: ${worst:.2}");
+    }
+    println!("   Total Fees: ${:.2}", execution.total_fee);
+    println!("   Total Cost: ${:.2}", execution.total_cost);
 
     // Test 4: Show routing statistics
     sor.print_routing_stats();
@@ -176,7 +208,7 @@ fn main() {
     println!("   Disabling Binance...");
     sor.set_exchange_active(ExchangeID::Binance, false);
 
-    let failover_decision = sor.route_order(104, 50000.0, 5, true);
+    let failover_decision = sor.route_order(104, 50000.0, Qty(5), true);
     println!("   New routing decision after Binance disabled:");
     print_routing_decision(&failover_decision, "Failover Buy");
 
@@ -205,39 +237,689 @@ fn main() {
     // Same order books
     binance2
         .get_order_book_mut()
-        .add_order(1, 45000.00, 10, true);
+        .add_order(1, 45000.00, Qty(10), true);
     binance2
         .get_order_book_mut()
-        .add_order(3, 45001.00, 8, false);
+        .add_order(3, 45001.00, Qty(8), false);
     coinbase2
         .get_order_book_mut()
-        .add_order(5, 44999.00, 7, true);
+        .add_order(5, 44999.00, Qty(7), true);
     coinbase2
         .get_order_book_mut()
-        .add_order(7, 45002.00, 6, false);
+        .add_order(7, 45002.00, Qty(6), false);
     kraken2
         .get_order_book_mut()
-        .add_order(9, 45000.50, 15, true);
+        .add_order(9, 45000.50, Qty(15), true);
     kraken2
         .get_order_book_mut()
-        .add_order(11, 45002.50, 10, false);
+        .add_order(11, 45002.50, Qty(10), false);
 
     sor_no_fees.add_exchange(Box::new(binance2), FeeSchedule::new(0.0010, 0.0010));
     sor_no_fees.add_exchange(Box::new(coinbase2), FeeSchedule::new(0.0005, 0.0015));
     sor_no_fees.add_exchange(Box::new(kraken2), FeeSchedule::new(0.0002, 0.0012));
 
-    let no_fee_decision = sor_no_fees.route_order(105, 50000.0, 5, true);
+    let no_fee_decision = sor_no_fees.route_order(105, 50000.0, Qty(5), true);
+    if let (Ok(no_fee_decision), Ok(buy_decision)) = (&no_fee_decision, &buy_decision) {
+        println!(
+            "   Without fee consideration: Route to {} @ ${:.2}",
+            no_fee_decision.exchange_id, no_fee_decision.expected_price
+        );
+        println!(
+            "   With fee consideration: Route to {} @ ${:.2} (Total: ${:.2})",
+            buy_decision.exchange_id, buy_decision.expected_price, buy_decision.total_cost
+        );
+    }
+
+    // Test 7: Crossed markets across venues
+    println!("\n7. Testing Cross-Exchange Arbitrage Detection");
+
+    let crossed_router = |bid_exchange: ExchangeID, bid: f64| {
+        let mut router = SmartOrderRouter::new(false, true);
+        let mut seller = MockExchange::new(
+            ExchangeID::Binance,
+            "Binance".to_string(),
+            ExchangeMetrics::default(),
+        );
+        let mut buyer = MockExchange::new(
+            bid_exchange,
+            bid_exchange.to_string(),
+            ExchangeMetrics::default(),
+        );
+        seller
+            .get_order_book_mut()
+            .add_order(1, 100.00, Qty(5), false);
+        buyer.get_order_book_mut().add_order(2, bid, Qty(3), true);
+        router.add_exchange(Box::new(seller), FeeSchedule::new(0.0010, 0.0010));
+        router.add_exchange(Box::new(buyer), FeeSchedule::new(0.0002, 0.0012));
+        router
+    };
+
+    // 1% edge comfortably covers ~0.22% of taker fees
+    match crossed_router(ExchangeID::Kraken, 101.00).detect_arbitrage() {
+        Some(arb) => println!(
+            "   Binance ask $100.00 / Kraken bid $101.00: buy {} on {} @ ${:.2}, sell on {} @ ${:.2}, \
+             edge ${:.4}/unit, profit ${:.2}",
+            arb.buy_quantity,
+            arb.buy_exchange,
+            arb.buy_price,
+            arb.sell_exchange,
+            arb.sell_price,
+            arb.edge_per_unit,
+            arb.expected_profit
+        ),
+        None => println!("   Binance ask $100.00 / Kraken bid $101.00: no opportunity (unexpected)"),
+    }
+
+    // A 0.1% cross is eaten by fees
+    match crossed_router(ExchangeID::Coinbase, 100.10).detect_arbitrage() {
+        Some(arb) => println!(
+            "   Binance ask $100.00 / Coinbase bid $100.10: opportunity of ${:.2} (unexpected)",
+            arb.expected_profit
+        ),
+        None => println!(
+            "   Binance ask $100.00 / Coinbase bid $100.10: crossed, but fees eat the edge"
+        ),
+    }
+
+    // Test 8: Per-venue position tracking
+    println!("\n8. Testing Per-Venue Position Tracking");
+    println!("{}", "=".repeat(50));
+
+    // Quantities are lots, 100 to the unit
+    sor.record_fill(ExchangeID::Binance, 100.00, Qty(1000), true);
+    sor.record_fill(ExchangeID::Binance, 101.00, Qty(1000), true);
+    sor.record_fill(ExchangeID::Binance, 102.00, Qty(500), false); // 5 @ avg 100.50
+    sor.record_fill(ExchangeID::Coinbase, 100.50, Qty(800), false);
+    sor.record_fill(ExchangeID::Coinbase, 100.00, Qty(800), true); // Covers the short
+
+    for id in [ExchangeID::Binance, ExchangeID::Coinbase] {
+        println!(
+            "   {id}: position {:+.0}, realized P&L ${:.2}",
+            sor.position(id),
+            sor.pnl(id)
+        );
+    }
+    println!("   Total position: {:+.0}", sor.total_position());
+
+    // Hold the 15 units evenly across venues instead
+    let targets = HashMap::from([
+        (ExchangeID::Binance, 5.0),
+        (ExchangeID::Coinbase, 5.0),
+        (ExchangeID::Kraken, 5.0),
+    ]);
+    println!("\n   Rebalance plan for 5 units per venue:");
+    let mut net_moved = HashMap::new();
+    for transfer in sor.plan_rebalance(&targets) {
+        println!(
+            "   Move {:.0} from {} to {} (offsetting trades would cost {})",
+            transfer.quantity,
+            transfer.from_exchange,
+            transfer.to_exchange,
+            match transfer.offset_trade_cost {
+                Some(cost) => format!("${cost:.2}"),
+                None => "n/a".to_string(),
+            }
+        );
+        *net_moved.entry(transfer.from_exchange).or_insert(0.0) -= transfer.quantity;
+        *net_moved.entry(transfer.to_exchange).or_insert(0.0) += transfer.quantity;
+    }
+    println!(
+        "   Net of all transfers: {:.0}",
+        net_moved.values().sum::<f64>()
+    );
+
+    // Test 9: Venue quality ranking
+    println!("\n9. Testing Exchange Quality Ranking");
+    println!("{}", "=".repeat(50));
+
+    for quality in sor.rank_exchanges() {
+        println!(
+            "   {}: score {:.3} (spread {}, depth {}, fill {:.0}%, {:.0}ms, uptime {:.1}%)",
+            quality.exchange_id,
+            quality.score,
+            match quality.spread_bps {
+                Some(bps) => format!("{bps:.2}bps"),
+                None => "n/a".to_string(),
+            },
+            quality.depth_within_band,
+            quality.fill_rate * 100.0,
+            quality.latency_ms,
+            quality.uptime * 100.0
+        );
+    }
+
+    // Care only about latency
+    sor.set_quality_weights(QualityWeights {
+        spread: 0.0,
+        depth: 0.0,
+        fill_rate: 0.0,
+        uptime: 0.0,
+        ..QualityWeights::default()
+    });
+    let fastest = sor.rank_exchanges();
+    println!(
+        "   Latency-only weighting prefers: {}",
+        fastest
+            .first()
+            .map_or(ExchangeID::Unknown, |q| q.exchange_id)
+    );
+
+    // Test 10: Trade-through protection. The fee-free venue looks cheapest
+    // net of fees but quotes a worse price than another venue's ask.
+    println!("\n10. Testing Trade-Through Protection");
+    println!("{}", "=".repeat(50));
+
+    let mut venues = [
+        MockExchange::new(
+            ExchangeID::Binance,
+            "Binance".to_string(),
+            ExchangeMetrics::default(),
+        ),
+        MockExchange::new(
+            ExchangeID::Coinbase,
+            "Coinbase".to_string(),
+            ExchangeMetrics::default(),
+        ),
+        MockExchange::new(
+            ExchangeID::Kraken,
+            "Kraken".to_string(),
+            ExchangeMetrics::default(),
+        ),
+    ];
+    venues[0]
+        .get_order_book_mut()
+        .add_order(1, 100.00, Qty(3), false);
+    venues[1]
+        .get_order_book_mut()
+        .add_order(1, 100.10, Qty(5), false);
+    venues[2]
+        .get_order_book_mut()
+        .add_order(1, 100.05, Qty(4), false);
+    println!("   Binance: Ask $100.00 x 3 (0.30% taker)");
+    println!("   Coinbase: Ask $100.10 x 5 (no fees)");
+    println!("   Kraken: Ask $100.05 x 4 (0.10% taker)");
+
+    let mut nbbo_sor = SmartOrderRouter::new(false, true);
+    let [binance3, coinbase3, kraken3] = venues;
+    nbbo_sor.add_exchange(Box::new(binance3), FeeSchedule::new(0.0030, 0.0030));
+    nbbo_sor.add_exchange(Box::new(coinbase3), FeeSchedule::new(0.0, 0.0));
+    nbbo_sor.add_exchange(Box::new(kraken3), FeeSchedule::new(0.0010, 0.0010));
+
+    let naive = nbbo_sor
+        .route_order(201, 101.0, Qty(3), true)
+        .expect("asks are resting");
+    println!(
+        "   Without protection, BUY 3 routes to {} @ ${:.2}",
+        naive.exchange_id, naive.expected_price
+    );
+
+    nbbo_sor.set_trade_through_protection(true);
+    let protected = nbbo_sor
+        .route_order(202, 101.0, Qty(3), true)
+        .expect("asks are resting");
+    println!(
+        "   With protection, BUY 3 routes to {} @ ${:.2}",
+        protected.exchange_id, protected.expected_price
+    );
+
+    let execution = nbbo_sor.route_order_split_execution(203, 101.0, Qty(10), true);
+    println!("   Protected split of BUY 10:");
+    for split in &execution.splits {
+        println!(
+            "   - {}: {} @ ${:.2}",
+            split.exchange_id, split.quantity, split.expected_price
+        );
+    }
+    let prices: Vec<f64> = execution.splits.iter().map(|s| s.expected_price).collect();
+    println!(
+        "   Best price first: {}",
+        if prices.windows(2).all(|pair| pair[0] <= pair[1]) {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+
+    // Test 11: Each way routing can fail
+    println!("\n11. Testing Routing Errors");
+    println!("{}", "=".repeat(50));
+
+    let empty_sor = SmartOrderRouter::new(false, false);
+    println!(
+        "   No venues: {:?}",
+        empty_sor.route_order(301, 100.0, Qty(1), true).unwrap_err()
+    );
+
+    let mut bids_only = MockExchange::new(
+        ExchangeID::Binance,
+        "Binance".to_string(),
+        ExchangeMetrics::default(),
+    );
+    bids_only
+        .get_order_book_mut()
+        .add_order(1, 99.0, Qty(10), true);
+    bids_only.min_order_size = Qty(5);
+    let mut thin_sor = SmartOrderRouter::new(false, false);
+    thin_sor.add_exchange(Box::new(bids_only), FeeSchedule::default());
+    println!(
+        "   Buy against a bid-only book: {:?}",
+        thin_sor.route_order(302, 100.0, Qty(1), true).unwrap_err()
+    );
+    println!(
+        "   Sell 2 where the minimum is 5: {:?}",
+        thin_sor.route_order(303, 99.0, Qty(2), false).unwrap_err()
+    );
+    println!(
+        "   Sell 5 where the minimum is 5: routes to {}",
+        thin_sor
+            .route_order(304, 99.0, Qty(5), false)
+            .map_or(ExchangeID::Unknown, |decision| decision.exchange_id)
+    );
+
+    // Test 12: Two venues with identical quotes and fees, so total_cost is
+    // exactly tied and only the tie-breaker decides
+    println!("\n12. Testing Tie-Breaking");
+    println!("{}", "=".repeat(50));
+
+    let mut slow_reliable = MockExchange::new(
+        ExchangeID::Binance,
+        "Binance".to_string(),
+        ExchangeMetrics::new(30, 0.99, 0.999),
+    );
+    let mut fast_flaky = MockExchange::new(
+        ExchangeID::Kraken,
+        "Kraken".to_string(),
+        ExchangeMetrics::new(5, 0.80, 0.999),
+    );
+    for venue in [&mut slow_reliable, &mut fast_flaky] {
+        venue
+            .get_order_book_mut()
+            .add_order(1, 100.00, Qty(10), false);
+    }
+    println!("   Binance: Ask $100.00 x 10 (30ms, 99% fill rate)");
+    println!("   Kraken: Ask $100.00 x 10 (5ms, 80% fill rate)");
+
+    // Latency is left out of the cost so it cannot break the tie itself
+    let mut tied_sor = SmartOrderRouter::new(false, true);
+    tied_sor.add_exchange(Box::new(slow_reliable), FeeSchedule::default());
+    tied_sor.add_exchange(Box::new(fast_flaky), FeeSchedule::default());
+
+    for tie_breaker in [
+        TieBreaker::FirstAdded,
+        TieBreaker::LowestLatency,
+        TieBreaker::HighestFillRate,
+    ] {
+        tied_sor.set_tie_breaker(tie_breaker);
+        let decision = tied_sor
+            .route_order(401, 101.0, Qty(5), true)
+            .expect("asks are resting");
+        println!(
+            "   {:?}: BUY 5 routes to {}",
+            tie_breaker, decision.exchange_id
+        );
+    }
+
+    tied_sor.set_tie_breaker(TieBreaker::RoundRobin);
+    let rotation: Vec<String> = (0..4)
+        .map(|n| {
+            tied_sor
+                .route_order(402 + n, 101.0, Qty(5), true)
+                .map_or(ExchangeID::Unknown, |decision| decision.exchange_id)
+                .to_string()
+        })
+        .collect();
+    println!("   RoundRobin: {}", rotation.join(" -> "));
+
+    // Test 13: Cost-minimizing split. Binance has the best touch but is
+    // thin behind it, so the whole order is cheaper at Kraken than at
+    // Binance, and cheaper still taking Binance's touch and the rest at Kraken.
+    println!("\n13. Testing Cost-Minimizing Split");
+    println!("{}", "=".repeat(50));
+
+    let mut deep_touch = MockExchange::new(
+        ExchangeID::Binance,
+        "Binance".to_string(),
+        ExchangeMetrics::default(),
+    );
+    deep_touch
+        .get_order_book_mut()
+        .add_order(1, 100.00, Qty(5), false);
+    deep_touch
+        .get_order_book_mut()
+        .add_order(2, 101.00, Qty(10), false);
+    let mut flat = MockExchange::new(
+        ExchangeID::Kraken,
+        "Kraken".to_string(),
+        ExchangeMetrics::default(),
+    );
+    flat.get_order_book_mut()
+        .add_order(1, 100.40, Qty(10), false);
+    println!("   Binance: Ask $100.00 x 5, $101.00 x 10");
+    println!("   Kraken: Ask $100.40 x 10");
+
+    let mut split_sor = SmartOrderRouter::new(false, false);
+    split_sor.add_exchange(Box::new(deep_touch), FeeSchedule::default());
+    split_sor.add_exchange(Box::new(flat), FeeSchedule::default());
+
+    // What sending the whole order to the single best venue costs
+    let single = split_sor
+        .route_order(501, 102.0, Qty(10), true)
+        .expect("asks are resting");
+    println!(
+        "   Single venue: BUY 10 at {} costs ${:.2}",
+        single.exchange_id, single.total_cost
+    );
+
+    let execution = split_sor.route_order_split_execution(502, 102.0, Qty(10), true);
+    for split in &execution.splits {
+        println!(
+            "   - {}: {} @ ${:.2}",
+            split.exchange_id, split.quantity, split.expected_price
+        );
+    }
+    println!(
+        "   Split: BUY 10 costs ${:.2} (saves ${:.2})",
+        execution.total_cost,
+        single.total_cost - execution.total_cost
+    );
+
+    // More than both books hold together
+    let execution = split_sor.route_order_split_execution(503, 102.0, Qty(30), true);
+    println!(
+        "   BUY 30 against 25 resting: filled {}, unfilled {}",
+        execution.filled_quantity, execution.unfilled_quantity
+    );
+
+    // Test 14: Venue reliability. Identical quotes and fees, so only the
+    // fill-rate penalty and the uptime threshold separate the venues.
+    println!("\n14. Testing Fill Rate and Uptime Weighting");
+    println!("{}", "=".repeat(50));
+
+    let mut low_fill = MockExchange::new(
+        ExchangeID::Binance,
+        "Binance".to_string(),
+        ExchangeMetrics::new(10, 0.70, 0.999),
+    );
+    let mut low_uptime = MockExchange::new(
+        ExchangeID::Kraken,
+        "Kraken".to_string(),
+        ExchangeMetrics::new(10, 0.99, 0.950),
+    );
+    for venue in [&mut low_fill, &mut low_uptime] {
+        venue
+            .get_order_book_mut()
+            .add_order(1, 100.00, Qty(10), false);
+    }
+    println!("   Binance: Ask $100.00 x 10 (70% fill rate, 99.9% uptime)");
+    println!("   Kraken: Ask $100.00 x 10 (99% fill rate, 95.0% uptime)");
+
+    let mut reliability_sor = SmartOrderRouter::new(false, true);
+    reliability_sor.add_exchange(Box::new(low_fill), FeeSchedule::default());
+    reliability_sor.add_exchange(Box::new(low_uptime), FeeSchedule::default());
+
+    let route = |sor: &SmartOrderRouter, order_id| {
+        sor.route_order(order_id, 101.0, Qty(5), true)
+            .map_or(ExchangeID::Unknown, |decision| decision.exchange_id)
+    };
+    println!(
+        "   Fill rate ignored: BUY 5 routes to {}",
+        route(&reliability_sor, 601)
+    );
+    reliability_sor.set_fill_rate_weight(1.0);
+    println!(
+        "   Fill rate weighted: BUY 5 routes to {}",
+        route(&reliability_sor, 602)
+    );
+    reliability_sor.set_min_uptime(0.99);
+    println!(
+        "   With a 99% uptime floor: BUY 5 routes to {}",
+        route(&reliability_sor, 603)
+    );
+
+    // Test 15: Venues stay reachable after registration
+    println!("\n15. Testing Exchange Access and Removal");
+    println!("{}", "=".repeat(50));
+
+    let mut registry_sor = SmartOrderRouter::new(false, false);
+    for (id, name, ask) in [
+        (ExchangeID::Binance, "Binance", 100.00),
+        (ExchangeID::Kraken, "Kraken", 100.50),
+    ] {
+        let mut venue = MockExchange::new(id, name.to_string(), ExchangeMetrics::default());
+        venue.get_order_book_mut().add_order(1, ask, Qty(10), false);
+        registry_sor.add_exchange(Box::new(venue), FeeSchedule::default());
+    }
+
+    let route = |sor: &SmartOrderRouter, order_id| {
+        sor.route_order(order_id, 101.0, Qty(5), true)
+            .map_or(ExchangeID::Unknown, |decision| decision.exchange_id)
+    };
+    println!("   BUY 5 routes to {}", route(&registry_sor, 701));
+
+    // Kraken improves its offer after being handed to the router
+    if let Some(kraken) = registry_sor.get_exchange_mut(ExchangeID::Kraken) {
+        kraken
+            .get_order_book_mut()
+            .add_order(2, 99.90, Qty(10), false);
+    }
+    println!(
+        "   Kraken best ask via get_exchange: ${:.2}",
+        registry_sor
+            .get_exchange(ExchangeID::Kraken)
+            .and_then(|kraken| kraken.get_order_book().get_best_ask())
+            .unwrap_or(0.0)
+    );
+    println!(
+        "   After Kraken improves: BUY 5 routes to {}",
+        route(&registry_sor, 702)
+    );
+
+    println!(
+        "   Remove Kraken: {}",
+        registry_sor.remove_exchange(ExchangeID::Kraken)
+    );
+    println!(
+        "   Remove Kraken again: {}",
+        registry_sor.remove_exchange(ExchangeID::Kraken)
+    );
+    println!(
+        "   After removal: BUY 5 routes to {}, Kraken registered: {}",
+        route(&registry_sor, 703),
+        registry_sor.get_exchange(ExchangeID::Kraken).is_some()
+    );
+
+    // Test 16: Maker rebates. Same book on both venues; Kraken pays 0.01%
+    // to makers but charges more to takers.
+    println!("\n16. Testing Maker Rebates");
+    println!("{}", "=".repeat(50));
+
+    let mut rebate_sor = SmartOrderRouter::new(false, true);
+    for (id, name, fees) in [
+        (ExchangeID::Binance, "Binance", FeeSchedule::new(0.0, 0.0)),
+        (
+            ExchangeID::Kraken,
+            "Kraken",
+            FeeSchedule::new(-0.0001, 0.0010),
+        ),
+    ] {
+        let mut venue = MockExchange::new(id, name.to_string(), ExchangeMetrics::default());
+        venue
+            .get_order_book_mut()
+            .add_order(1, 99.00, Qty(10), true);
+        venue
+            .get_order_book_mut()
+            .add_order(2, 100.00, Qty(10), false);
+        rebate_sor.add_exchange(Box::new(venue), fees);
+    }
+    println!("   Binance: 99.00 / 100.00, no fees");
+    println!("   Kraken: 99.00 / 100.00, -0.01% maker / 0.10% taker");
+
+    let taker = rebate_sor.route_order(801, 100.0, Qty(10), true);
+    print_routing_decision(&taker, "Crossing Buy");
+    let maker = rebate_sor.route_maker_order(802, 99.50, Qty(10), true);
+    print_routing_decision(&maker, "Post-Only Buy @ $99.50");
     println!(
-        "   Without fee consideration: Route to {} @ ${:.2}",
-        no_fee_decision.exchange_id, no_fee_decision.expected_price
+        "   Post-only buy @ $100.00: {:?}",
+        rebate_sor
+            .route_maker_order(803, 100.0, Qty(10), true)
+            .unwrap_err()
     );
+
+    // Test 17: Volume-tiered fees. Taker fee halves once 1,000 of notional
+    // has been booked at the venue.
+    println!("\n17. Testing Volume-Tiered Fees");
+    println!("{}", "=".repeat(50));
+
+    let mut tiered_venue = MockExchange::new(
+        ExchangeID::Binance,
+        "Binance".to_string(),
+        ExchangeMetrics::default(),
+    );
+    tiered_venue
+        .get_order_book_mut()
+        .add_order(1, 100.00, Qty(5000), false);
+    let mut tiered_sor = SmartOrderRouter::new(false, true);
+    tiered_sor.add_exchange(
+        Box::new(tiered_venue),
+        TieredFeeSchedule::new(FeeSchedule::new(0.0010, 0.0020))
+            .with_tier(10_000.0, 0.0, 0.0005)
+            .with_tier(1_000.0, 0.0005, 0.0010),
+    );
+    println!("   Binance tiers: 0.20% taker, 0.10% from $1,000, 0.05% from $10,000");
+
+    let show_tier = |sor: &SmartOrderRouter, order_id| {
+        let decision = sor
+            .route_order(order_id, 101.0, Qty(500), true)
+            .expect("asks are resting");
+        println!(
+            "   Volume ${:.2}: taker {:.2}%, BUY 5.00 fee ${:.2}",
+            sor.traded_volume(ExchangeID::Binance),
+            sor.current_fees(ExchangeID::Binance)
+                .map_or(0.0, |fees| fees.taker_fee * 100.0),
+            decision.expected_fee
+        );
+    };
+    show_tier(&tiered_sor, 901);
+    tiered_sor.record_fill(ExchangeID::Binance, 100.0, Qty(900), true);
+    show_tier(&tiered_sor, 902);
+    tiered_sor.record_fill(ExchangeID::Binance, 100.0, Qty(100), false);
+    show_tier(&tiered_sor, 903);
+
+    // Test 18: Venues outside the built-in list
+    println!("\n18. Testing Custom Exchange Identifiers");
+    println!("{}", "=".repeat(50));
+
+    let okx = ExchangeID::custom("OKX").expect("OKX is not a built-in name");
+    let bybit = ExchangeID::custom("Bybit").expect("Bybit is not a built-in name");
+    println!(
+        "   Registered {okx} ({okx:?}) and {bybit} ({bybit:?}); OKX again is {:?}",
+        ExchangeID::custom("OKX")
+    );
+    if let Err(e) = ExchangeID::custom("binance") {
+        println!("   Registering \"binance\": {e}");
+    }
+
+    let mut custom_sor = SmartOrderRouter::new(false, false);
+    for (id, ask) in [(okx, 100.20), (bybit, 100.10)] {
+        let mut venue = MockExchange::new(id, id.to_string(), ExchangeMetrics::default());
+        venue.get_order_book_mut().add_order(1, ask, Qty(10), false);
+        custom_sor.add_exchange(Box::new(venue), FeeSchedule::default());
+    }
+    println!("   OKX: Ask $100.20 x 10, Bybit: Ask $100.10 x 10");
+
+    let route = |sor: &SmartOrderRouter, order_id| {
+        sor.route_order(order_id, 101.0, Qty(5), true)
+            .map_or(ExchangeID::Unknown, |decision| decision.exchange_id)
+    };
+    println!("   BUY 5 routes to {}", route(&custom_sor, 1001));
+    custom_sor.set_exchange_active(bybit, false);
+    println!(
+        "   With Bybit inactive: BUY 5 routes to {}",
+        route(&custom_sor, 1002)
+    );
+
+    // Test 19: TWAP. A simulated clock steps through the schedule one
+    // interval at a time.
+    println!("\n19. Testing TWAP Execution");
+    println!("{}", "=".repeat(50));
+
+    let interval = Duration::from_secs(60);
+    let mut twap = TwapExecution::new(1101, 101.0, Qty(100), true, 7, interval);
+    let mut sizes = Vec::new();
+    let mut now = Duration::ZERO;
+    while !twap.is_finished() {
+        if let Some(quantity) = twap.next_slice_at(now) {
+            sizes.push(quantity);
+            twap.record_fill(quantity);
+        }
+        now += interval;
+    }
+    println!(
+        "   BUY 100 in 7 slices: {} (sum {})",
+        sizes
+            .iter()
+            .map(|quantity| quantity.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        sizes.iter().copied().sum::<Qty>()
+    );
+    println!(
+        "   Nothing more after the schedule ends: {:?}",
+        twap.next_slice_at(now)
+    );
+
+    // Driving the router: each slice is split across Binance and Kraken
+    let mut twap = TwapExecution::new(1102, 101.0, Qty(30), true, 3, interval);
+    for slice in 0..3 {
+        let splits = twap
+            .execute_slice_at(&split_sor, interval * slice)
+            .unwrap_or_default();
+        let routed: Vec<String> = splits
+            .iter()
+            .map(|split| {
+                format!(
+                    "{} @ ${:.2} on {}",
+                    split.quantity, split.expected_price, split.exchange_id
+                )
+            })
+            .collect();
+        println!("   Slice {}: {}", slice + 1, routed.join(", "));
+    }
+    println!(
+        "   Filled {} of 30, complete: {}",
+        twap.filled_quantity(),
+        twap.is_complete()
+    );
+
+    // Test 20: VWAP. Most of the volume is expected mid-session.
+    println!("\n20. Testing VWAP Execution");
+    println!("{}", "=".repeat(50));
+
+    let profile = vec![1.0, 2.0, 4.0, 2.0, 1.0];
+    let total_weight: f64 = profile.iter().sum();
+    let mut vwap = VwapExecution::new(&tiered_sor, 1201, 101.0, Qty(101), true, profile.clone());
+    println!("   BUY 101 over a {:?} volume profile:", profile);
+    for (bucket, weight) in profile.iter().enumerate() {
+        let executed: Qty = vwap
+            .on_bucket(bucket)
+            .iter()
+            .map(|split| split.quantity)
+            .sum();
+        println!(
+            "   Bucket {}: executed {} (weight share {:.1})",
+            bucket + 1,
+            executed,
+            101.0 * weight / total_weight
+        );
+    }
     println!(
-        "   With fee consideration: Route to {} @ ${:.2} (Total: ${:.2})",
-        buy_decision.exchange_id, buy_decision.expected_price, buy_decision.total_cost
+        "   Filled {} of 101, complete: {}",
+        vwap.filled_quantity(),
+        vwap.is_complete()
     );
 
-    // Test 7: Rust-specific - Demonstrate trait object flexibility
-    println!("\n7. Rust-Specific Feature: Dynamic Exchange Types");
+    // Test 21: Rust-specific - Demonstrate trait object flexibility
+    println!("\n21. Rust-Specific Feature: Dynamic Exchange Types");
     println!("   The Rust implementation uses trait objects (Box<dyn Exchange>)");
     println!("   This allows runtime polymorphism without inheritance");
     println!("   Each exchange can have different internal implementations");
@@ -1,164 +1,423 @@
 use futures_util::{SinkExt, StreamExt};
-use rust_core::order_book::OrderBook;
-use serde::Deserialize;
-use std::collections::HashMap;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-
-#[derive(Debug, Deserialize)]
-struct DepthUpdate {
-    #[serde(rename = "e")]
-    #[allow(dead_code)]
-    event_type: String,
-    #[serde(rename = "E")]
-    #[allow(dead_code)]
-    event_time: u64,
-    #[serde(rename = "s")]
-    #[allow(dead_code)]
-    symbol: String,
-    #[serde(rename = "b")]
-    bids: Vec<Vec<String>>,
-    #[serde(rename = "a")]
-    asks: Vec<Vec<String>>,
-}
-
-async fn handle_binance_stream() -> Result<(), Box<dyn std::error::Error>> {
-    // Binance WebSocket endpoint for BTC/USDT depth updates
-    let url = "wss://stream.binance.com:9443/ws/btcusdt@depth";
-
-    println!("Connecting to Binance WebSocket stream: {url}");
+use rust_core::depth_source::{
+    depth_source_for, DepthSource, NormalizedDepth, SequenceCheck, SequenceTracker,
+};
+use rust_core::market_data::OrderEvent;
+use rust_core::money::{price_to_tick, Qty, PRICE_SCALE, QUANTITY_SCALE};
+use rust_core::order_book::{BookSnapshot, OrderBook};
+use rust_core::telemetry;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::future::Future;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::{protocol::Message, Error as WsError};
+use tracing::{debug, error, info, warn};
+
+// Flush the capture file every this many rows
+const RECORD_FLUSH_INTERVAL: usize = 1000;
+
+// How long to wait for the server's reply to our close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Levels per side covered by venue book checksums
+const CHECKSUM_LEVELS: usize = 10;
+
+// (is_buy_side, price tick) of one recorded level
+type LevelKey = (bool, u64);
+
+// Writes the book the client maintains to a replayable CSV. Each price level
+// is one synthetic order with a stable id: an add row when the level
+// appears, a modify row when its quantity changes and a cancel row when it
+// is removed, so replay_tool rebuilds the same levels.
+struct DepthRecorder<W: Write = File> {
+    writer: csv::Writer<W>,
+    rows: usize,
+    // Order id and quantity of every level currently recorded
+    levels: BTreeMap<LevelKey, (u32, Qty)>,
+    next_order_id: u32,
+}
+
+impl DepthRecorder {
+    fn create(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(DepthRecorder::new(csv::Writer::from_path(path)?))
+    }
+}
+
+impl<W: Write> DepthRecorder<W> {
+    fn new(writer: csv::Writer<W>) -> Self {
+        DepthRecorder {
+            writer,
+            rows: 0,
+            levels: BTreeMap::new(),
+            next_order_id: 1,
+        }
+    }
+
+    // Record a level's new quantity; zero removes the level
+    fn record_level(
+        &mut self,
+        is_buy_side: bool,
+        price: f64,
+        quantity: Qty,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = (is_buy_side, price_to_tick(price));
+        let event = match self.levels.get(&key).copied() {
+            None if quantity.is_zero() => return Ok(()),
+            None => {
+                let order_id = self.next_order_id;
+                self.next_order_id += 1;
+                self.levels.insert(key, (order_id, quantity));
+                OrderEvent::add(is_buy_side, price, quantity).with_order_id(order_id)
+            }
+            Some((order_id, _)) if quantity.is_zero() => {
+                self.levels.remove(&key);
+                OrderEvent::cancel(order_id)
+            }
+            Some((_, recorded)) if recorded == quantity => return Ok(()),
+            Some((order_id, _)) => {
+                self.levels.insert(key, (order_id, quantity));
+                OrderEvent::modify(order_id, price, quantity)
+            }
+        };
+        self.write(event)
+    }
+
+    // Record a whole book as (price, quantity, is_buy_side) levels. Recorded
+    // levels it doesn't list are cancelled first, so they can't cross the
+    // new ones on replay.
+    fn record_book(&mut self, levels: &[(f64, Qty, bool)]) -> Result<(), Box<dyn Error>> {
+        let listed: HashSet<LevelKey> = levels
+            .iter()
+            .map(|&(price, _, is_buy_side)| (is_buy_side, price_to_tick(price)))
+            .collect();
+        let removed: Vec<u32> = self
+            .levels
+            .iter()
+            .filter(|(key, _)| !listed.contains(key))
+            .map(|(_, &(order_id, _))| order_id)
+            .collect();
+        self.levels.retain(|key, _| listed.contains(key));
+        for order_id in removed {
+            self.write(OrderEvent::cancel(order_id))?;
+        }
+
+        for &(price, quantity, is_buy_side) in levels {
+            self.record_level(is_buy_side, price, quantity)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, event: OrderEvent) -> Result<(), Box<dyn Error>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        self.writer.serialize(event.with_timestamp(timestamp))?;
+        self.rows += 1;
+
+        if self.rows.is_multiple_of(RECORD_FLUSH_INTERVAL) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<usize, Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(self.rows)
+    }
+}
+
+// Base-unit levels as book lots. Zero quantities stay in: they clear the
+// level.
+fn to_lots(order_book: &OrderBook, levels: &[(f64, f64)]) -> Vec<(f64, Qty)> {
+    levels
+        .iter()
+        .filter(|&&(price, quantity)| price > 0.0 && quantity >= 0.0)
+        .map(|&(price, quantity)| (price, order_book.quantity_from_base(quantity)))
+        .collect()
+}
+
+// Apply one incremental depth update in a single call. Strict books refuse
+// levels that would lock or cross, which points at stale levels on the
+// other side.
+fn apply_update(
+    order_book: &mut OrderBook,
+    depth: &NormalizedDepth,
+    order_id: &mut u32,
+    recorder: Option<&mut DepthRecorder<impl Write>>,
+) -> Result<(), Box<dyn Error>> {
+    let bids = to_lots(order_book, &depth.bids);
+    let asks = to_lots(order_book, &depth.asks);
+
+    let update = match order_book.apply_depth_update(&bids, &asks, *order_id) {
+        Ok(update) => update,
+        Err(e) => {
+            warn!(error = %e, "Depth update rejected");
+            return Ok(());
+        }
+    };
+    *order_id = update.next_order_id;
+
+    let scale = order_book.quantity_scale();
+    for &(price, quantity, is_buy_side) in &update.rejected {
+        let side = if is_buy_side { "bid" } else { "ask" };
+        warn!(
+            side,
+            price,
+            quantity = quantity.to_base_f64(scale),
+            "Level update would lock or cross, rejected"
+        );
+    }
+
+    if let Some(recorder) = recorder {
+        let levels = bids
+            .iter()
+            .map(|&(price, quantity)| (price, quantity, true))
+            .chain(
+                asks.iter()
+                    .map(|&(price, quantity)| (price, quantity, false)),
+            );
+        for level in levels {
+            let (price, quantity, is_buy_side) = level;
+            // A rejected level leaves its price empty
+            let quantity = if update.rejected.contains(&level) {
+                Qty::ZERO
+            } else {
+                quantity
+            };
+            recorder.record_level(is_buy_side, price, quantity)?;
+        }
+    }
+
+    if !update.trades.is_empty() {
+        telemetry::record_trades(update.trades.len());
+        debug!(
+            trades = update.trades.len(),
+            "Depth update generated trades"
+        );
+    }
+
+    Ok(())
+}
+
+// Replace the book with one built from a full depth update. Returns false,
+// leaving the book untouched, when the update is crossed.
+fn seed_levels(
+    order_book: &mut OrderBook,
+    depth: &NormalizedDepth,
+    order_id: &mut u32,
+    recorder: Option<&mut DepthRecorder<impl Write>>,
+) -> Result<bool, Box<dyn Error>> {
+    let non_empty = |levels: Vec<(f64, Qty)>| -> Vec<(f64, Qty)> {
+        levels
+            .into_iter()
+            .filter(|&(_, quantity)| !quantity.is_zero())
+            .collect()
+    };
+    let bids = non_empty(to_lots(order_book, &depth.bids));
+    let asks = non_empty(to_lots(order_book, &depth.asks));
+
+    match OrderBook::from_levels(&bids, &asks) {
+        Ok(book) => {
+            let mut book = book
+                .with_quantity_scale(order_book.quantity_scale())
+                .with_price_scale(order_book.price_scale());
+            book.set_trade_id_counter(order_book.trade_id_counter());
+            *order_book = if order_book.is_strict() {
+                book.with_strict_mode()
+            } else {
+                book
+            };
+        }
+        Err(e) => {
+            warn!(error = %e, "Cannot seed book from first update, applying per level");
+            return Ok(false);
+        }
+    }
+
+    // Ids run 1, 2, ... over bids then asks
+    let levels: Vec<(f64, Qty, bool)> = bids
+        .iter()
+        .map(|&(price, quantity)| (price, quantity, true))
+        .chain(
+            asks.iter()
+                .map(|&(price, quantity)| (price, quantity, false)),
+        )
+        .collect();
+    *order_id = levels.len() as u32 + 1;
+    if let Some(recorder) = recorder {
+        recorder.record_book(&levels)?;
+    }
+
+    Ok(true)
+}
+
+// Top of book as (price, base quantity) pairs, copied out so printing
+// happens without holding the book lock
+type TopOfBook = (Option<(f64, f64)>, Option<(f64, f64)>);
+
+fn top_of_book(order_book: &OrderBook) -> TopOfBook {
+    let scale = order_book.quantity_scale();
+    let bid = order_book
+        .get_best_bid()
+        .map(|bid| (bid, order_book.get_bid_quantity_at(bid).to_base_f64(scale)));
+    let ask = order_book
+        .get_best_ask()
+        .map(|ask| (ask, order_book.get_ask_quantity_at(ask).to_base_f64(scale)));
+    (bid, ask)
+}
+
+// Why the stream loop returned
+enum StreamExit {
+    Closed,
+    // Update ids were not contiguous; the local book can no longer be trusted
+    ResyncRequired,
+    // The shutdown signal fired, e.g. Ctrl-C was pressed
+    Interrupted,
+}
+
+// The book is shared so other tasks (e.g. a strategy) can read best bid/ask
+// while this task applies updates. Each depth message is applied under a
+// single lock acquisition. When `shutdown` completes the connection is
+// closed with a close handshake.
+async fn handle_depth_stream(
+    source: &dyn DepthSource,
+    order_book: Arc<Mutex<OrderBook>>,
+    mut recorder: Option<&mut DepthRecorder>,
+    update_count: &mut usize,
+    shutdown: impl Future<Output = ()>,
+) -> Result<StreamExit, Box<dyn Error>> {
+    let url = source.url();
+
+    tokio::pin!(shutdown);
+
+    info!(exchange = source.name(), %url, "Connecting to WebSocket stream");
 
     // Connect to the WebSocket
-    let (ws_stream, _) = connect_async(url).await?;
-    println!("Connected to Binance WebSocket stream: /ws/btcusdt@depth");
-    println!("Listening for BTC/USDT depth updates...\n");
+    let (ws_stream, _) = tokio::select! {
+        connected = connect_async(url.as_str()) => connected?,
+        _ = &mut shutdown => {
+            info!("Shutdown requested while connecting");
+            return Ok(StreamExit::Interrupted);
+        }
+    };
+    info!(exchange = source.name(), "Connected to WebSocket stream");
 
     let (mut write, mut read) = ws_stream.split();
 
-    // Create OrderBook instance
-    let mut order_book = OrderBook::new();
-    let mut order_id: u32 = 1;
-    let mut update_count = 0;
+    if let Some(subscribe) = source.subscribe_message() {
+        write.send(Message::Text(subscribe)).await?;
+    }
+    info!("Listening for depth updates");
 
-    // Track orders at each price level for cancellation
-    let mut buy_orders: HashMap<String, Vec<u32>> = HashMap::new();
-    let mut sell_orders: HashMap<String, Vec<u32>> = HashMap::new();
+    let mut order_id: u32 = 1;
+    // Whether this connection's first update has been offered to seed_levels
+    let mut seeded = false;
+    let mut sequence = SequenceTracker::new();
 
     // Process incoming messages
-    while let Some(message) = read.next().await {
+    loop {
+        let message = tokio::select! {
+            message = read.next() => message,
+            _ = &mut shutdown => {
+                info!("Shutdown requested, closing stream");
+                close_gracefully(&mut write, &mut read).await;
+                return Ok(StreamExit::Interrupted);
+            }
+        };
+
+        let Some(message) = message else {
+            break;
+        };
+
         match message {
             Ok(Message::Text(text)) => {
-                // Parse the JSON message
-                match serde_json::from_str::<DepthUpdate>(&text) {
+                // Parse the message with the venue-specific parser
+                match source.parse(&text) {
                     Ok(depth) => {
-                        update_count += 1;
-                        println!("=== Update #{update_count} ===");
-
-                        // Process bids (buy orders)
-                        println!("Processing {} bid levels...", depth.bids.len());
-                        for bid in &depth.bids {
-                            if bid.len() >= 2 {
-                                let price = bid[0].parse::<f64>().unwrap_or(0.0);
-                                let quantity = bid[1].parse::<f64>().unwrap_or(0.0);
-
-                                if quantity > 0.0 && price > 0.0 {
-                                    // Cancel existing orders at this price level
-                                    let price_key = bid[0].clone();
-                                    if let Some(existing_orders) = buy_orders.get(&price_key) {
-                                        for &oid in existing_orders {
-                                            order_book.cancel_order(oid);
-                                        }
-                                    }
-
-                                    // Add new order
-                                    let trades = order_book.add_order(
-                                        order_id,
-                                        price,
-                                        quantity as u32,
-                                        true,
-                                    );
-
-                                    // Track the order
-                                    buy_orders.entry(price_key).or_default().clear();
-                                    buy_orders.get_mut(&bid[0]).unwrap().push(order_id);
-
-                                    if !trades.is_empty() {
-                                        println!(
-                                            "  Generated {} trade(s) from bid @ ${price}",
-                                            trades.len()
-                                        );
-                                    }
-
-                                    order_id += 1;
-                                }
+                        match sequence.check(&depth) {
+                            SequenceCheck::InSequence => {}
+                            SequenceCheck::Stale => continue,
+                            SequenceCheck::Gap { expected, received } => {
+                                warn!(
+                                    expected,
+                                    received, "Sequence gap detected - resync required"
+                                );
+                                return Ok(StreamExit::ResyncRequired);
                             }
                         }
 
-                        // Process asks (sell orders)
-                        println!("Processing {} ask levels...", depth.asks.len());
-                        for ask in &depth.asks {
-                            if ask.len() >= 2 {
-                                let price = ask[0].parse::<f64>().unwrap_or(0.0);
-                                let quantity = ask[1].parse::<f64>().unwrap_or(0.0);
-
-                                if quantity > 0.0 && price > 0.0 {
-                                    // Cancel existing orders at this price level
-                                    let price_key = ask[0].clone();
-                                    if let Some(existing_orders) = sell_orders.get(&price_key) {
-                                        for &oid in existing_orders {
-                                            order_book.cancel_order(oid);
-                                        }
-                                    }
-
-                                    // Add new order
-                                    let trades = order_book.add_order(
-                                        order_id,
-                                        price,
-                                        quantity as u32,
-                                        false,
-                                    );
+                        *update_count += 1;
+                        debug!(
+                            update = *update_count,
+                            bid_levels = depth.bids.len(),
+                            ask_levels = depth.asks.len(),
+                            "Processing depth update"
+                        );
 
-                                    // Track the order
-                                    sell_orders.entry(price_key).or_default().clear();
-                                    sell_orders.get_mut(&ask[0]).unwrap().push(order_id);
+                        let (touch_moved, (best_bid, best_ask), spread) = {
+                            let mut book = order_book.lock().unwrap();
 
-                                    if !trades.is_empty() {
-                                        println!(
-                                            "  Generated {} trade(s) from ask @ ${price}",
-                                            trades.len()
-                                        );
-                                    }
+                            // The first update of a connection bulk-loads the
+                            // empty book; later ones replace the levels they list
+                            let bulk_loaded = !seeded
+                                && seed_levels(
+                                    &mut book,
+                                    &depth,
+                                    &mut order_id,
+                                    recorder.as_deref_mut(),
+                                )?;
+                            seeded = true;
 
-                                    order_id += 1;
+                            if !bulk_loaded {
+                                apply_update(
+                                    &mut book,
+                                    &depth,
+                                    &mut order_id,
+                                    recorder.as_deref_mut(),
+                                )?;
+                            }
+
+                            if let Some(expected) = depth.checksum {
+                                let local = book.checksum(CHECKSUM_LEVELS);
+                                if local != expected {
+                                    warn!(
+                                        expected,
+                                        local, "Book checksum mismatch - local book out of sync"
+                                    );
                                 }
                             }
-                        }
 
-                        // Display current order book state
-                        println!("\nLocal Order Book State:");
-                        if let Some(best_bid) = order_book.get_best_bid() {
-                            let bid_qty = order_book.get_bid_quantity_at(best_bid);
-                            print!("  Best Bid: ${best_bid:.2} (Qty: {bid_qty})");
-                        } else {
-                            print!("  Best Bid: None");
-                        }
+                            // A seeded book is new, so its touch always counts as moved
+                            let touch_moved =
+                                book.take_touch_delta().touch_changed() || bulk_loaded;
+                            (touch_moved, top_of_book(&book), book.spread())
+                        };
 
-                        if let Some(best_ask) = order_book.get_best_ask() {
-                            let ask_qty = order_book.get_ask_quantity_at(best_ask);
-                            println!(" | Best Ask: ${best_ask:.2} (Qty: {ask_qty})");
-                        } else {
-                            println!(" | Best Ask: None");
+                        // Depth-only changes leave the touch alone
+                        if !touch_moved {
+                            continue;
                         }
 
-                        if let (Some(bid), Some(ask)) =
-                            (order_book.get_best_bid(), order_book.get_best_ask())
-                        {
-                            println!("  Spread: ${:.2}\n", ask - bid);
-                        } else {
-                            println!("  Spread: N/A\n");
-                        }
+                        telemetry::set_top_of_book(
+                            best_bid.map(|(price, _)| price),
+                            best_ask.map(|(price, _)| price),
+                        );
+
+                        // Report current order book state
+                        info!(
+                            update = *update_count,
+                            best_bid = ?best_bid.map(|(price, _)| price),
+                            bid_qty = ?best_bid.map(|(_, qty)| qty),
+                            best_ask = ?best_ask.map(|(price, _)| price),
+                            ask_qty = ?best_ask.map(|(_, qty)| qty),
+                            spread = ?spread,
+                            "Top of book moved"
+                        );
                     }
                     Err(e) => {
-                        eprintln!("Failed to parse depth update: {e}");
+                        warn!(error = %e, "Failed to parse depth update");
                     }
                 }
             }
@@ -167,24 +426,507 @@ async fn handle_binance_stream() -> Result<(), Box<dyn std::error::Error>> {
                 write.send(Message::Pong(ping)).await?;
             }
             Ok(Message::Close(_)) => {
-                println!("WebSocket connection closed");
+                info!("WebSocket connection closed");
                 break;
             }
             Err(e) => {
-                eprintln!("WebSocket error: {e}");
+                error!(error = %e, "WebSocket error");
                 break;
             }
             _ => {}
         }
     }
 
+    Ok(StreamExit::Closed)
+}
+
+// Send a close frame and give the server a moment to answer with its own,
+// so the connection ends with a proper handshake rather than a reset
+async fn close_gracefully<W, R>(write: &mut W, read: &mut R)
+where
+    W: SinkExt<Message> + Unpin,
+    W::Error: std::fmt::Display,
+    R: StreamExt<Item = Result<Message, WsError>> + Unpin,
+{
+    if let Err(e) = write.send(Message::Close(None)).await {
+        warn!(error = %e, "Failed to send close frame");
+        return;
+    }
+
+    let acknowledged = async {
+        while let Some(Ok(message)) = read.next().await {
+            if message.is_close() {
+                break;
+            }
+        }
+    };
+    match tokio::time::timeout(CLOSE_TIMEOUT, acknowledged).await {
+        Ok(()) => info!("WebSocket closed cleanly"),
+        Err(_) => warn!("Server did not acknowledge close frame"),
+    }
+}
+
+// Final state, logged once the stream has stopped
+#[derive(Debug, Clone, PartialEq)]
+struct BookSummary {
+    updates: usize,
+    bid_levels: usize,
+    ask_levels: usize,
+    best_bid: Option<f64>,
+    best_ask: Option<f64>,
+    spread: Option<f64>,
+}
+
+impl BookSummary {
+    fn new(order_book: &OrderBook, updates: usize) -> Self {
+        let (bids, asks) = order_book.get_depth(usize::MAX);
+        BookSummary {
+            updates,
+            bid_levels: bids.len(),
+            ask_levels: asks.len(),
+            best_bid: order_book.get_best_bid(),
+            best_ask: order_book.get_best_ask(),
+            spread: order_book.spread(),
+        }
+    }
+
+    fn log(&self) {
+        info!(
+            updates = self.updates,
+            bid_levels = self.bid_levels,
+            ask_levels = self.ask_levels,
+            best_bid = ?self.best_bid,
+            best_ask = ?self.best_ask,
+            spread = ?self.spread,
+            "Final order book state"
+        );
+    }
+}
+
+fn write_snapshot(path: &str, snapshot: &BookSnapshot) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer(File::create(path)?, snapshot)?;
     Ok(())
 }
 
+#[cfg(feature = "metrics")]
+fn start_metrics_exporter(addr: &str) -> Result<(), Box<dyn Error>> {
+    telemetry::install(addr.parse()?)?;
+    info!(%addr, "Serving Prometheus metrics on /metrics");
+    Ok(())
+}
+
+#[cfg(not(feature = "metrics"))]
+fn start_metrics_exporter(_addr: &str) -> Result<(), Box<dyn Error>> {
+    Err("built without the `metrics` feature".into())
+}
+
 #[tokio::main]
 async fn main() {
-    match handle_binance_stream().await {
-        Ok(_) => println!("WebSocket client terminated successfully"),
-        Err(e) => eprintln!("WebSocket client error: {e}"),
+    rust_core::logging::init();
+
+    let args: Vec<String> = std::env::args().collect();
+
+    let mut exchange = "binance".to_string();
+    let mut symbol = None;
+    let mut record_path = None;
+    let mut metrics_addr: Option<String> = None;
+    let mut snapshot_out = None;
+    let mut strict = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--exchange" if i + 1 < args.len() => {
+                exchange = args[i + 1].clone();
+                i += 1;
+            }
+            "--symbol" if i + 1 < args.len() => {
+                symbol = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--record" if i + 1 < args.len() => {
+                record_path = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--snapshot-out" if i + 1 < args.len() => {
+                snapshot_out = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--strict" => strict = true,
+            "--metrics-addr" if i + 1 < args.len() => {
+                metrics_addr = Some(args[i + 1].clone());
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let source = match depth_source_for(&exchange, symbol.as_deref()) {
+        Some(source) => source,
+        None => {
+            error!(%exchange, "Unsupported exchange (expected binance, coinbase or kraken)");
+            return;
+        }
+    };
+
+    if let Some(addr) = metrics_addr {
+        if let Err(e) = start_metrics_exporter(&addr) {
+            error!(%addr, error = %e, "Failed to start metrics exporter");
+            return;
+        }
+    }
+
+    let mut recorder = match record_path.as_deref().map(DepthRecorder::create) {
+        Some(Ok(recorder)) => {
+            info!(path = record_path.as_deref(), "Recording applied updates");
+            Some(recorder)
+        }
+        Some(Err(e)) => {
+            error!(error = %e, "Failed to create capture file");
+            return;
+        }
+        None => None,
+    };
+
+    // Venues with checksums need the book at their precisions
+    let (price_scale, quantity_scale) = source
+        .book_scales()
+        .unwrap_or((PRICE_SCALE, QUANTITY_SCALE));
+    let new_book = || {
+        let book = OrderBook::new()
+            .with_price_scale(price_scale)
+            .with_quantity_scale(quantity_scale);
+        if strict {
+            book.with_strict_mode()
+        } else {
+            book
+        }
+    };
+    let order_book = Arc::new(Mutex::new(new_book()));
+
+    // Example consumer: a separate task sampling the live book while the
+    // socket task keeps it up to date
+    let reader_book = Arc::clone(&order_book);
+    let reader = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            interval.tick().await;
+            let (best_bid, best_ask) = top_of_book(&reader_book.lock().unwrap());
+            if let (Some((bid, _)), Some((ask, _))) = (best_bid, best_ask) {
+                info!(target: "reader", best_bid = bid, best_ask = ask, "Sampled top of book");
+            }
+        }
+    });
+
+    let mut update_count = 0;
+    loop {
+        let result = handle_depth_stream(
+            source.as_ref(),
+            Arc::clone(&order_book),
+            recorder.as_mut(),
+            &mut update_count,
+            async {
+                // An error installing the handler also stops the client
+                let _ = tokio::signal::ctrl_c().await;
+            },
+        )
+        .await;
+
+        match result {
+            Ok(StreamExit::ResyncRequired) => {
+                // Drop the corrupted book and reconnect for a fresh stream
+                info!("Resetting local book and reconnecting");
+                // Trade ids keep counting across the reset
+                let mut book = order_book.lock().unwrap();
+                let next_trade_id = book.trade_id_counter();
+                *book = new_book();
+                book.set_trade_id_counter(next_trade_id);
+                // The replayed book is emptied too
+                if let Some(recorder) = recorder.as_mut() {
+                    if let Err(e) = recorder.record_book(&[]) {
+                        error!(error = %e, "Failed to record book reset");
+                    }
+                }
+            }
+            Ok(StreamExit::Closed) | Ok(StreamExit::Interrupted) => {
+                info!("WebSocket client terminated successfully");
+                break;
+            }
+            Err(e) => {
+                error!(error = %e, "WebSocket client error");
+                break;
+            }
+        }
+    }
+
+    reader.abort();
+    BookSummary::new(&order_book.lock().unwrap(), update_count).log();
+
+    // Final book state, for offline comparison with snapshot_diff
+    if let Some(path) = snapshot_out {
+        let snapshot = order_book.lock().unwrap().to_snapshot();
+        match write_snapshot(&path, &snapshot) {
+            Ok(()) => info!(%path, orders = snapshot.orders.len(), "Wrote book snapshot"),
+            Err(e) => error!(%path, error = %e, "Failed to write book snapshot"),
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        match recorder.finish() {
+            Ok(rows) => info!(rows, "Wrote recorded orders"),
+            Err(e) => error!(error = %e, "Failed to flush capture file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_core::depth_source::BinanceDepthSource;
+    use rust_core::market_data::OrderAction;
+
+    fn depth(bids: &[(f64, f64)], asks: &[(f64, f64)]) -> NormalizedDepth {
+        NormalizedDepth {
+            bids: bids.to_vec(),
+            asks: asks.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    // Apply rows the way replay_tool does
+    fn replay(events: &[OrderEvent]) -> OrderBook {
+        let mut book = OrderBook::new();
+        for event in events {
+            let order_id = event.order_id.expect("recorded rows carry ids");
+            match event.action {
+                OrderAction::Add => {
+                    book.try_add_order(
+                        order_id,
+                        event.price.unwrap(),
+                        event.quantity.unwrap(),
+                        event.is_buy_side.unwrap(),
+                    )
+                    .unwrap();
+                }
+                OrderAction::Cancel => {
+                    book.try_cancel_order(order_id).unwrap();
+                }
+                OrderAction::Modify => {
+                    book.try_modify_order(order_id, event.price.unwrap(), event.quantity.unwrap())
+                        .unwrap();
+                }
+            }
+        }
+        book
+    }
+
+    // Binance's parser against a local server
+    struct MockSource {
+        url: String,
+    }
+
+    impl DepthSource for MockSource {
+        fn name(&self) -> &str {
+            "Mock"
+        }
+
+        fn url(&self) -> String {
+            self.url.clone()
+        }
+
+        fn parse(&self, text: &str) -> rust_core::depth_source::Result<NormalizedDepth> {
+            BinanceDepthSource::new("btcusdt").parse(text)
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_the_connection_and_keeps_the_final_book() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Sends two updates, then reports whether the client closed with a
+        // close frame
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            for text in [
+                r#"{"e":"depthUpdate","E":1,"s":"BTCUSDT","U":1,"u":1,"b":[["100.0","1"]],"a":[["101.0","2"]]}"#,
+                r#"{"e":"depthUpdate","E":2,"s":"BTCUSDT","U":2,"u":2,"b":[["100.5","1"]],"a":[]}"#,
+            ] {
+                socket.send(Message::Text(text.to_string())).await.unwrap();
+            }
+            // Keep reading so tungstenite can answer the close frame
+            let mut closed = false;
+            while let Some(Ok(message)) = socket.next().await {
+                closed |= message.is_close();
+            }
+            closed
+        });
+
+        let order_book = Arc::new(Mutex::new(OrderBook::new()));
+        let watched = Arc::clone(&order_book);
+        // Stand-in for Ctrl-C, fired once both updates are in the book
+        let shutdown = async move {
+            while watched.lock().unwrap().get_best_bid() != Some(100.5) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+
+        let mut updates = 0;
+        let exit = tokio::time::timeout(
+            Duration::from_secs(5),
+            handle_depth_stream(
+                &MockSource { url },
+                Arc::clone(&order_book),
+                None,
+                &mut updates,
+                shutdown,
+            ),
+        )
+        .await
+        .expect("stream stopped")
+        .unwrap();
+
+        assert!(matches!(exit, StreamExit::Interrupted));
+        assert!(server.await.unwrap(), "server received a close frame");
+        assert_eq!(
+            BookSummary::new(&order_book.lock().unwrap(), updates),
+            BookSummary {
+                updates: 2,
+                bid_levels: 2,
+                ask_levels: 1,
+                best_bid: Some(100.5),
+                best_ask: Some(101.0),
+                spread: Some(0.5),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reader_task_observes_updates_through_the_shared_book() {
+        let order_book = Arc::new(Mutex::new(OrderBook::new()));
+
+        // Samples the best bid until it reaches the last update's
+        let reader_book = Arc::clone(&order_book);
+        let reader = tokio::spawn(async move {
+            let mut seen: Vec<f64> = Vec::new();
+            loop {
+                let (best_bid, _) = top_of_book(&reader_book.lock().unwrap());
+                if let Some((bid, _)) = best_bid {
+                    if seen.last() != Some(&bid) {
+                        seen.push(bid);
+                    }
+                    if bid == 100.5 {
+                        return seen;
+                    }
+                }
+                tokio::task::yield_now().await;
+            }
+        });
+
+        let stream = [
+            depth(&[(100.0, 1.0)], &[(101.0, 1.0)]),
+            depth(&[(100.2, 1.0)], &[]),
+            depth(&[(100.5, 1.0)], &[]),
+        ];
+        let mut order_id = 1;
+        let mut seeded = false;
+        for update in &stream {
+            {
+                let mut book = order_book.lock().unwrap();
+                let bulk_loaded = !seeded
+                    && seed_levels(&mut book, update, &mut order_id, None::<&mut DepthRecorder>)
+                        .unwrap();
+                seeded = true;
+                if !bulk_loaded {
+                    apply_update(&mut book, update, &mut order_id, None::<&mut DepthRecorder>)
+                        .unwrap();
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+
+        let seen = tokio::time::timeout(Duration::from_secs(1), reader)
+            .await
+            .expect("reader saw the last update")
+            .unwrap();
+        assert_eq!(seen, [100.0, 100.2, 100.5]);
+        assert_eq!(order_book.lock().unwrap().get_best_ask(), Some(101.0));
+    }
+
+    #[test]
+    fn recorded_stream_replays_to_the_live_book() {
+        let stream = [
+            depth(&[(100.0, 1.0), (99.5, 2.0)], &[(101.0, 1.5), (102.0, 3.0)]),
+            // Resize the best bid, drop an ask, add a level on each side
+            depth(&[(100.0, 0.5), (99.0, 4.0)], &[(102.0, 0.0), (101.5, 2.5)]),
+            depth(&[(99.5, 0.0)], &[(101.0, 1.0)]),
+        ];
+
+        let mut live = OrderBook::new();
+        let mut recorder = DepthRecorder::new(csv::Writer::from_writer(Vec::new()));
+        let mut order_id = 1;
+        assert!(seed_levels(&mut live, &stream[0], &mut order_id, Some(&mut recorder)).unwrap());
+        for update in &stream[1..] {
+            apply_update(&mut live, update, &mut order_id, Some(&mut recorder)).unwrap();
+        }
+
+        let csv = recorder.writer.into_inner().unwrap();
+        let events: Vec<OrderEvent> = csv::Reader::from_reader(csv.as_slice())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let actions: Vec<(OrderAction, u32)> = events
+            .iter()
+            .map(|event| (event.action, event.order_id.unwrap()))
+            .collect();
+        // Levels keep the id they were added with
+        assert_eq!(
+            actions,
+            [
+                (OrderAction::Add, 1),
+                (OrderAction::Add, 2),
+                (OrderAction::Add, 3),
+                (OrderAction::Add, 4),
+                (OrderAction::Modify, 1),
+                (OrderAction::Add, 5),
+                (OrderAction::Cancel, 4),
+                (OrderAction::Add, 6),
+                (OrderAction::Cancel, 2),
+                (OrderAction::Modify, 3),
+            ]
+        );
+
+        let replayed = replay(&events);
+        assert_eq!(replayed.get_depth(usize::MAX), live.get_depth(usize::MAX));
+        assert_eq!(
+            live.get_depth(usize::MAX),
+            (
+                vec![(100.0, Qty(50)), (99.0, Qty(400))],
+                vec![(101.0, Qty(100)), (101.5, Qty(250))],
+            )
+        );
+    }
+
+    #[test]
+    fn recording_a_new_book_cancels_levels_it_drops() {
+        let mut recorder = DepthRecorder::new(csv::Writer::from_writer(Vec::new()));
+        recorder
+            .record_book(&[(100.0, Qty(100), true), (101.0, Qty(100), false)])
+            .unwrap();
+        recorder.record_book(&[(100.0, Qty(100), true)]).unwrap();
+        recorder.record_book(&[]).unwrap();
+
+        let csv = recorder.writer.into_inner().unwrap();
+        let events: Vec<OrderEvent> = csv::Reader::from_reader(csv.as_slice())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[2].action, OrderAction::Cancel);
+        assert_eq!(events[2].order_id, Some(2));
+        assert_eq!(events[3].order_id, Some(1));
+        assert_eq!(replay(&events).get_depth(usize::MAX), (vec![], vec![]));
     }
 }
@@ -0,0 +1,207 @@
+// Structured comparison of two book snapshots, for reconciling a locally
+// rebuilt book against a reference copy. Prices are compared at the
+// snapshots' own price scale, so sub-cent levels of a 1e8 book stay apart.
+use crate::error::{Error, Result};
+use crate::money::{key_to_price, price_to_key, Qty};
+use crate::order_book::{BookSnapshot, Order};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LevelDiff {
+    pub is_buy_side: bool,
+    pub price: f64,
+    pub left_quantity: Qty,
+    pub right_quantity: Qty,
+    // right - left
+    pub delta: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderChange {
+    pub order_id: u32,
+    pub left: Order,
+    pub right: Order,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiff {
+    pub levels: Vec<LevelDiff>,
+    pub only_in_left: Vec<Order>,
+    pub only_in_right: Vec<Order>,
+    pub changed_orders: Vec<OrderChange>,
+    pub next_trade_id: Option<(u32, u32)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+            && self.only_in_left.is_empty()
+            && self.only_in_right.is_empty()
+            && self.changed_orders.is_empty()
+            && self.next_trade_id.is_none()
+    }
+}
+
+// (is_buy_side, price key) -> total quantity
+fn level_totals(snapshot: &BookSnapshot) -> BTreeMap<(bool, u64), Qty> {
+    let mut totals = BTreeMap::new();
+    for order in &snapshot.orders {
+        *totals
+            .entry((
+                order.is_buy_side,
+                price_to_key(order.price, snapshot.price_scale),
+            ))
+            .or_default() += order.quantity;
+    }
+    totals
+}
+
+fn same_order(left: &Order, right: &Order, price_scale: u64) -> bool {
+    left.is_buy_side == right.is_buy_side
+        && left.quantity == right.quantity
+        && price_to_key(left.price, price_scale) == price_to_key(right.price, price_scale)
+}
+
+// Snapshots at different price or quantity scales are refused: their keys
+// and lots don't line up
+pub fn diff_snapshots(left: &BookSnapshot, right: &BookSnapshot) -> Result<SnapshotDiff> {
+    if left.price_scale != right.price_scale || left.quantity_scale != right.quantity_scale {
+        return Err(Error::ScaleMismatch {
+            left: (left.price_scale, left.quantity_scale),
+            right: (right.price_scale, right.quantity_scale),
+        });
+    }
+    let price_scale = left.price_scale;
+    let mut diff = SnapshotDiff::default();
+
+    // Price levels, asks then bids, each in ascending price
+    let left_levels = level_totals(left);
+    let right_levels = level_totals(right);
+    let mut keys: Vec<_> = left_levels.keys().chain(right_levels.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for &(is_buy_side, key) in keys {
+        let left_quantity = left_levels
+            .get(&(is_buy_side, key))
+            .copied()
+            .unwrap_or_default();
+        let right_quantity = right_levels
+            .get(&(is_buy_side, key))
+            .copied()
+            .unwrap_or_default();
+        if left_quantity != right_quantity {
+            diff.levels.push(LevelDiff {
+                is_buy_side,
+                price: key_to_price(key, price_scale),
+                left_quantity,
+                right_quantity,
+                delta: right_quantity.lots() as i64 - left_quantity.lots() as i64,
+            });
+        }
+    }
+
+    // Individual orders, matched by id
+    let right_orders: HashMap<u32, &Order> = right.orders.iter().map(|o| (o.order_id, o)).collect();
+    let left_orders: HashMap<u32, &Order> = left.orders.iter().map(|o| (o.order_id, o)).collect();
+
+    for order in &left.orders {
+        match right_orders.get(&order.order_id) {
+            None => diff.only_in_left.push(order.clone()),
+            Some(other) if !same_order(order, other, price_scale) => {
+                diff.changed_orders.push(OrderChange {
+                    order_id: order.order_id,
+                    left: order.clone(),
+                    right: (*other).clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff.only_in_right = right
+        .orders
+        .iter()
+        .filter(|o| !left_orders.contains_key(&o.order_id))
+        .cloned()
+        .collect();
+
+    if left.next_trade_id != right.next_trade_id {
+        diff.next_trade_id = Some((left.next_trade_id, right.next_trade_id));
+    }
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_book::OrderBook;
+
+    #[test]
+    fn one_mutated_level_shows_up_as_one_level_diff() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 99.0, Qty(10), true);
+        book.add_order(2, 100.0, Qty(20), true);
+        book.add_order(3, 101.0, Qty(30), false);
+        let left = book.to_snapshot();
+
+        // Swap the 100.00 bid for a larger one under a new id
+        book.cancel_order(2);
+        book.add_order(4, 100.0, Qty(25), true);
+        let right = book.to_snapshot();
+
+        let diff = diff_snapshots(&left, &right).unwrap();
+        assert_eq!(
+            diff.levels,
+            [LevelDiff {
+                is_buy_side: true,
+                price: 100.0,
+                left_quantity: Qty(20),
+                right_quantity: Qty(25),
+                delta: 5,
+            }]
+        );
+        let ids = |orders: &[Order]| {
+            orders
+                .iter()
+                .map(|order| order.order_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&diff.only_in_left), [2]);
+        assert_eq!(ids(&diff.only_in_right), [4]);
+        assert!(diff.changed_orders.is_empty());
+        assert_eq!(diff.next_trade_id, None);
+
+        assert!(diff_snapshots(&left, &left).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sub_cent_levels_stay_apart_at_fine_price_scales() {
+        let mut left = OrderBook::new().with_price_scale(1_000);
+        left.add_order(1, 100.001, Qty(5), true);
+        let mut right = OrderBook::new().with_price_scale(1_000);
+        right.add_order(1, 100.009, Qty(5), true);
+
+        let diff = diff_snapshots(&left.to_snapshot(), &right.to_snapshot()).unwrap();
+        let prices: Vec<f64> = diff.levels.iter().map(|level| level.price).collect();
+        assert_eq!(prices, [100.001, 100.009]);
+        assert_eq!(diff.changed_orders.len(), 1);
+    }
+
+    #[test]
+    fn snapshots_at_different_scales_are_refused() {
+        let left = OrderBook::new().to_snapshot();
+        let right = OrderBook::new().with_price_scale(1_000).to_snapshot();
+        assert!(matches!(
+            diff_snapshots(&left, &right),
+            Err(Error::ScaleMismatch {
+                left: (100, 100),
+                right: (1_000, 100),
+            })
+        ));
+
+        let finer_lots = OrderBook::new().with_quantity_scale(1_000).to_snapshot();
+        assert!(diff_snapshots(&left, &finer_lots).is_err());
+    }
+}
@@ -0,0 +1,580 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+// Exchange-agnostic depth update: (price, quantity) pairs per side.
+// A quantity of zero means the level was removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NormalizedDepth {
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+    // Update-id range covered by this event, for venues that provide one
+    pub first_update_id: Option<u64>,
+    pub final_update_id: Option<u64>,
+    // Venue checksum of the book after this event, as OrderBook::checksum
+    // computes it at DepthSource::book_scales; None when the venue does not
+    // send one
+    pub checksum: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    InSequence,
+    // Already covered by a previously applied event - safe to drop
+    Stale,
+    // One or more events were missed - the local book must be resynced
+    Gap { expected: u64, received: u64 },
+}
+
+// Tracks update-id continuity: each event's first id must equal the
+// previous event's final id + 1. A gap means reconnecting, which starts a
+// fresh tracker.
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    last_update_id: Option<u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        SequenceTracker::default()
+    }
+
+    // Check an event and, if it may be applied, record its final id
+    pub fn check(&mut self, depth: &NormalizedDepth) -> SequenceCheck {
+        let (first, last) = match (depth.first_update_id, depth.final_update_id) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return SequenceCheck::InSequence, // Venue has no sequencing
+        };
+
+        if let Some(previous) = self.last_update_id {
+            if last <= previous {
+                return SequenceCheck::Stale;
+            }
+            if first != previous + 1 {
+                return SequenceCheck::Gap {
+                    expected: previous + 1,
+                    received: first,
+                };
+            }
+        }
+
+        self.last_update_id = Some(last);
+        SequenceCheck::InSequence
+    }
+}
+
+#[derive(Debug)]
+pub enum DepthParseError {
+    Json(serde_json::Error),
+    UnexpectedMessage(String),
+    InvalidLevel(String),
+}
+
+impl fmt::Display for DepthParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepthParseError::Json(e) => write!(f, "invalid JSON: {e}"),
+            DepthParseError::UnexpectedMessage(kind) => {
+                write!(f, "not a depth message: {kind}")
+            }
+            DepthParseError::InvalidLevel(level) => write!(f, "invalid price level: {level}"),
+        }
+    }
+}
+
+impl Error for DepthParseError {}
+
+impl From<serde_json::Error> for DepthParseError {
+    fn from(e: serde_json::Error) -> Self {
+        DepthParseError::Json(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, DepthParseError>;
+
+// Trait for exchange depth feeds - each venue knows its endpoint, how to
+// subscribe and how to turn a raw text frame into a NormalizedDepth
+pub trait DepthSource: Send + Sync {
+    fn name(&self) -> &str;
+    fn url(&self) -> String;
+    fn subscribe_message(&self) -> Option<String> {
+        None
+    }
+    // (price scale, quantity scale) the local book must use for the venue's
+    // checksums to match; None keeps the book's defaults
+    fn book_scales(&self) -> Option<(u64, u64)> {
+        None
+    }
+    fn parse(&self, text: &str) -> Result<NormalizedDepth>;
+}
+
+// Select a parser by exchange name, as passed on the command line
+pub fn depth_source_for(exchange: &str, symbol: Option<&str>) -> Option<Box<dyn DepthSource>> {
+    match exchange.to_ascii_lowercase().as_str() {
+        "binance" => Some(Box::new(BinanceDepthSource::new(
+            symbol.unwrap_or("btcusdt"),
+        ))),
+        "coinbase" => Some(Box::new(CoinbaseDepthSource::new(
+            symbol.unwrap_or("BTC-USD"),
+        ))),
+        "kraken" => Some(Box::new(KrakenDepthSource::new(
+            symbol.unwrap_or("BTC/USD"),
+        ))),
+        _ => None,
+    }
+}
+
+fn parse_level(price: &str, quantity: &str) -> Result<(f64, f64)> {
+    match (price.parse::<f64>(), quantity.parse::<f64>()) {
+        (Ok(p), Ok(q)) => Ok((p, q)),
+        _ => Err(DepthParseError::InvalidLevel(format!("{price},{quantity}"))),
+    }
+}
+
+fn parse_string_levels(levels: &[Vec<String>]) -> Result<Vec<(f64, f64)>> {
+    levels
+        .iter()
+        .map(|level| {
+            if level.len() >= 2 {
+                parse_level(&level[0], &level[1])
+            } else {
+                Err(DepthParseError::InvalidLevel(level.join(",")))
+            }
+        })
+        .collect()
+}
+
+// Binance diff depth stream (<symbol>@depth)
+#[derive(Debug, Deserialize)]
+struct BinanceDepthUpdate {
+    #[serde(rename = "e")]
+    #[allow(dead_code)]
+    event_type: String,
+    #[serde(rename = "E")]
+    #[allow(dead_code)]
+    event_time: u64,
+    #[serde(rename = "s")]
+    #[allow(dead_code)]
+    symbol: String,
+    #[serde(rename = "U")]
+    first_update_id: u64,
+    #[serde(rename = "u")]
+    final_update_id: u64,
+    #[serde(rename = "b")]
+    bids: Vec<Vec<String>>,
+    #[serde(rename = "a")]
+    asks: Vec<Vec<String>>,
+}
+
+pub struct BinanceDepthSource {
+    symbol: String,
+}
+
+impl BinanceDepthSource {
+    pub fn new(symbol: &str) -> Self {
+        BinanceDepthSource {
+            symbol: symbol.to_ascii_lowercase(),
+        }
+    }
+}
+
+impl DepthSource for BinanceDepthSource {
+    fn name(&self) -> &str {
+        "Binance"
+    }
+
+    fn url(&self) -> String {
+        format!("wss://stream.binance.com:9443/ws/{}@depth", self.symbol)
+    }
+
+    fn parse(&self, text: &str) -> Result<NormalizedDepth> {
+        let update: BinanceDepthUpdate = serde_json::from_str(text)?;
+        Ok(NormalizedDepth {
+            bids: parse_string_levels(&update.bids)?,
+            asks: parse_string_levels(&update.asks)?,
+            first_update_id: Some(update.first_update_id),
+            final_update_id: Some(update.final_update_id),
+            checksum: None,
+        })
+    }
+}
+
+// Coinbase Exchange `level2` channel: an initial "snapshot" followed by
+// "l2update" messages carrying [side, price, size] changes
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CoinbaseMessage {
+    Snapshot {
+        bids: Vec<Vec<String>>,
+        asks: Vec<Vec<String>>,
+    },
+    L2update {
+        changes: Vec<Vec<String>>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+pub struct CoinbaseDepthSource {
+    product_id: String,
+}
+
+impl CoinbaseDepthSource {
+    pub fn new(product_id: &str) -> Self {
+        CoinbaseDepthSource {
+            product_id: product_id.to_ascii_uppercase(),
+        }
+    }
+}
+
+impl DepthSource for CoinbaseDepthSource {
+    fn name(&self) -> &str {
+        "Coinbase"
+    }
+
+    fn url(&self) -> String {
+        "wss://ws-feed.exchange.coinbase.com".to_string()
+    }
+
+    fn subscribe_message(&self) -> Option<String> {
+        Some(
+            serde_json::json!({
+                "type": "subscribe",
+                "product_ids": [self.product_id],
+                "channels": ["level2"],
+            })
+            .to_string(),
+        )
+    }
+
+    fn parse(&self, text: &str) -> Result<NormalizedDepth> {
+        match serde_json::from_str::<CoinbaseMessage>(text)? {
+            CoinbaseMessage::Snapshot { bids, asks } => Ok(NormalizedDepth {
+                bids: parse_string_levels(&bids)?,
+                asks: parse_string_levels(&asks)?,
+                ..Default::default()
+            }),
+            CoinbaseMessage::L2update { changes } => {
+                let mut depth = NormalizedDepth::default();
+                for change in &changes {
+                    if change.len() < 3 {
+                        return Err(DepthParseError::InvalidLevel(change.join(",")));
+                    }
+                    let level = parse_level(&change[1], &change[2])?;
+                    match change[0].as_str() {
+                        "buy" => depth.bids.push(level),
+                        "sell" => depth.asks.push(level),
+                        side => return Err(DepthParseError::InvalidLevel(side.to_string())),
+                    }
+                }
+                Ok(depth)
+            }
+            CoinbaseMessage::Other => Err(DepthParseError::UnexpectedMessage(text.to_string())),
+        }
+    }
+}
+
+// Kraken v2 `book` channel: snapshot and update messages share one layout,
+// with numeric price/qty objects per level
+#[derive(Debug, Deserialize)]
+struct KrakenLevel {
+    price: f64,
+    qty: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenBookData {
+    #[serde(default)]
+    bids: Vec<KrakenLevel>,
+    #[serde(default)]
+    asks: Vec<KrakenLevel>,
+    // CRC32 of the top 10 levels per side after this message
+    #[serde(default)]
+    checksum: Option<u32>,
+}
+
+pub struct KrakenDepthSource {
+    symbol: String,
+    // Decimal places of the pair's prices and quantities, which the
+    // checksum is computed at
+    price_precision: u32,
+    qty_precision: u32,
+}
+
+impl KrakenDepthSource {
+    // Precisions default to BTC/USD's; see with_precision for other pairs
+    pub fn new(symbol: &str) -> Self {
+        KrakenDepthSource {
+            symbol: symbol.to_ascii_uppercase(),
+            price_precision: 1,
+            qty_precision: 8,
+        }
+    }
+
+    // The pair's price_precision and qty_precision, as Kraken's instrument
+    // channel reports them
+    pub fn with_precision(mut self, price_precision: u32, qty_precision: u32) -> Self {
+        self.price_precision = price_precision;
+        self.qty_precision = qty_precision;
+        self
+    }
+}
+
+impl DepthSource for KrakenDepthSource {
+    fn name(&self) -> &str {
+        "Kraken"
+    }
+
+    fn url(&self) -> String {
+        "wss://ws.kraken.com/v2".to_string()
+    }
+
+    fn book_scales(&self) -> Option<(u64, u64)> {
+        Some((
+            10u64.pow(self.price_precision),
+            10u64.pow(self.qty_precision),
+        ))
+    }
+
+    fn subscribe_message(&self) -> Option<String> {
+        Some(
+            serde_json::json!({
+                "method": "subscribe",
+                "params": {
+                    "channel": "book",
+                    "symbol": [self.symbol],
+                },
+            })
+            .to_string(),
+        )
+    }
+
+    fn parse(&self, text: &str) -> Result<NormalizedDepth> {
+        let message: Value = serde_json::from_str(text)?;
+        if message.get("channel").and_then(Value::as_str) != Some("book") {
+            return Err(DepthParseError::UnexpectedMessage(text.to_string()));
+        }
+
+        let data: Vec<KrakenBookData> = match message.get("data") {
+            Some(data) => serde_json::from_value(data.clone())?,
+            None => return Err(DepthParseError::UnexpectedMessage(text.to_string())),
+        };
+
+        let mut depth = NormalizedDepth::default();
+        for book in data {
+            depth
+                .bids
+                .extend(book.bids.iter().map(|level| (level.price, level.qty)));
+            depth
+                .asks
+                .extend(book.asks.iter().map(|level| (level.price, level.qty)));
+            depth.checksum = book.checksum.or(depth.checksum);
+        }
+        Ok(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binance_diff_depth() {
+        let depth = BinanceDepthSource::new("BTCUSDT")
+            .parse(
+                r#"{"e":"depthUpdate","E":1700000000000,"s":"BTCUSDT","U":157,"u":160,
+                "b":[["45000.10","0.500"],["44999.00","0.000"]],"a":[["45001.20","1.25"]]}"#,
+            )
+            .unwrap();
+
+        assert_eq!(depth.bids, [(45000.10, 0.5), (44999.0, 0.0)]);
+        assert_eq!(depth.asks, [(45001.20, 1.25)]);
+        assert_eq!(depth.first_update_id, Some(157));
+        assert_eq!(depth.final_update_id, Some(160));
+        assert_eq!(depth.checksum, None);
+    }
+
+    #[test]
+    fn binance_rejects_a_bad_level() {
+        let result = BinanceDepthSource::new("btcusdt").parse(
+            r#"{"e":"depthUpdate","E":1,"s":"BTCUSDT","U":1,"u":1,"b":[["abc","1"]],"a":[]}"#,
+        );
+        assert!(matches!(result, Err(DepthParseError::InvalidLevel(level)) if level == "abc,1"));
+    }
+
+    #[test]
+    fn coinbase_snapshot_and_update() {
+        let source = CoinbaseDepthSource::new("btc-usd");
+        let snapshot = source
+            .parse(
+                r#"{"type":"snapshot","product_id":"BTC-USD",
+                "bids":[["45000.00","2.0"]],"asks":[["45001.00","1.5"],["45002.00","3"]]}"#,
+            )
+            .unwrap();
+        assert_eq!(snapshot.bids, [(45000.0, 2.0)]);
+        assert_eq!(snapshot.asks, [(45001.0, 1.5), (45002.0, 3.0)]);
+
+        let update = source
+            .parse(
+                r#"{"type":"l2update","product_id":"BTC-USD",
+                "changes":[["buy","45000.50","0.7"],["sell","45001.00","0"]]}"#,
+            )
+            .unwrap();
+        assert_eq!(update.bids, [(45000.5, 0.7)]);
+        assert_eq!(update.asks, [(45001.0, 0.0)]);
+        assert_eq!(update.first_update_id, None);
+    }
+
+    #[test]
+    fn coinbase_rejects_other_messages() {
+        let source = CoinbaseDepthSource::new("BTC-USD");
+        assert!(matches!(
+            source.parse(r#"{"type":"subscriptions","channels":[]}"#),
+            Err(DepthParseError::UnexpectedMessage(_))
+        ));
+        assert!(matches!(
+            source.parse(r#"{"type":"l2update","changes":[["hold","1","1"]]}"#),
+            Err(DepthParseError::InvalidLevel(side)) if side == "hold"
+        ));
+        assert!(matches!(
+            source.parse("not json"),
+            Err(DepthParseError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn kraken_snapshot() {
+        let depth = KrakenDepthSource::new("BTC/USD")
+            .parse(
+                r#"{"channel":"book","type":"snapshot","data":[{"symbol":"BTC/USD",
+                "bids":[{"price":45283.5,"qty":0.001},{"price":45283.4,"qty":1.54582015}],
+                "asks":[{"price":45285.2,"qty":0.001}]}]}"#,
+            )
+            .unwrap();
+
+        assert_eq!(depth.bids, [(45283.5, 0.001), (45283.4, 1.54582015)]);
+        assert_eq!(depth.asks, [(45285.2, 0.001)]);
+        assert_eq!(depth.checksum, None);
+    }
+
+    #[test]
+    fn kraken_rejects_other_channels() {
+        let source = KrakenDepthSource::new("BTC/USD");
+        assert!(matches!(
+            source.parse(r#"{"channel":"heartbeat"}"#),
+            Err(DepthParseError::UnexpectedMessage(_))
+        ));
+        assert!(matches!(
+            source.parse(r#"{"channel":"book","type":"update"}"#),
+            Err(DepthParseError::UnexpectedMessage(_))
+        ));
+    }
+
+    #[test]
+    fn sources_are_chosen_by_exchange_name() {
+        assert_eq!(depth_source_for("Binance", None).unwrap().name(), "Binance");
+        assert_eq!(
+            depth_source_for("COINBASE", None).unwrap().name(),
+            "Coinbase"
+        );
+        let kraken = depth_source_for("kraken", Some("eth/usd")).unwrap();
+        assert!(kraken.subscribe_message().unwrap().contains("ETH/USD"));
+        assert!(depth_source_for("ftx", None).is_none());
+    }
+
+    #[test]
+    fn kraken_reads_the_book_checksum() {
+        let source = KrakenDepthSource::new("btc/usd");
+        let depth = source
+            .parse(
+                r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD",
+                "bids":[{"price":45283.5,"qty":0.001}],"asks":[],
+                "checksum":746069961,"timestamp":"2023-10-06T17:35:55.440295Z"}]}"#,
+            )
+            .unwrap();
+
+        assert_eq!(depth.bids, [(45283.5, 0.001)]);
+        assert_eq!(depth.checksum, Some(746_069_961));
+        assert_eq!(source.book_scales(), Some((10, 100_000_000)));
+        assert_eq!(
+            source.with_precision(2, 5).book_scales(),
+            Some((100, 100_000))
+        );
+    }
+
+    fn sequenced(first: u64, last: u64) -> NormalizedDepth {
+        NormalizedDepth {
+            first_update_id: Some(first),
+            final_update_id: Some(last),
+            ..NormalizedDepth::default()
+        }
+    }
+
+    #[test]
+    fn sequence_accepts_contiguous_events() {
+        let mut tracker = SequenceTracker::new();
+        // Any first event is accepted
+        assert_eq!(
+            tracker.check(&sequenced(157, 160)),
+            SequenceCheck::InSequence
+        );
+        assert_eq!(
+            tracker.check(&sequenced(161, 161)),
+            SequenceCheck::InSequence
+        );
+        assert_eq!(
+            tracker.check(&sequenced(162, 170)),
+            SequenceCheck::InSequence
+        );
+    }
+
+    #[test]
+    fn sequence_reports_a_gap_without_advancing() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(&sequenced(157, 160));
+        assert_eq!(
+            tracker.check(&sequenced(163, 165)),
+            SequenceCheck::Gap {
+                expected: 161,
+                received: 163,
+            }
+        );
+        // The missed event still fits where it belongs
+        assert_eq!(
+            tracker.check(&sequenced(161, 162)),
+            SequenceCheck::InSequence
+        );
+    }
+
+    #[test]
+    fn sequence_drops_events_already_covered() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(&sequenced(157, 160));
+        assert_eq!(tracker.check(&sequenced(150, 156)), SequenceCheck::Stale);
+        assert_eq!(tracker.check(&sequenced(158, 160)), SequenceCheck::Stale);
+        // Overlapping the last event but reaching past it is a gap
+        assert_eq!(
+            tracker.check(&sequenced(159, 163)),
+            SequenceCheck::Gap {
+                expected: 161,
+                received: 159,
+            }
+        );
+    }
+
+    #[test]
+    fn unsequenced_events_always_pass() {
+        let mut tracker = SequenceTracker::new();
+        let unsequenced = NormalizedDepth::default();
+        assert_eq!(tracker.check(&unsequenced), SequenceCheck::InSequence);
+
+        tracker.check(&sequenced(157, 160));
+        assert_eq!(tracker.check(&unsequenced), SequenceCheck::InSequence);
+        // Nor do they move the expected id on
+        assert_eq!(
+            tracker.check(&sequenced(161, 161)),
+            SequenceCheck::InSequence
+        );
+    }
+}
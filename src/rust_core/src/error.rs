@@ -0,0 +1,206 @@
+use crate::money::Qty;
+use thiserror::Error;
+
+// Crate-wide error type for fallible library calls
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("missing field `{0}`")]
+    MissingField(&'static str),
+
+    #[error("invalid value `{value}` for field `{field}`")]
+    Parse { field: &'static str, value: String },
+
+    #[error("invalid price: {0}")]
+    InvalidPrice(f64),
+
+    #[error("price {price} has no tick at price scale {scale}: below one tick or too large")]
+    UnrepresentablePrice { price: f64, scale: u64 },
+
+    #[error("invalid quantity: {0}")]
+    InvalidQuantity(Qty),
+
+    #[error("order id {0} is already resting")]
+    DuplicateOrderId(u32),
+
+    #[error("ClOrdID `{0}` is already in use")]
+    DuplicateClOrdId(String),
+
+    #[error("order id {0} is not resting")]
+    OrderNotFound(u32),
+
+    #[error("crossed levels: best bid {best_bid} is at or above best ask {best_ask}")]
+    CrossedLevels { best_bid: f64, best_ask: f64 },
+
+    #[error("{} at {price} would lock or cross the book", if *is_buy_side { "bid" } else { "ask" })]
+    WouldCross { price: f64, is_buy_side: bool },
+
+    #[error("halted: trade at {price} is outside the price band around {reference}")]
+    Halted { price: f64, reference: f64 },
+
+    #[error("rate limited: more than {max_per_sec} orders per second")]
+    RateLimited { max_per_sec: u32 },
+
+    #[error("exchange name `{0}` belongs to a built-in venue")]
+    ReservedExchangeName(String),
+
+    #[error("no more custom exchange ids: at most {0} can be registered")]
+    TooManyExchanges(usize),
+
+    #[error("snapshot scales differ: (price, quantity) {left:?} vs {right:?}")]
+    ScaleMismatch { left: (u64, u64), right: (u64, u64) },
+
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::{parse_timestamp, MarketTick};
+    use crate::market_maker::MarketMakerParameters;
+    use crate::order_book::OrderBook;
+    use crate::smart_order_router::ExchangeID;
+    use std::time::Duration;
+
+    #[test]
+    fn io_csv_and_json_errors_convert() {
+        let io = Error::from(std::io::Error::new(std::io::ErrorKind::NotFound, "gone"));
+        assert_eq!(io.to_string(), "I/O error: gone");
+
+        let record = csv::StringRecord::from(vec!["not a timestamp"]);
+        assert!(matches!(
+            MarketTick::from_record(&record),
+            Err(Error::Csv(_))
+        ));
+
+        let json = Error::from(serde_json::from_str::<u32>("{").unwrap_err());
+        assert!(matches!(json, Error::Json(_)));
+    }
+
+    #[test]
+    fn snapshot_scale_mismatch_names_both_scales() {
+        let mismatch = Error::ScaleMismatch {
+            left: (100, 100),
+            right: (100_000_000, 100),
+        };
+        assert_eq!(
+            mismatch.to_string(),
+            "snapshot scales differ: (price, quantity) (100, 100) vs (100000000, 100)"
+        );
+    }
+
+    #[test]
+    fn field_errors_name_the_field() {
+        assert_eq!(
+            Error::MissingField("price").to_string(),
+            "missing field `price`"
+        );
+        match parse_timestamp("yesterday") {
+            Err(Error::Parse { field, value }) => {
+                assert_eq!(field, "timestamp");
+                assert_eq!(value, "yesterday");
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn invalid_orders_are_refused() {
+        let mut book = OrderBook::new();
+        assert!(matches!(
+            book.try_add_order(1, -1.0, Qty(1), true),
+            Err(Error::InvalidPrice(price)) if price == -1.0
+        ));
+        // Below one cent at the default price scale
+        assert!(matches!(
+            book.try_add_order(1, 0.001, Qty(1), true),
+            Err(Error::UnrepresentablePrice { scale: 100, .. })
+        ));
+        assert!(matches!(
+            book.try_add_order(1, 100.0, Qty::ZERO, true),
+            Err(Error::InvalidQuantity(Qty::ZERO))
+        ));
+
+        book.try_add_order(1, 100.0, Qty(1), true).unwrap();
+        assert!(matches!(
+            book.try_add_order(1, 99.0, Qty(1), true),
+            Err(Error::DuplicateOrderId(1))
+        ));
+        assert!(matches!(
+            book.try_cancel_order(2),
+            Err(Error::OrderNotFound(2))
+        ));
+    }
+
+    #[test]
+    fn crossing_orders_and_levels_are_refused() {
+        assert!(matches!(
+            OrderBook::from_levels(&[(101.0, Qty(1))], &[(100.0, Qty(1))]),
+            Err(Error::CrossedLevels { best_bid, best_ask }) if best_bid == 101.0 && best_ask == 100.0
+        ));
+
+        let mut strict = OrderBook::new().with_strict_mode();
+        strict.try_add_order(1, 100.0, Qty(1), false).unwrap();
+        let crossing = strict.try_add_order(2, 100.0, Qty(1), true).unwrap_err();
+        assert!(matches!(
+            crossing,
+            Error::WouldCross {
+                is_buy_side: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            crossing.to_string(),
+            "bid at 100 would lock or cross the book"
+        );
+    }
+
+    #[test]
+    fn halts_and_rate_limits_are_refused() {
+        let mut banded = OrderBook::new();
+        banded.try_add_order(1, 110.0, Qty(1), false).unwrap();
+        banded.set_price_band(100.0, 5.0);
+        assert!(matches!(
+            banded.try_add_order(2, 110.0, Qty(1), true),
+            Err(Error::Halted { price, reference }) if price == 110.0 && reference == 100.0
+        ));
+
+        let mut limited = OrderBook::new().with_rate_limit(1);
+        limited
+            .try_add_order_at(Duration::ZERO, 1, 100.0, Qty(1), true)
+            .unwrap();
+        assert!(matches!(
+            limited.try_add_order_at(Duration::ZERO, 2, 99.0, Qty(1), true),
+            Err(Error::RateLimited { max_per_sec: 1 })
+        ));
+    }
+
+    #[test]
+    fn configuration_errors_are_reported() {
+        let params = MarketMakerParameters::builder()
+            .min_spread_bps(20.0)
+            .max_spread_bps(10.0)
+            .build();
+        assert!(matches!(params, Err(Error::InvalidConfig(_))));
+
+        assert!(matches!(
+            ExchangeID::custom("Kraken"),
+            Err(Error::ReservedExchangeName(name)) if name == "Kraken"
+        ));
+        assert_eq!(
+            Error::TooManyExchanges(65_536).to_string(),
+            "no more custom exchange ids: at most 65536 can be registered"
+        );
+    }
+}
@@ -0,0 +1,363 @@
+// Schedules that work a parent order over time on top of the router. The
+// router only prices and splits each child order; what it allocates is
+// taken as filled, and later children are sized on what is still left.
+use crate::money::Qty;
+use crate::smart_order_router::{SmartOrderRouter, SplitOrder};
+use std::time::{Duration, Instant};
+
+// Time-weighted execution: `slices` child orders, one due every `interval`.
+// Slice k brings the filled quantity up to k * (total / slices), so when
+// every slice fills they are all total / slices lots and the last one
+// absorbs the rounding remainder, and a short fill is made up by the next
+// slice. Slice k is due `k * interval` after creation, so a zero interval
+// makes every slice due at once. A poll after the schedule's end
+// (`slices * interval`) sends everything still owed as the final slice.
+#[derive(Debug, Clone)]
+pub struct TwapExecution {
+    order_id: u32,
+    price: f64, // Limit price passed to the router for every slice
+    total_quantity: Qty,
+    is_buy_side: bool,
+    slices: u32,
+    interval: Duration,
+    slices_sent: u32,
+    filled: Qty,
+    origin: Instant,
+}
+
+impl TwapExecution {
+    pub fn new(
+        order_id: u32,
+        price: f64,
+        total_quantity: Qty,
+        is_buy_side: bool,
+        slices: u32,
+        interval: Duration,
+    ) -> Self {
+        TwapExecution {
+            order_id,
+            price,
+            total_quantity,
+            is_buy_side,
+            slices: slices.max(1),
+            interval,
+            slices_sent: 0,
+            filled: Qty::ZERO,
+            origin: Instant::now(),
+        }
+    }
+
+    pub fn filled_quantity(&self) -> Qty {
+        self.filled
+    }
+
+    pub fn remaining_quantity(&self) -> Qty {
+        self.total_quantity.saturating_sub(self.filled)
+    }
+
+    pub fn slices_sent(&self) -> u32 {
+        self.slices_sent
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.filled >= self.total_quantity
+    }
+
+    // Whether no more slices will be issued: the parent is filled or every
+    // slice has been sent
+    pub fn is_finished(&self) -> bool {
+        self.is_complete() || self.slices_sent >= self.slices
+    }
+
+    // The next child quantity if one is due now against the wall clock
+    pub fn next_slice(&mut self) -> Option<Qty> {
+        let now = self.origin.elapsed();
+        self.next_slice_at(now)
+    }
+
+    // The next child quantity if one is due at an explicit clock reading,
+    // for simulated time. None when the schedule is finished or the next
+    // slice isn't due yet.
+    pub fn next_slice_at(&mut self, now: Duration) -> Option<Qty> {
+        if self.is_finished() || now < self.interval * self.slices_sent {
+            return None;
+        }
+
+        // Late polls don't drop slices: past the end the rest go together
+        let past_end = !self.interval.is_zero() && now >= self.interval * self.slices;
+        self.slices_sent = if past_end {
+            self.slices
+        } else {
+            self.slices_sent + 1
+        };
+        if self.slices_sent == self.slices {
+            return Some(self.remaining_quantity());
+        }
+
+        let per_slice = self.total_quantity.lots() / u64::from(self.slices);
+        let target = Qty(per_slice * u64::from(self.slices_sent));
+        Some(target.saturating_sub(self.filled))
+    }
+
+    // Report quantity filled outside execute_slice
+    pub fn record_fill(&mut self, quantity: Qty) {
+        self.filled = (self.filled + quantity).min(self.total_quantity);
+    }
+
+    // Route the next slice, if one is due now, across the router's venues
+    pub fn execute_slice(&mut self, router: &SmartOrderRouter) -> Option<Vec<SplitOrder>> {
+        let now = self.origin.elapsed();
+        self.execute_slice_at(router, now)
+    }
+
+    pub fn execute_slice_at(
+        &mut self,
+        router: &SmartOrderRouter,
+        now: Duration,
+    ) -> Option<Vec<SplitOrder>> {
+        let quantity = self.next_slice_at(now)?;
+        let splits =
+            router.route_order_split(self.order_id, self.price, quantity, self.is_buy_side);
+        self.record_fill(splits.iter().map(|split| split.quantity).sum());
+        Some(splits)
+    }
+}
+
+// Volume-weighted execution: one child order per bucket of an intraday
+// volume profile, sized so the filled quantity tracks the profile's
+// cumulative weight. Buckets may be skipped, in which case their share
+// rolls into the next one executed, and the last bucket takes whatever is
+// left. Weights are relative and need not sum to 1; negative ones count
+// as 0.
+pub struct VwapExecution<'a> {
+    router: &'a SmartOrderRouter,
+    order_id: u32,
+    price: f64, // Limit price passed to the router for every bucket
+    total_quantity: Qty,
+    is_buy_side: bool,
+    // Running sum of the profile's weights, one entry per bucket
+    cumulative_weights: Vec<f64>,
+    next_bucket: usize,
+    filled: Qty,
+}
+
+impl<'a> VwapExecution<'a> {
+    pub fn new(
+        router: &'a SmartOrderRouter,
+        order_id: u32,
+        price: f64,
+        total_quantity: Qty,
+        is_buy_side: bool,
+        volume_profile: Vec<f64>,
+    ) -> Self {
+        let cumulative_weights = volume_profile
+            .iter()
+            .scan(0.0, |sum, weight| {
+                *sum += weight.max(0.0);
+                Some(*sum)
+            })
+            .collect();
+
+        VwapExecution {
+            router,
+            order_id,
+            price,
+            total_quantity,
+            is_buy_side,
+            cumulative_weights,
+            next_bucket: 0,
+            filled: Qty::ZERO,
+        }
+    }
+
+    pub fn buckets(&self) -> usize {
+        self.cumulative_weights.len()
+    }
+
+    pub fn filled_quantity(&self) -> Qty {
+        self.filled
+    }
+
+    pub fn remaining_quantity(&self) -> Qty {
+        self.total_quantity.saturating_sub(self.filled)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.filled >= self.total_quantity
+    }
+
+    // Report quantity filled outside on_bucket
+    pub fn record_fill(&mut self, quantity: Qty) {
+        self.filled = (self.filled + quantity).min(self.total_quantity);
+    }
+
+    // Route this bucket's child order. Empty for a bucket that is out of
+    // range or earlier than one already executed, or when there is nothing
+    // left to fill.
+    pub fn on_bucket(&mut self, bucket_index: usize) -> Vec<SplitOrder> {
+        if bucket_index < self.next_bucket || bucket_index >= self.buckets() {
+            return Vec::new();
+        }
+        self.next_bucket = bucket_index + 1;
+
+        let quantity = if self.next_bucket == self.buckets() {
+            self.remaining_quantity()
+        } else {
+            let total_weight = self.cumulative_weights[self.buckets() - 1];
+            let share = if total_weight > 0.0 {
+                self.cumulative_weights[bucket_index] / total_weight
+            } else {
+                0.0
+            };
+            let target = Qty((self.total_quantity.lots() as f64 * share) as u64);
+            target.saturating_sub(self.filled)
+        };
+        if quantity.is_zero() {
+            return Vec::new();
+        }
+
+        let splits =
+            self.router
+                .route_order_split(self.order_id, self.price, quantity, self.is_buy_side);
+        self.record_fill(splits.iter().map(|split| split.quantity).sum());
+        splits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_book::OrderBook;
+    use crate::smart_order_router::{Exchange, ExchangeID, FeeSchedule};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    struct Venue {
+        book: OrderBook,
+    }
+
+    impl Exchange for Venue {
+        fn get_order_book(&self) -> &OrderBook {
+            &self.book
+        }
+
+        fn get_order_book_mut(&mut self) -> &mut OrderBook {
+            &mut self.book
+        }
+
+        fn get_id(&self) -> ExchangeID {
+            ExchangeID::Binance
+        }
+
+        fn get_name(&self) -> &str {
+            "Binance"
+        }
+    }
+
+    // A router over one venue deep enough to fill any bucket
+    fn deep_router() -> SmartOrderRouter {
+        let mut book = OrderBook::new();
+        book.add_order(1, 100.0, Qty(1_000_000), false);
+        let mut router = SmartOrderRouter::new(false, false);
+        router.add_exchange(Box::new(Venue { book }), FeeSchedule::default());
+        router
+    }
+
+    fn routed(splits: &[SplitOrder]) -> Qty {
+        splits.iter().map(|split| split.quantity).sum()
+    }
+
+    fn twap(total: u64, slices: u32, interval: Duration) -> TwapExecution {
+        TwapExecution::new(1, 101.0, Qty(total), true, slices, interval)
+    }
+
+    // Poll once per interval and fill each slice in full
+    fn run_on_schedule(twap: &mut TwapExecution) -> Vec<Qty> {
+        let mut sizes = Vec::new();
+        let mut now = Duration::ZERO;
+        while !twap.is_finished() {
+            if let Some(quantity) = twap.next_slice_at(now) {
+                sizes.push(quantity);
+                twap.record_fill(quantity);
+            }
+            now += twap.interval;
+        }
+        sizes
+    }
+
+    #[test]
+    fn twap_slices_sum_to_the_parent() {
+        let mut execution = twap(100, 7, MINUTE);
+        let sizes = run_on_schedule(&mut execution);
+
+        assert_eq!(sizes.len(), 7);
+        assert!(sizes[..6].iter().all(|&quantity| quantity == Qty(14)));
+        assert_eq!(sizes[6], Qty(16));
+        assert_eq!(sizes.iter().copied().sum::<Qty>(), Qty(100));
+        assert!(execution.is_complete());
+        assert_eq!(execution.next_slice_at(MINUTE * 100), None);
+    }
+
+    #[test]
+    fn twap_waits_for_each_slice_to_come_due() {
+        let mut execution = twap(100, 4, MINUTE);
+        assert_eq!(execution.next_slice_at(Duration::ZERO), Some(Qty(25)));
+        assert_eq!(execution.next_slice_at(Duration::from_secs(59)), None);
+        assert_eq!(execution.next_slice_at(MINUTE), Some(Qty(50)));
+        assert_eq!(execution.slices_sent(), 2);
+    }
+
+    #[test]
+    fn twap_with_zero_interval_has_every_slice_due_now() {
+        let mut execution = twap(10, 3, Duration::ZERO);
+        assert!(!execution.is_finished());
+
+        let sizes = run_on_schedule(&mut execution);
+        assert_eq!(sizes, [Qty(3), Qty(3), Qty(4)]);
+    }
+
+    #[test]
+    fn twap_polled_after_the_end_still_sends_the_remainder() {
+        let mut execution = twap(100, 4, MINUTE);
+        let first = execution.next_slice_at(Duration::ZERO).unwrap();
+        execution.record_fill(first);
+
+        // Three slices were missed; one late poll sends all of them
+        assert!(!execution.is_finished());
+        assert_eq!(execution.next_slice_at(MINUTE * 10), Some(Qty(75)));
+        assert_eq!(execution.slices_sent(), 4);
+        assert!(execution.is_finished());
+    }
+
+    #[test]
+    fn vwap_buckets_follow_the_volume_profile() {
+        let router = deep_router();
+        let mut execution = VwapExecution::new(
+            &router,
+            1,
+            101.0,
+            Qty(1_000),
+            true,
+            vec![1.0, 3.0, 0.0, 6.0],
+        );
+
+        let sizes: Vec<Qty> = (0..execution.buckets())
+            .map(|bucket| routed(&execution.on_bucket(bucket)))
+            .collect();
+        assert_eq!(sizes, [Qty(100), Qty(300), Qty::ZERO, Qty(600)]);
+        assert!(execution.is_complete());
+    }
+
+    #[test]
+    fn vwap_rolls_a_skipped_bucket_into_the_next() {
+        let router = deep_router();
+        let mut execution =
+            VwapExecution::new(&router, 1, 101.0, Qty(1_000), true, vec![1.0, 1.0, 2.0]);
+
+        assert_eq!(routed(&execution.on_bucket(1)), Qty(500));
+        // Buckets already passed are ignored
+        assert!(execution.on_bucket(0).is_empty());
+        assert_eq!(routed(&execution.on_bucket(2)), Qty(500));
+        assert_eq!(execution.remaining_quantity(), Qty::ZERO);
+    }
+}
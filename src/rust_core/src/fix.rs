@@ -0,0 +1,362 @@
+// Minimal FIX 4.2 parsing for order entry: NewOrderSingle (35=D) and
+// OrderCancelRequest (35=F). Fields are SOH-delimited tag=value pairs; logs
+// that print SOH as '|' are accepted too.
+use crate::error::Error;
+use crate::order_book::{OrderBook, Trade};
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub const SOH: char = '\x01';
+
+// Tags used by the parser
+pub mod tags {
+    pub const BEGIN_STRING: u32 = 8;
+    pub const CHECK_SUM: u32 = 10;
+    pub const CL_ORD_ID: u32 = 11;
+    pub const MSG_TYPE: u32 = 35;
+    pub const ORDER_QTY: u32 = 38;
+    pub const ORD_TYPE: u32 = 40;
+    pub const ORIG_CL_ORD_ID: u32 = 41;
+    pub const PRICE: u32 = 44;
+    pub const SIDE: u32 = 54;
+    pub const SYMBOL: u32 = 55;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FixError {
+    #[error("malformed field `{0}`")]
+    MalformedField(String),
+
+    #[error("missing tag {0}")]
+    MissingTag(u32),
+
+    #[error("invalid value `{value}` for tag {tag}")]
+    InvalidValue { tag: u32, value: String },
+
+    #[error("checksum mismatch: message says {expected}, computed {computed:03}")]
+    ChecksumMismatch { expected: String, computed: u32 },
+
+    #[error("unsupported message type {0}")]
+    UnsupportedMsgType(String),
+
+    #[error("unsupported {field} `{value}`")]
+    Unsupported { field: &'static str, value: String },
+}
+
+pub type Result<T> = std::result::Result<T, FixError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixOrder {
+    NewOrder {
+        cl_ord_id: String,
+        symbol: Option<String>,
+        is_buy_side: bool,
+        price: f64,
+        quantity: u32,
+    },
+    Cancel {
+        cl_ord_id: String,
+        orig_cl_ord_id: String,
+    },
+}
+
+// Sum of all bytes preceding the CheckSum field, mod 256, with '|' counted
+// as the SOH it stands in for
+fn compute_checksum(body: &str) -> u32 {
+    body.bytes()
+        .map(|b| if b == b'|' { 1u32 } else { b as u32 })
+        .sum::<u32>()
+        % 256
+}
+
+fn required<'a>(fields: &'a HashMap<u32, &str>, tag: u32) -> Result<&'a str> {
+    fields.get(&tag).copied().ok_or(FixError::MissingTag(tag))
+}
+
+fn parse_value<T: std::str::FromStr>(fields: &HashMap<u32, &str>, tag: u32) -> Result<T> {
+    let value = required(fields, tag)?;
+    value.parse().map_err(|_| FixError::InvalidValue {
+        tag,
+        value: value.to_string(),
+    })
+}
+
+pub fn parse_fix_message(raw: &str) -> Result<FixOrder> {
+    let raw = raw.trim_end_matches(['\r', '\n']);
+    let mut fields = HashMap::new();
+    let mut offset = 0;
+
+    for field in raw.split([SOH, '|']) {
+        let start = offset;
+        offset += field.len() + 1;
+        if field.is_empty() {
+            continue;
+        }
+
+        let (tag, value) = field
+            .split_once('=')
+            .ok_or_else(|| FixError::MalformedField(field.to_string()))?;
+        let tag: u32 = tag
+            .parse()
+            .map_err(|_| FixError::MalformedField(field.to_string()))?;
+
+        // Validate the trailer when present
+        if tag == tags::CHECK_SUM {
+            let computed = compute_checksum(&raw[..start]);
+            if value.parse::<u32>().ok() != Some(computed) {
+                return Err(FixError::ChecksumMismatch {
+                    expected: value.to_string(),
+                    computed,
+                });
+            }
+        }
+
+        fields.insert(tag, value);
+    }
+
+    let msg_type = required(&fields, tags::MSG_TYPE)?;
+    match msg_type {
+        "D" => {
+            // Only limit orders can rest in the book; OrdType defaults to limit
+            if let Some(&ord_type) = fields.get(&tags::ORD_TYPE) {
+                if ord_type != "2" {
+                    return Err(FixError::Unsupported {
+                        field: "OrdType",
+                        value: ord_type.to_string(),
+                    });
+                }
+            }
+
+            let is_buy_side = match required(&fields, tags::SIDE)? {
+                "1" => true,
+                "2" => false,
+                side => {
+                    return Err(FixError::Unsupported {
+                        field: "Side",
+                        value: side.to_string(),
+                    })
+                }
+            };
+
+            Ok(FixOrder::NewOrder {
+                cl_ord_id: required(&fields, tags::CL_ORD_ID)?.to_string(),
+                symbol: fields.get(&tags::SYMBOL).map(|s| s.to_string()),
+                is_buy_side,
+                price: parse_value(&fields, tags::PRICE)?,
+                quantity: parse_value(&fields, tags::ORDER_QTY)?,
+            })
+        }
+        "F" => Ok(FixOrder::Cancel {
+            cl_ord_id: required(&fields, tags::CL_ORD_ID)?.to_string(),
+            orig_cl_ord_id: required(&fields, tags::ORIG_CL_ORD_ID)?.to_string(),
+        }),
+        other => Err(FixError::UnsupportedMsgType(other.to_string())),
+    }
+}
+
+// What applying one FIX order did to the book
+#[derive(Debug)]
+pub enum FixOutcome {
+    Added { order_id: u32, trades: Vec<Trade> },
+    Cancelled { order_id: u32 },
+    // OrigClOrdID was never seen or is no longer resting
+    UnknownOrder(String),
+}
+
+// Maps string ClOrdIDs onto the book's numeric order ids
+pub struct FixSession {
+    order_ids: HashMap<String, u32>,
+    next_order_id: u32,
+}
+
+impl Default for FixSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FixSession {
+    pub fn new() -> Self {
+        FixSession {
+            order_ids: HashMap::new(),
+            next_order_id: 1,
+        }
+    }
+
+    pub fn apply(
+        &mut self,
+        book: &mut OrderBook,
+        order: FixOrder,
+    ) -> crate::error::Result<FixOutcome> {
+        match order {
+            FixOrder::NewOrder {
+                cl_ord_id,
+                is_buy_side,
+                price,
+                quantity,
+                ..
+            } => {
+                // A reused ClOrdID would orphan the earlier order: it could
+                // no longer be cancelled
+                if self.order_ids.contains_key(&cl_ord_id) {
+                    return Err(Error::DuplicateClOrdId(cl_ord_id));
+                }
+                let order_id = self.next_order_id;
+                let trades = book.try_add_order(order_id, price, quantity.into(), is_buy_side)?;
+                self.next_order_id += 1;
+                self.order_ids.insert(cl_ord_id, order_id);
+                Ok(FixOutcome::Added { order_id, trades })
+            }
+            FixOrder::Cancel { orig_cl_ord_id, .. } => {
+                match self.order_ids.remove(&orig_cl_ord_id) {
+                    Some(order_id) if book.cancel_order_exists(order_id) => {
+                        Ok(FixOutcome::Cancelled { order_id })
+                    }
+                    _ => Ok(FixOutcome::UnknownOrder(orig_cl_ord_id)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Qty;
+
+    // Append a valid CheckSum trailer to a '|'-delimited body
+    fn signed(body: &str) -> String {
+        format!("{body}10={:03}|", compute_checksum(body))
+    }
+
+    fn new_order(cl_ord_id: &str, side: &str, price: &str, quantity: &str) -> String {
+        signed(&format!(
+            "8=FIX.4.2|35=D|11={cl_ord_id}|55=BTC-USD|54={side}|38={quantity}|40=2|44={price}|"
+        ))
+    }
+
+    #[test]
+    fn parses_a_new_order_single() {
+        let message = new_order("A1", "1", "100.25", "5").replace('|', "\x01");
+        assert_eq!(
+            parse_fix_message(&message),
+            Ok(FixOrder::NewOrder {
+                cl_ord_id: "A1".to_string(),
+                symbol: Some("BTC-USD".to_string()),
+                is_buy_side: true,
+                price: 100.25,
+                quantity: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_a_cancel_request_with_pipe_separators() {
+        let message = signed("8=FIX.4.2|35=F|11=C1|41=A1|55=BTC-USD|54=1|");
+        assert_eq!(
+            parse_fix_message(&message),
+            Ok(FixOrder::Cancel {
+                cl_ord_id: "C1".to_string(),
+                orig_cl_ord_id: "A1".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum() {
+        let body = "8=FIX.4.2|35=F|11=C1|41=A1|";
+        let computed = compute_checksum(body);
+        let wrong = (computed + 1) % 256;
+        assert_eq!(
+            parse_fix_message(&format!("{body}10={wrong:03}|")),
+            Err(FixError::ChecksumMismatch {
+                expected: format!("{wrong:03}"),
+                computed,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_missing_tags_and_bad_values() {
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|35=D|11=A1|54=1|38=5|"),
+            Err(FixError::MissingTag(tags::PRICE))
+        );
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|35=F|11=C1|"),
+            Err(FixError::MissingTag(tags::ORIG_CL_ORD_ID))
+        );
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|11=A1|"),
+            Err(FixError::MissingTag(tags::MSG_TYPE))
+        );
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|35=D|11=A1|54=1|38=five|44=100|"),
+            Err(FixError::InvalidValue {
+                tag: tags::ORDER_QTY,
+                value: "five".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|35D|"),
+            Err(FixError::MalformedField("35D".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_messages_and_order_types() {
+        // Heartbeat and Logon
+        for msg_type in ["0", "A"] {
+            assert_eq!(
+                parse_fix_message(&signed(&format!("8=FIX.4.2|35={msg_type}|"))),
+                Err(FixError::UnsupportedMsgType(msg_type.to_string()))
+            );
+        }
+        // Market order
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|35=D|11=A1|54=1|38=5|40=1|44=100|"),
+            Err(FixError::Unsupported {
+                field: "OrdType",
+                value: "1".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_fix_message("8=FIX.4.2|35=D|11=A1|54=5|38=5|44=100|"),
+            Err(FixError::Unsupported {
+                field: "Side",
+                value: "5".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn session_applies_orders_and_cancels_by_cl_ord_id() {
+        let mut book = OrderBook::new();
+        let mut session = FixSession::new();
+        let apply = |session: &mut FixSession, book: &mut OrderBook, raw: &str| {
+            session.apply(book, parse_fix_message(raw).unwrap())
+        };
+
+        let added = apply(&mut session, &mut book, &new_order("A1", "1", "100", "5")).unwrap();
+        assert!(
+            matches!(added, FixOutcome::Added { order_id: 1, ref trades } if trades.is_empty())
+        );
+        assert_eq!(book.get_bid_quantity_at(100.0), Qty(5));
+
+        // Reusing a live ClOrdID is refused and leaves the book alone
+        let reused = apply(&mut session, &mut book, &new_order("A1", "1", "99", "5"));
+        assert!(matches!(reused, Err(Error::DuplicateClOrdId(id)) if id == "A1"));
+        assert_eq!(book.get_bid_quantity_at(99.0), Qty::ZERO);
+
+        let cancel = signed("8=FIX.4.2|35=F|11=C1|41=A1|");
+        assert!(matches!(
+            apply(&mut session, &mut book, &cancel).unwrap(),
+            FixOutcome::Cancelled { order_id: 1 }
+        ));
+        assert_eq!(book.get_best_bid(), None);
+        assert!(matches!(
+            apply(&mut session, &mut book, &cancel).unwrap(),
+            FixOutcome::UnknownOrder(id) if id == "A1"
+        ));
+    }
+}
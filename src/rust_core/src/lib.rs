@@ -1,3 +1,16 @@
+pub mod book_diff;
+pub mod depth_source;
+pub mod error;
+pub mod execution;
+pub mod fix;
+pub mod logging;
+pub mod market_data;
 pub mod market_maker;
+pub mod money;
 pub mod order_book;
+#[cfg(feature = "python")]
+mod python;
+pub mod rate_limit;
+pub mod shared_book;
 pub mod smart_order_router;
+pub mod telemetry;
@@ -0,0 +1,141 @@
+use std::io;
+use tracing::Subscriber;
+use tracing_subscriber::filter::{filter_fn, EnvFilter, FilterExt};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+// Target for machine-parseable output (e.g. the backtest's TRADE/MM_STATE
+// lines). Events logged here are written verbatim to stdout.
+pub const RECORD_TARGET: &str = "records";
+
+// Install the global subscriber: diagnostics go to stderr filtered by
+// RUST_LOG (default "info"), records go undecorated to stdout so downstream
+// CSV parsing keeps working. Safe to call more than once.
+pub fn init() {
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let _ = tracing_subscriber::registry()
+        .with(record_layer(io::stdout))
+        .with(diagnostic_layer(io::stderr, env_filter))
+        .try_init();
+}
+
+// Just the message of each RECORD_TARGET event, one per line
+fn record_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + 'static,
+{
+    fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .without_time()
+        .with_level(false)
+        .with_target(false)
+        .with_filter(filter_fn(|metadata| metadata.target() == RECORD_TARGET))
+}
+
+// Everything else, decorated and filtered by `env_filter`
+fn diagnostic_layer<S, W>(writer: W, env_filter: EnvFilter) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + 'static,
+{
+    fmt::layer()
+        .with_writer(writer)
+        .with_filter(env_filter.and(filter_fn(|metadata| metadata.target() != RECORD_TARGET)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::{debug, info, info_span, warn};
+
+    // In-memory writer shared between a layer and the test
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Capture {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Capture {
+        type Writer = Capture;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    // Run `f` with the two layers writing to fresh captures
+    fn capture(filter: &str, f: impl FnOnce()) -> (String, String) {
+        let (records, diagnostics) = (Capture::default(), Capture::default());
+        let subscriber = tracing_subscriber::registry()
+            .with(record_layer(records.clone()))
+            .with(diagnostic_layer(
+                diagnostics.clone(),
+                EnvFilter::new(filter),
+            ));
+        tracing::subscriber::with_default(subscriber, f);
+        (records.contents(), diagnostics.contents())
+    }
+
+    #[test]
+    fn records_are_written_verbatim_and_apart_from_diagnostics() {
+        let (records, diagnostics) = capture("info", || {
+            info!(target: RECORD_TARGET, "TRADE,1700000000,0,45000.1000,0.500000");
+            info!(price = 45000.1, "Quote placed");
+        });
+
+        assert_eq!(records, "TRADE,1700000000,0,45000.1000,0.500000\n");
+        assert!(diagnostics.contains("Quote placed"));
+        assert!(diagnostics.contains("45000.1"));
+        assert!(diagnostics.contains("INFO"));
+        assert!(!diagnostics.contains("TRADE"));
+    }
+
+    #[test]
+    fn diagnostics_honor_the_filter_but_records_do_not() {
+        let (records, diagnostics) = capture("warn", || {
+            info!(target: RECORD_TARGET, "MM_STATE,1,0.5");
+            debug!("Processing depth update");
+            info!("Top of book moved");
+            warn!("Sequence gap detected");
+        });
+
+        assert_eq!(records, "MM_STATE,1,0.5\n");
+        assert!(diagnostics.contains("Sequence gap detected"));
+        assert!(!diagnostics.contains("Processing depth update"));
+        assert!(!diagnostics.contains("Top of book moved"));
+    }
+
+    #[test]
+    fn diagnostics_carry_their_span() {
+        let (records, diagnostics) = capture("info", || {
+            let _span = info_span!("stream", exchange = "kraken").entered();
+            info!("Connected to WebSocket stream");
+        });
+
+        assert!(records.is_empty());
+        assert!(diagnostics.contains("stream"));
+        assert!(diagnostics.contains("kraken"));
+        assert!(diagnostics.contains("Connected to WebSocket stream"));
+    }
+}
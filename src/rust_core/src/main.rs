@@ -1,6 +1,5 @@
-mod order_book;
-
-use order_book::{OrderBook, Trade};
+use rust_core::money::Qty;
+use rust_core::order_book::{OrderBook, OrderRequest, SelfTradePrevention, TimeInForce, Trade};
 
 fn print_trades(trades: &Vec<Trade>) {
     if trades.is_empty() {
@@ -29,17 +28,17 @@ fn main() {
     println!("\n--- Building Initial Order Book ---");
 
     // Add buy orders (no matches expected)
-    let trades = book.add_order(1, 100.50, 10, true);
+    let trades = book.add_order(1, 100.50, Qty(10), true);
     print_trades(&trades);
-    let trades = book.add_order(2, 100.75, 5, true);
+    let trades = book.add_order(2, 100.75, Qty(5), true);
     print_trades(&trades);
-    let trades = book.add_order(3, 100.25, 15, true);
+    let trades = book.add_order(3, 100.25, Qty(15), true);
     print_trades(&trades);
 
     // Add sell orders (no matches expected)
-    let trades = book.add_order(4, 101.00, 10, false);
+    let trades = book.add_order(4, 101.00, Qty(10), false);
     print_trades(&trades);
-    let trades = book.add_order(5, 101.25, 15, false);
+    let trades = book.add_order(5, 101.25, Qty(15), false);
     print_trades(&trades);
 
     if let Some(best_bid) = book.get_best_bid() {
@@ -63,7 +62,7 @@ fn main() {
 
     // Add aggressive buy order that crosses the spread
     println!("\nAdding Buy Order #6: 25 @ $101.10 (crosses spread)...");
-    let trades = book.add_order(6, 101.10, 25, true);
+    let trades = book.add_order(6, 101.10, Qty(25), true);
     print_trades(&trades);
 
     if let Some(best_bid) = book.get_best_bid() {
@@ -84,7 +83,7 @@ fn main() {
 
     // Add aggressive sell order that crosses the spread
     println!("\nAdding Sell Order #7: 30 @ $100.00 (crosses spread)...");
-    let trades = book.add_order(7, 100.00, 30, false);
+    let trades = book.add_order(7, 100.00, Qty(30), false);
     print_trades(&trades);
 
     if let Some(best_bid) = book.get_best_bid() {
@@ -102,4 +101,501 @@ fn main() {
             book.get_ask_quantity_at(best_ask)
         );
     }
+
+    // Liquidity needed to lift the best ask through deeper levels
+    println!("\n--- Testing Cost to Move ---");
+    book.add_order(8, 101.50, Qty(20), false);
+    book.add_order(9, 102.00, Qty(10), false);
+    println!("Asks: 15 @ $101.25, 20 @ $101.50, 10 @ $102.00");
+    for target in [101.75, 103.00] {
+        match book.cost_to_move(target, true) {
+            Some((quantity, notional)) => println!(
+                "Buying up to ${target:.2} takes {quantity} units (${notional:.4} notional)"
+            ),
+            None => println!("The asks run out before ${target:.2}"),
+        }
+    }
+    for quantity in [Qty(25), Qty(50)] {
+        match (
+            book.vwap_to_fill(quantity, true),
+            book.cost_to_fill(quantity, true),
+        ) {
+            (Some(vwap), Some(cost)) => {
+                println!("Buying {quantity} units: VWAP ${vwap:.4}, ${cost:.4} notional")
+            }
+            _ => println!("Not enough asks to buy {quantity} units"),
+        }
+    }
+
+    // Queue length per level: clear the asks below $102.00, queue two more
+    // orders there and partially fill the first one
+    println!("\n--- Testing Depth With Order Counts ---");
+    book.add_order(10, 102.00, Qty(8), false);
+    book.add_order(11, 102.00, Qty(12), false);
+    book.add_order(12, 101.50, Qty(35), true);
+    book.add_order(13, 102.00, Qty(4), true);
+    let (_, asks) = book.get_depth_with_counts(3);
+    for level in &asks {
+        println!(
+            "  Ask ${:.2}: {} units across {} order(s)",
+            level.price, level.quantity, level.order_count
+        );
+    }
+
+    // Amend a resting bid: a size cut keeps its place, a reprice through
+    // the ask trades
+    println!("\n--- Testing Order Amendment ---");
+    let mut amended = OrderBook::new();
+    amended.add_order(1, 99.00, Qty(10), true);
+    amended.add_order(2, 99.00, Qty(10), true);
+    amended.add_order(3, 100.00, Qty(5), false);
+    amended.modify_order(2, 99.00, Qty(6));
+    println!(
+        "Order #2 cut to 6: {} units ahead of it, {} bid at $99.00",
+        amended.queue_ahead(2).unwrap_or_default(),
+        amended.get_bid_quantity_at(99.00)
+    );
+    amended.modify_order(1, 99.00, Qty(12));
+    println!(
+        "Order #1 raised to 12: {} units ahead of it",
+        amended.queue_ahead(1).unwrap_or_default()
+    );
+    let trades = amended.modify_order(2, 100.00, Qty(6));
+    print_trades(&trades);
+    if let Some(order) = amended.get_order(2) {
+        println!(
+            "Order #2 now rests {} at ${:.2}",
+            order.quantity, order.price
+        );
+    }
+    println!("Order #3 resting: {}", amended.contains_order(3));
+
+    // Prices finer than a cent need a finer price scale
+    println!("\n--- Testing Price Precision ---");
+    let mut fine = OrderBook::new().with_price_scale(100_000_000);
+    fine.add_order(1, 45000.12345, Qty(3), true);
+    fine.add_order(2, 45000.12346, Qty(4), false);
+    if let (Some(bid), Some(ask)) = (fine.get_best_bid(), fine.get_best_ask()) {
+        println!(
+            "Best Bid: ${bid} (Qty: {}), Best Ask: ${ask} (Qty: {})",
+            fine.get_bid_quantity_at(bid),
+            fine.get_ask_quantity_at(ask)
+        );
+    }
+
+    // Zero, sub-tick and out-of-range prices and empty orders never reach
+    // the book
+    println!("\n--- Testing Input Validation ---");
+    let mut checked = OrderBook::new();
+    for (order_id, price, quantity) in [
+        (1, 0.0, Qty(10)),
+        (2, 0.001, Qty(10)),
+        (3, 1e20, Qty(10)),
+        (4, 100.00, Qty(0)),
+    ] {
+        if let Err(e) = checked.try_add_order(order_id, price, quantity, true) {
+            println!("Order #{order_id} rejected: {e}");
+        }
+        checked.add_order(order_id, price, quantity, true);
+    }
+    println!("Best Bid: {:?}", checked.get_best_bid());
+
+    // A second order under a live id is refused, leaving the first intact
+    println!("\n--- Testing Duplicate Order IDs ---");
+    let mut unique = OrderBook::new();
+    unique.add_order(5, 100.00, Qty(10), false);
+    if let Err(e) = unique.try_add_order(5, 101.00, Qty(7), false) {
+        println!("Second Order #5 rejected: {e}");
+    }
+    unique.add_order(5, 101.00, Qty(7), false);
+    println!(
+        "Asks: {} @ $100.00, {} @ $101.00",
+        unique.get_ask_quantity_at(100.00),
+        unique.get_ask_quantity_at(101.00)
+    );
+
+    // Orders from a merged feed arrive out of order; the earlier timestamp
+    // still fills first
+    println!("\n--- Testing Time Priority ---");
+    let mut merged = OrderBook::new();
+    merged.submit(OrderRequest::new(1, 100.00, Qty(5), false).with_timestamp(2_000));
+    merged.submit(OrderRequest::new(2, 100.00, Qty(5), false).with_timestamp(1_000));
+    for order_id in [1, 2] {
+        println!(
+            "Order #{order_id}: timestamp {} ns",
+            merged.get_order_timestamp(order_id).unwrap_or_default()
+        );
+    }
+    let trades = merged.add_order(3, 100.00, Qty(5), true);
+    print_trades(&trades);
+
+    // An iceberg shows 10 of 100; each refill goes behind order #2
+    println!("\n--- Testing Iceberg Orders ---");
+    let mut iceberg = OrderBook::new();
+    iceberg.add_iceberg_order(1, 100.00, Qty(10), Qty(100), false);
+    iceberg.add_order(2, 100.00, Qty(5), false);
+    println!("Visible ask: {}", iceberg.get_ask_quantity_at(100.00));
+    let trades = iceberg.add_order(3, 100.00, Qty(25), true);
+    print_trades(&trades);
+    for quantity in [Qty(40), Qty(40)] {
+        let filled = iceberg
+            .add_order_detailed(4, 100.00, quantity, true)
+            .filled_qty;
+        println!(
+            "Buy {quantity}: filled {filled}, visible ask {}, hidden {}",
+            iceberg.get_ask_quantity_at(100.00),
+            iceberg
+                .get_order(1)
+                .map_or(Qty::ZERO, |order| order.hidden_quantity)
+        );
+    }
+
+    // Owner 7 buys into its own resting ask under each self-trade policy
+    println!("\n--- Testing Self-Trade Prevention ---");
+    for policy in [
+        SelfTradePrevention::CancelResting,
+        SelfTradePrevention::CancelIncoming,
+        SelfTradePrevention::DecrementBoth,
+    ] {
+        let mut owned = OrderBook::new().with_self_trade_prevention(policy);
+        owned.submit(OrderRequest::new(1, 100.00, Qty(10), false).with_owner(7));
+        owned.submit(OrderRequest::new(2, 100.00, Qty(5), false).with_owner(8));
+        let result = owned.submit(OrderRequest::new(3, 100.00, Qty(12), true).with_owner(7));
+        println!(
+            "{policy:?}: filled {}, resting bid {}, asks left {}",
+            result.filled_qty,
+            result.resting_qty,
+            owned.get_ask_quantity_at(100.00)
+        );
+    }
+
+    // Circuit breaker: orders that would trade more than 5% from $100 halt
+    println!("\n--- Testing Price Band ---");
+    let mut banded = OrderBook::new();
+    banded.add_order(1, 102.00, Qty(10), false);
+    banded.add_order(2, 110.00, Qty(10), false);
+    banded.set_price_band(100.00, 5.0);
+    for (order_id, price, quantity) in [(3, 120.00, Qty(15)), (4, 104.00, Qty(5))] {
+        let result = banded.submit(OrderRequest::new(order_id, price, quantity, true));
+        if result.halted {
+            println!("Buy {quantity} @ ${price:.2}: halted");
+        } else {
+            println!("Buy {quantity} @ ${price:.2}: filled {}", result.filled_qty);
+        }
+    }
+
+    // Execution reports for a resting order filled in two steps
+    println!("\n--- Testing Execution Events ---");
+    let mut audited = OrderBook::new().with_event_log(64);
+    audited.add_order(1, 100.00, Qty(10), false);
+    audited.add_order(2, 100.00, Qty(4), true);
+    audited.add_order(3, 100.00, Qty(6), true);
+    for event in audited.take_events() {
+        println!("  {event:?}");
+    }
+
+    // Sizes below the default 0.01 lot need a finer quantity scale
+    println!("\n--- Testing Fractional Quantities ---");
+    let mut btc_book = OrderBook::new().with_quantity_scale(100_000_000);
+    let dust = btc_book.quantity_from_base(0.005);
+    println!("\nAdding Sell Order #1: 0.005 BTC ({dust} satoshi) @ $45000.00...");
+    btc_book.add_order(1, 45000.00, dust, false);
+    let trades = btc_book.add_order(2, 45000.00, btc_book.quantity_from_base(0.0025), true);
+    for trade in &trades {
+        println!(
+            "  Trade #{}: {} BTC @ ${:.2}",
+            trade.trade_id,
+            trade.quantity.to_base(btc_book.quantity_scale()),
+            trade.price
+        );
+    }
+    println!(
+        "Resting Ask: {} BTC",
+        btc_book
+            .get_ask_quantity_at(45000.00)
+            .to_base(btc_book.quantity_scale())
+    );
+
+    // Stops rest off-book until a trade prints through the trigger
+    println!("\n--- Testing Stop Orders ---");
+    let mut stop_book = OrderBook::new();
+    stop_book.add_order(1, 100.00, Qty(5), false);
+    stop_book.add_order(2, 101.00, Qty(5), false);
+    stop_book.add_order(3, 102.00, Qty(10), false);
+    stop_book.add_stop_order(10, 101.00, Some(102.00), Qty(5), true);
+    stop_book.add_stop_order(11, 101.00, None, Qty(3), true);
+    println!("Pending stops: {}", stop_book.pending_stop_orders().len());
+    println!("\nBuying 5 @ $100.00 (below trigger)...");
+    let trades = stop_book.add_order(4, 100.00, Qty(5), true);
+    print_trades(&trades);
+    println!("Pending stops: {}", stop_book.pending_stop_orders().len());
+    println!("\nBuying 2 @ $101.00 (through trigger)...");
+    let trades = stop_book.add_order(5, 101.00, Qty(2), true);
+    print_trades(&trades);
+    println!("Pending stops: {}", stop_book.pending_stop_orders().len());
+
+    // A market buy larger than the whole ask side leaves nothing resting
+    println!("\n--- Testing Market Orders ---");
+    let mut market_book = OrderBook::new();
+    market_book.add_order(1, 100.00, Qty(5), false);
+    market_book.add_order(2, 105.00, Qty(5), false);
+    println!("\nMarket Buy 15 against 10 resting...");
+    let (trades, unfilled) = market_book.add_market_order(3, Qty(15), true);
+    print_trades(&trades);
+    println!(
+        "Unfilled: {unfilled}, Best Bid: {:?}, Best Ask: {:?}",
+        market_book.get_best_bid(),
+        market_book.get_best_ask()
+    );
+
+    // IOC takes what it can at its limit and cancels the rest
+    println!("\n--- Testing Immediate-or-Cancel ---");
+    let mut ioc_book = OrderBook::new();
+    ioc_book.add_order(1, 100.00, Qty(4), false);
+    ioc_book.add_order(2, 101.00, Qty(4), false);
+    println!("\nIOC Buy 10 @ $100.00...");
+    let (trades, cancelled) = ioc_book.add_order_ioc(3, 100.00, Qty(10), true);
+    print_trades(&trades);
+    println!(
+        "Cancelled: {cancelled}, Best Bid: {:?}, Best Ask: {:?}",
+        ioc_book.get_best_bid(),
+        ioc_book.get_best_ask()
+    );
+
+    // Kraken-style checksum at BTC/USD precisions (0.1 price, 1e-8 qty), over
+    // "452852100000452864154582015452835100000452834154582015" (asks then
+    // bids, best first)
+    println!("\n--- Testing Book Checksum ---");
+    let checksum_book = OrderBook::from_levels(
+        &[(45283.5, Qty(100_000)), (45283.4, Qty(154_582_015))],
+        &[(45285.2, Qty(100_000)), (45286.4, Qty(154_582_015))],
+    )
+    .expect("levels do not cross")
+    .with_price_scale(10)
+    .with_quantity_scale(100_000_000);
+    println!(
+        "Checksum (10 levels): {} (expected 1694872844)",
+        checksum_book.checksum(10)
+    );
+
+    // Touch metrics need both sides quoted
+    println!("\n--- Testing Midpoint and Spread ---");
+    let mut quoted = OrderBook::new();
+    println!(
+        "Empty: mid {:?}, spread {:?}, bps {:?}",
+        quoted.midpoint(),
+        quoted.spread(),
+        quoted.spread_bps()
+    );
+    quoted.add_order(1, 99.00, Qty(10), true);
+    println!(
+        "Bid only: mid {:?}, spread {:?}, bps {:?}",
+        quoted.midpoint(),
+        quoted.spread(),
+        quoted.spread_bps()
+    );
+    quoted.add_order(2, 101.00, Qty(10), false);
+    println!(
+        "Two-sided: mid {:?}, spread {:?}, bps {:.1}",
+        quoted.midpoint(),
+        quoted.spread(),
+        quoted.spread_bps().unwrap_or_default()
+    );
+
+    // Whole-side totals, not just the touch
+    println!("\n--- Testing Side Totals ---");
+    quoted.add_order(3, 98.00, Qty(15), true);
+    quoted.add_order(4, 99.00, Qty(5), true);
+    println!(
+        "Bids: {} in {} orders, Asks: {} in {} orders",
+        quoted.total_bid_quantity(),
+        quoted.bid_order_count(),
+        quoted.total_ask_quantity(),
+        quoted.ask_order_count()
+    );
+
+    // Cancelling hands back the order as it rested
+    println!("\n--- Testing Cancel Returns Order ---");
+    match quoted.cancel_order(3) {
+        Some(order) => println!(
+            "Cancelled #{}: {} {} @ ${:.2}",
+            order.order_id,
+            if order.is_buy_side { "BUY" } else { "SELL" },
+            order.quantity,
+            order.price
+        ),
+        None => println!("Order #3 not found"),
+    }
+    println!(
+        "Cancel #3 again: {:?}",
+        quoted.cancel_order(3).map(|o| o.order_id)
+    );
+
+    // Flatten one side, then reset the whole book
+    println!("\n--- Testing Cancel All and Clear ---");
+    println!("Cancelled bids: {:?}", quoted.cancel_all_for_side(true));
+    println!(
+        "Best Bid: {:?}, Best Ask: {:?}",
+        quoted.get_best_bid(),
+        quoted.get_best_ask()
+    );
+    quoted.clear();
+    println!(
+        "After clear - Best Bid: {:?}, Best Ask: {:?}, next trade id: {}",
+        quoted.get_best_bid(),
+        quoted.get_best_ask(),
+        quoted.trade_id_counter()
+    );
+
+    // Every trade from one submission carries the incoming order's time
+    println!("\n--- Testing Trade Timestamps ---");
+    let mut stamped = OrderBook::new();
+    stamped.add_order(1, 100.00, Qty(5), false);
+    stamped.add_order(2, 101.00, Qty(5), false);
+    let result = stamped.submit(OrderRequest::new(3, 101.00, Qty(8), true).with_timestamp(5_000));
+    for trade in &result.trades {
+        println!(
+            "  Trade #{}: {} @ ${:.2} at {} ns",
+            trade.trade_id, trade.quantity, trade.price, trade.timestamp
+        );
+    }
+
+    // A sell into a resting bid: the bid provided liquidity
+    println!("\n--- Testing Maker/Taker Labels ---");
+    let mut labelled = OrderBook::new();
+    labelled.add_order(1, 100.00, Qty(10), true);
+    for trade in labelled.add_order(2, 100.00, Qty(4), false) {
+        println!(
+            "  Trade #{}: maker #{} (buy #{}), taker #{} (sell #{})",
+            trade.trade_id,
+            trade.maker_order_id,
+            trade.buy_order_id,
+            trade.taker_order_id,
+            trade.sell_order_id
+        );
+    }
+
+    // Owner 7 is long 10; a reduce-only sell of 15 only closes those 10
+    println!("\n--- Testing Reduce-Only Orders ---");
+    let mut flattening = OrderBook::new();
+    flattening.add_order(1, 100.00, Qty(10), false);
+    flattening.submit(OrderRequest::new(2, 100.00, Qty(10), true).with_owner(7));
+    flattening.add_order(3, 99.00, Qty(50), true);
+    println!("Position before: {}", flattening.position(7));
+    let trades = flattening.add_reduce_only_order(4, 7, 99.00, Qty(15), false);
+    print_trades(&trades);
+    println!(
+        "Position after: {}, resting #4: {}",
+        flattening.position(7),
+        flattening.contains_order(4)
+    );
+
+    // The observer sees each trade as it prints, ahead of the returned Vec
+    println!("\n--- Testing Trade Observer ---");
+    let mut observed = OrderBook::new();
+    observed.set_trade_observer(Box::new(|trade| {
+        println!(
+            "  Observed trade #{}: {} @ ${:.2}",
+            trade.trade_id, trade.quantity, trade.price
+        )
+    }));
+    observed.add_order(1, 100.00, Qty(5), false);
+    observed.add_order(2, 100.50, Qty(5), false);
+    let trades = observed.add_order(3, 100.50, Qty(8), true);
+    println!("Returned {} trade(s)", trades.len());
+
+    // Buying 30 takes 10 @ 100, 10 @ 101, 10 @ 102: VWAP 101, 1% over touch
+    println!("\n--- Testing Slippage Estimate ---");
+    let ladder = OrderBook::from_levels(
+        &[(99.00, Qty(10))],
+        &[(100.00, Qty(10)), (101.00, Qty(10)), (102.00, Qty(10))],
+    )
+    .expect("levels do not cross");
+    for quantity in [5, 30, 50] {
+        println!(
+            "Buy {quantity}: {:?}",
+            ladder.estimated_slippage(Qty(quantity), true)
+        );
+    }
+    println!("Sell 5: {:?}", ladder.estimated_slippage(Qty(5), false));
+
+    // A size cut keeps its place; a size increase goes to the back
+    println!("\n--- Testing Level Queues ---");
+    let mut queued = OrderBook::new();
+    for order_id in 1..=3 {
+        queued.add_order(order_id, 100.00, Qty(10), true);
+    }
+    println!("Bids @ $100.00: {:?}", queued.get_bid_orders_at(100.00));
+    queued.modify_order(1, 100.00, Qty(5));
+    println!("After cutting #1: {:?}", queued.get_bid_orders_at(100.00));
+    queued.modify_order(2, 100.00, Qty(20));
+    println!("After growing #2: {:?}", queued.get_bid_orders_at(100.00));
+    println!("Asks @ $100.00: {:?}", queued.get_ask_orders_at(100.00));
+
+    // A bid pegged one tick under the ask follows the ask up when the
+    // $101.00 offer is pulled
+    println!("\n--- Testing Pegged Orders ---");
+    let mut pegged = OrderBook::new();
+    pegged.add_order(1, 101.00, Qty(10), false);
+    pegged.add_order(2, 102.00, Qty(10), false);
+    pegged.add_pegged_order(3, 1, Qty(5), true);
+    println!("Ask $101.00 -> pegged bid {:?}", pegged.get_best_bid());
+    pegged.cancel_order(1);
+    let trades = pegged.reprice_pegs();
+    println!(
+        "Ask $102.00 -> pegged bid {:?} ({} trade(s))",
+        pegged.get_best_bid(),
+        trades.len()
+    );
+
+    // Second update resizes $100.00, removes $99.50 and adds an ask
+    println!("\n--- Testing Depth Updates ---");
+    let mut l2 = OrderBook::new();
+    let first = l2
+        .apply_depth_update(
+            &[(100.00, Qty(10)), (99.50, Qty(20))],
+            &[(100.50, Qty(5))],
+            1,
+        )
+        .expect("valid prices");
+    let second = l2
+        .apply_depth_update(
+            &[(100.00, Qty(4)), (99.50, Qty(0))],
+            &[(101.00, Qty(8))],
+            first.next_order_id,
+        )
+        .expect("valid prices");
+    let (bids, asks) = l2.get_depth(5);
+    println!(
+        "Bids: {bids:?}, Asks: {asks:?}, next id: {}",
+        second.next_order_id
+    );
+
+    // Checked calls say why nothing happened
+    println!("\n--- Testing Checked Errors ---");
+    let mut checked = OrderBook::new();
+    checked.add_order(1, 100.00, Qty(10), false);
+    let post_only =
+        OrderRequest::new(2, 100.00, Qty(5), true).with_time_in_force(TimeInForce::PostOnly);
+    if let Err(e) = checked.try_submit(post_only) {
+        println!("Post-only: {e}");
+    }
+    if let Err(e) = checked.try_cancel_order(9) {
+        println!("Cancel: {e}");
+    }
+    if let Err(e) = checked.try_modify_order(1, f64::NAN, Qty(5)) {
+        println!("Modify: {e}");
+    }
+
+    // The tape keeps the last three prints; the book knows its last price
+    println!("\n--- Testing Trade Tape ---");
+    let mut taped = OrderBook::new().with_trade_tape(3);
+    for order_id in 1..=4 {
+        taped.add_order(order_id, 100.00 + order_id as f64, Qty(1), false);
+    }
+    taped.add_order(5, 105.00, Qty(4), true);
+    println!("Last trade price: {:?}", taped.last_trade_price());
+    for trade in taped.recent_trades(10) {
+        println!("  Tape: #{} @ ${:.2}", trade.trade_id, trade.price);
+    }
 }
@@ -0,0 +1,176 @@
+// Record types shared by the backtest, the replay tool and the websocket
+// capture, so a file written by one tool reads back in the others. Field
+// names are the CSV column names.
+use crate::error::{Error, Result};
+use crate::money::Qty;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// One top-of-book sample.
+// Columns: timestamp,symbol,bid,ask,bid_size,ask_size,last_price,volume
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketTick {
+    // Epoch seconds; `YYYY-MM-DD HH:MM:SS` is accepted on input
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub timestamp: i64,
+    #[serde(default = "default_symbol")]
+    pub symbol: String,
+    pub bid: f64,
+    pub ask: f64,
+    pub bid_size: f64,
+    pub ask_size: f64,
+    pub last_price: f64,
+    pub volume: f64,
+}
+
+impl MarketTick {
+    // Positional parse, for files whose first row is data rather than a
+    // header (as in test_data/)
+    pub fn from_record(record: &csv::StringRecord) -> Result<Self> {
+        Ok(record.deserialize(None)?)
+    }
+}
+
+fn default_symbol() -> String {
+    "BTC-USD".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderAction {
+    #[default]
+    Add,
+    Cancel,
+    Modify,
+}
+
+// One order-flow row. Legacy files only carry is_buy,price,quantity; the
+// action and order_id columns are optional, and cancel rows may leave the
+// order fields empty.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrderEvent {
+    #[serde(default)]
+    pub action: OrderAction,
+    #[serde(default)]
+    pub order_id: Option<u32>,
+    // Written as 1 (buy) or 0 (sell)
+    #[serde(default, rename = "is_buy", with = "side_flag")]
+    pub is_buy_side: Option<bool>,
+    #[serde(default)]
+    pub price: Option<f64>,
+    // Whole lots at the book's quantity scale
+    #[serde(default)]
+    pub quantity: Option<Qty>,
+    // Arrival time in milliseconds, used for paced replay
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+}
+
+impl OrderEvent {
+    pub fn add(is_buy_side: bool, price: f64, quantity: Qty) -> Self {
+        OrderEvent {
+            action: OrderAction::Add,
+            is_buy_side: Some(is_buy_side),
+            price: Some(price),
+            quantity: Some(quantity),
+            ..Default::default()
+        }
+    }
+
+    pub fn cancel(order_id: u32) -> Self {
+        OrderEvent {
+            action: OrderAction::Cancel,
+            order_id: Some(order_id),
+            ..Default::default()
+        }
+    }
+
+    pub fn modify(order_id: u32, price: f64, quantity: Qty) -> Self {
+        OrderEvent {
+            action: OrderAction::Modify,
+            order_id: Some(order_id),
+            price: Some(price),
+            quantity: Some(quantity),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_order_id(mut self, order_id: u32) -> Self {
+        self.order_id = Some(order_id);
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp_ms: u64) -> Self {
+        self.timestamp = Some(timestamp_ms);
+        self
+    }
+}
+
+mod side_flag {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<bool>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        value.map(u8::from).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<bool>, D::Error> {
+        Ok(Option::<u8>::deserialize(deserializer)?.map(|flag| flag == 1))
+    }
+}
+
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<i64, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_timestamp(value.trim()).map_err(serde::de::Error::custom)
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Accepts either an integer epoch timestamp or `YYYY-MM-DD HH:MM:SS`
+// (as in test_data/), which is converted to epoch seconds
+pub fn parse_timestamp(value: &str) -> Result<i64> {
+    if let Ok(timestamp) = value.parse::<i64>() {
+        return Ok(timestamp);
+    }
+
+    let invalid = || Error::Parse {
+        field: "timestamp",
+        value: value.to_string(),
+    };
+
+    let (date, time) = value.split_once([' ', 'T']).ok_or_else(invalid)?;
+    let date: Vec<i64> = date
+        .split('-')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    let time: Vec<i64> = time
+        .split(':')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+
+    match (date.as_slice(), time.as_slice()) {
+        ([year, month, day], [hour, minute, second])
+            if (1..=12).contains(month)
+                && (1..=31).contains(day)
+                && (0..24).contains(hour)
+                && (0..60).contains(minute)
+                && (0..=60).contains(second) =>
+        {
+            Ok(days_from_civil(*year, *month, *day) * 86_400 + hour * 3600 + minute * 60 + second)
+        }
+        _ => Err(invalid()),
+    }
+}
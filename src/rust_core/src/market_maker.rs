@@ -1,16 +1,20 @@
+use crate::error::{Error, Result};
+use crate::money::{to_decimal, to_f64, Decimal, Qty};
 use crate::smart_order_router::{ExchangeID, SmartOrderRouter};
-use std::time::Instant;
+use crate::telemetry;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 
 #[derive(Debug, Clone)]
 pub struct Quote {
     pub price: f64,
-    pub quantity: u32,
+    pub quantity: Qty,
     pub is_buy_side: bool,
     pub target_exchange: ExchangeID,
 }
 
 impl Quote {
-    pub fn new(price: f64, quantity: u32, is_buy_side: bool, target_exchange: ExchangeID) -> Self {
+    pub fn new(price: f64, quantity: Qty, is_buy_side: bool, target_exchange: ExchangeID) -> Self {
         Quote {
             price,
             quantity,
@@ -27,6 +31,16 @@ pub struct MarketMakerQuotes {
     pub theoretical_edge: f64, // Expected profit if both quotes fill
 }
 
+// Cancel-on-disconnect (dead man's switch) status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatState {
+    Disabled, // No heartbeat timeout configured
+    Alive,
+    // The deadline passed without a quote update and the outstanding quotes
+    // were pulled; the next update_quotes call rearms the switch
+    Tripped,
+}
+
 #[derive(Debug, Clone)]
 pub struct InventoryPosition {
     pub base_inventory: f64,  // e.g., BTC
@@ -56,6 +70,18 @@ pub struct MarketMakerParameters {
     pub base_quote_size: f64, // Base size for quotes
     pub min_quote_size: f64,  // Minimum quote size
     pub max_quote_size: f64,  // Maximum quote size
+
+    // Integer order quantity per base unit, e.g. 100 for 0.01 BTC lots
+    pub units_per_base: u64,
+
+    // Fair value: size-weighted microprice instead of the plain midpoint
+    pub use_microprice: bool,
+
+    // Anti-flicker: keep the outstanding quotes unless the midpoint has
+    // moved more than requote_threshold_bps or requote_interval_ms has
+    // passed since they were made. The zero defaults re-quote every call.
+    pub requote_threshold_bps: f64,
+    pub requote_interval_ms: u64,
 }
 
 impl Default for MarketMakerParameters {
@@ -72,7 +98,201 @@ impl Default for MarketMakerParameters {
             base_quote_size: 0.1,          // 0.1 BTC base size
             min_quote_size: 0.01,          // 0.01 BTC minimum
             max_quote_size: 1.0,           // 1.0 BTC maximum
+            units_per_base: 100,           // 0.01 BTC lots
+            use_microprice: false,
+            requote_threshold_bps: 0.0,
+            requote_interval_ms: 0,
+        }
+    }
+}
+
+impl MarketMakerParameters {
+    pub fn builder() -> MarketMakerParametersBuilder {
+        MarketMakerParametersBuilder::default()
+    }
+
+    // Reject contradictory settings that would otherwise be silently clamped
+    pub fn validate(&self) -> Result<()> {
+        let fields = [
+            ("base_spread_bps", self.base_spread_bps),
+            ("min_spread_bps", self.min_spread_bps),
+            ("max_spread_bps", self.max_spread_bps),
+            ("max_base_inventory", self.max_base_inventory),
+            ("max_quote_inventory", self.max_quote_inventory),
+            ("target_base_inventory", self.target_base_inventory),
+            ("inventory_skew_factor", self.inventory_skew_factor),
+            ("volatility_adjustment", self.volatility_adjustment),
+            ("base_quote_size", self.base_quote_size),
+            ("min_quote_size", self.min_quote_size),
+            ("max_quote_size", self.max_quote_size),
+            ("requote_threshold_bps", self.requote_threshold_bps),
+        ];
+        for (name, value) in fields {
+            if !value.is_finite() || value < 0.0 {
+                return Err(invalid_config(format!(
+                    "{name} must be finite and non-negative, got {value}"
+                )));
+            }
+        }
+
+        if self.min_spread_bps > self.max_spread_bps {
+            return Err(invalid_config(format!(
+                "min_spread_bps ({}) exceeds max_spread_bps ({})",
+                self.min_spread_bps, self.max_spread_bps
+            )));
+        }
+        if self.base_spread_bps < self.min_spread_bps || self.base_spread_bps > self.max_spread_bps
+        {
+            return Err(invalid_config(format!(
+                "base_spread_bps ({}) outside [{}, {}]",
+                self.base_spread_bps, self.min_spread_bps, self.max_spread_bps
+            )));
+        }
+
+        if self.min_quote_size > self.max_quote_size {
+            return Err(invalid_config(format!(
+                "min_quote_size ({}) exceeds max_quote_size ({})",
+                self.min_quote_size, self.max_quote_size
+            )));
+        }
+        if self.base_quote_size < self.min_quote_size || self.base_quote_size > self.max_quote_size
+        {
+            return Err(invalid_config(format!(
+                "base_quote_size ({}) outside [{}, {}]",
+                self.base_quote_size, self.min_quote_size, self.max_quote_size
+            )));
+        }
+
+        if self.units_per_base == 0 {
+            return Err(invalid_config(
+                "units_per_base must be at least 1".to_string(),
+            ));
+        }
+
+        if self.max_base_inventory == 0.0 {
+            return Err(invalid_config(
+                "max_base_inventory must be positive".to_string(),
+            ));
+        }
+        if self.target_base_inventory > self.max_base_inventory {
+            return Err(invalid_config(format!(
+                "target_base_inventory ({}) exceeds max_base_inventory ({})",
+                self.target_base_inventory, self.max_base_inventory
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Base quantity to integer order units, truncating any remainder
+    pub fn to_units(&self, base_quantity: f64) -> Qty {
+        Qty::from_base(base_quantity, self.units_per_base)
+    }
+
+    pub fn from_units(&self, units: Qty) -> f64 {
+        units.to_base_f64(self.units_per_base)
+    }
+
+    // Whether quotes made at `quoted_midpoint`, `age` ago, should be replaced
+    // now that the midpoint is `midpoint`
+    pub fn should_requote(&self, quoted_midpoint: f64, midpoint: f64, age: Duration) -> bool {
+        if age >= Duration::from_millis(self.requote_interval_ms) || quoted_midpoint <= 0.0 {
+            return true;
         }
+        let moved_bps = (midpoint - quoted_midpoint).abs() / quoted_midpoint * 10000.0;
+        moved_bps > self.requote_threshold_bps
+    }
+}
+
+fn invalid_config(message: String) -> Error {
+    Error::InvalidConfig(message)
+}
+
+// Starts from the defaults; `build` validates the result
+#[derive(Debug, Clone, Default)]
+pub struct MarketMakerParametersBuilder {
+    params: MarketMakerParameters,
+}
+
+impl MarketMakerParametersBuilder {
+    pub fn base_spread_bps(mut self, value: f64) -> Self {
+        self.params.base_spread_bps = value;
+        self
+    }
+
+    pub fn min_spread_bps(mut self, value: f64) -> Self {
+        self.params.min_spread_bps = value;
+        self
+    }
+
+    pub fn max_spread_bps(mut self, value: f64) -> Self {
+        self.params.max_spread_bps = value;
+        self
+    }
+
+    pub fn max_base_inventory(mut self, value: f64) -> Self {
+        self.params.max_base_inventory = value;
+        self
+    }
+
+    pub fn max_quote_inventory(mut self, value: f64) -> Self {
+        self.params.max_quote_inventory = value;
+        self
+    }
+
+    pub fn target_base_inventory(mut self, value: f64) -> Self {
+        self.params.target_base_inventory = value;
+        self
+    }
+
+    pub fn inventory_skew_factor(mut self, value: f64) -> Self {
+        self.params.inventory_skew_factor = value;
+        self
+    }
+
+    pub fn volatility_adjustment(mut self, value: f64) -> Self {
+        self.params.volatility_adjustment = value;
+        self
+    }
+
+    pub fn base_quote_size(mut self, value: f64) -> Self {
+        self.params.base_quote_size = value;
+        self
+    }
+
+    pub fn min_quote_size(mut self, value: f64) -> Self {
+        self.params.min_quote_size = value;
+        self
+    }
+
+    pub fn max_quote_size(mut self, value: f64) -> Self {
+        self.params.max_quote_size = value;
+        self
+    }
+
+    pub fn units_per_base(mut self, value: u64) -> Self {
+        self.params.units_per_base = value;
+        self
+    }
+
+    pub fn use_microprice(mut self, value: bool) -> Self {
+        self.params.use_microprice = value;
+        self
+    }
+
+    pub fn requote_threshold_bps(mut self, value: f64) -> Self {
+        self.params.requote_threshold_bps = value;
+        self
+    }
+
+    pub fn requote_interval_ms(mut self, value: u64) -> Self {
+        self.params.requote_interval_ms = value;
+        self
+    }
+
+    pub fn build(self) -> Result<MarketMakerParameters> {
+        self.params.validate()?;
+        Ok(self.params)
     }
 }
 
@@ -80,11 +300,11 @@ pub struct MarketMaker<'a> {
     sor: &'a SmartOrderRouter,
     params: MarketMakerParameters,
 
-    // Inventory tracking
-    base_inventory: f64,
-    quote_inventory: f64,
-    initial_base_inventory: f64,
-    initial_quote_inventory: f64,
+    // Inventory tracking, kept as exact decimals so fills never drift
+    base_inventory: Decimal,
+    quote_inventory: Decimal,
+    initial_base_inventory: Decimal,
+    initial_quote_inventory: Decimal,
 
     // Market data
     last_midpoint: f64,
@@ -93,9 +313,20 @@ pub struct MarketMaker<'a> {
     // Performance tracking
     quotes_placed: u32,
     quotes_filled: u32,
-    total_volume: f64,
-    realized_pnl: f64,
+    total_volume: Decimal,
+    realized_pnl: Decimal,
     start_time: Instant,
+
+    // Cancel-on-disconnect; clock readings are relative to start_time
+    outstanding_quotes: Option<MarketMakerQuotes>,
+    // Midpoint and time the outstanding quotes were made at
+    quoted_midpoint: f64,
+    quoted_at: Duration,
+    quotes_reused: u32,
+    heartbeat_timeout: Option<Duration>,
+    last_heartbeat: Duration,
+    heartbeat_state: HeartbeatState,
+    quotes_pulled: u32,
 }
 
 impl<'a> MarketMaker<'a> {
@@ -103,29 +334,35 @@ impl<'a> MarketMaker<'a> {
         MarketMaker {
             sor,
             params,
-            base_inventory: 0.0,
-            quote_inventory: 0.0,
-            initial_base_inventory: 0.0,
-            initial_quote_inventory: 0.0,
+            base_inventory: Decimal::ZERO,
+            quote_inventory: Decimal::ZERO,
+            initial_base_inventory: Decimal::ZERO,
+            initial_quote_inventory: Decimal::ZERO,
             last_midpoint: 0.0,
             volatility_estimate: 0.001, // 0.1% default volatility
             quotes_placed: 0,
             quotes_filled: 0,
-            total_volume: 0.0,
-            realized_pnl: 0.0,
+            total_volume: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
             start_time: Instant::now(),
+            outstanding_quotes: None,
+            quoted_midpoint: 0.0,
+            quoted_at: Duration::ZERO,
+            quotes_reused: 0,
+            heartbeat_timeout: None,
+            last_heartbeat: Duration::ZERO,
+            heartbeat_state: HeartbeatState::Disabled,
+            quotes_pulled: 0,
         }
     }
 
     pub fn initialize(&mut self, base_inventory: f64, quote_inventory: f64) {
-        self.base_inventory = base_inventory;
-        self.quote_inventory = quote_inventory;
-        self.initial_base_inventory = base_inventory;
-        self.initial_quote_inventory = quote_inventory;
+        self.base_inventory = to_decimal(base_inventory);
+        self.quote_inventory = to_decimal(quote_inventory);
+        self.initial_base_inventory = self.base_inventory;
+        self.initial_quote_inventory = self.quote_inventory;
 
-        println!("Market Maker initialized with:");
-        println!("  Base inventory: {} BTC", self.base_inventory);
-        println!("  Quote inventory: ${}", self.quote_inventory);
+        info!(base_inventory, quote_inventory, "Market maker initialized");
     }
 
     fn calculate_midpoint(&mut self) -> f64 {
@@ -136,7 +373,15 @@ impl<'a> MarketMaker<'a> {
             return self.last_midpoint;
         }
 
-        let midpoint = (market_data.best_bid + market_data.best_ask) / 2.0;
+        let total_quantity = market_data.touch_bid_quantity + market_data.touch_ask_quantity;
+        let midpoint = if self.params.use_microprice && !total_quantity.is_zero() {
+            // Weighted by the touch sizes summed across venues
+            (market_data.best_bid * market_data.touch_ask_quantity.lots() as f64
+                + market_data.best_ask * market_data.touch_bid_quantity.lots() as f64)
+                / total_quantity.lots() as f64
+        } else {
+            (market_data.best_bid + market_data.best_ask) / 2.0
+        };
         self.last_midpoint = midpoint;
         midpoint
     }
@@ -165,7 +410,7 @@ impl<'a> MarketMaker<'a> {
             return 0.0;
         }
 
-        let inventory_ratio = self.base_inventory / self.params.target_base_inventory;
+        let inventory_ratio = self.base_inventory_f64() / self.params.target_base_inventory;
         let imbalance = inventory_ratio - 1.0;
 
         // Skew factor: positive means too much inventory (lower bid, raise ask)
@@ -187,37 +432,55 @@ impl<'a> MarketMaker<'a> {
         (bid_price, ask_price)
     }
 
-    fn calculate_quote_size(&self, is_buy_side: bool) -> u32 {
+    fn calculate_quote_size(&self, is_buy_side: bool) -> Qty {
         let mut base_size = self.params.base_quote_size;
 
         // Adjust size based on inventory
         if is_buy_side {
             // Reduce buy size if we have too much base inventory
-            let inventory_ratio = self.base_inventory / self.params.max_base_inventory;
+            let inventory_ratio = self.base_inventory_f64() / self.params.max_base_inventory;
             base_size *= 1.0 - inventory_ratio * 0.5;
         } else {
             // Reduce sell size if we have too little base inventory
-            let inventory_ratio = self.base_inventory / self.params.target_base_inventory;
+            let inventory_ratio = self.base_inventory_f64() / self.params.target_base_inventory;
             base_size *= inventory_ratio.min(1.0);
         }
 
-        // Convert to integer quantity (assuming whole units for simplicity)
-        let quantity = (base_size * 100.0) as u32; // Convert to smallest unit
+        let quantity = self.params.to_units(base_size);
 
         // Enforce limits
         quantity
-            .max((self.params.min_quote_size * 100.0) as u32)
-            .min((self.params.max_quote_size * 100.0) as u32)
+            .max(self.params.to_units(self.params.min_quote_size))
+            .min(self.params.to_units(self.params.max_quote_size))
     }
 
     pub fn update_quotes(&mut self) -> Option<MarketMakerQuotes> {
+        // Any quote update counts as a heartbeat
+        self.last_heartbeat = self.elapsed();
+        if self.heartbeat_timeout.is_some() {
+            self.heartbeat_state = HeartbeatState::Alive;
+        }
+
         // Get current market state
         let midpoint = self.calculate_midpoint();
         if midpoint <= 0.0 {
-            eprintln!("Invalid market midpoint");
+            warn!("Invalid market midpoint");
             return None;
         }
 
+        // Leave resting quotes alone on small moves
+        let now = self.elapsed();
+        if let Some(quotes) = &self.outstanding_quotes {
+            let age = now.saturating_sub(self.quoted_at);
+            if !self
+                .params
+                .should_requote(self.quoted_midpoint, midpoint, age)
+            {
+                self.quotes_reused += 1;
+                return Some(quotes.clone());
+            }
+        }
+
         // Calculate spread and quote prices
         let spread = self.calculate_spread();
         let (bid_price, ask_price) = self.calculate_quote_prices(midpoint, spread);
@@ -226,15 +489,23 @@ impl<'a> MarketMaker<'a> {
         let buy_size = self.calculate_quote_size(true);
         let sell_size = self.calculate_quote_size(false);
 
-        // Determine best exchanges for each quote
+        // Determine best exchanges for each quote. Quotes rest, so they are
+        // routed as maker orders and venues paying a rebate are preferred.
         self.quotes_placed += 1;
         let buy_routing = self
             .sor
-            .route_order(self.quotes_placed, bid_price, buy_size, true);
+            .route_maker_order(self.quotes_placed, bid_price, buy_size, true);
         self.quotes_placed += 1;
-        let sell_routing = self
-            .sor
-            .route_order(self.quotes_placed, ask_price, sell_size, false);
+        let sell_routing =
+            self.sor
+                .route_maker_order(self.quotes_placed, ask_price, sell_size, false);
+        let (buy_routing, sell_routing) = match (buy_routing, sell_routing) {
+            (Ok(buy), Ok(sell)) => (buy, sell),
+            (Err(e), _) | (_, Err(e)) => {
+                warn!(error = %e, "Could not route quotes");
+                return None;
+            }
+        };
 
         // Create quotes
         let buy_quote = Quote::new(bid_price, buy_size, true, buy_routing.exchange_id);
@@ -244,55 +515,126 @@ impl<'a> MarketMaker<'a> {
         let theoretical_edge =
             (ask_price - bid_price) - (buy_routing.expected_fee + sell_routing.expected_fee);
 
-        Some(MarketMakerQuotes {
+        let quotes = MarketMakerQuotes {
             buy_quote,
             sell_quote,
             theoretical_edge,
-        })
+        };
+        self.outstanding_quotes = Some(quotes.clone());
+        self.quoted_midpoint = midpoint;
+        self.quoted_at = now;
+        Some(quotes)
+    }
+
+    // Time since the market maker was created; the clock check_heartbeat
+    // readings are taken on
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    // Pull all quotes if update_quotes isn't called again within `timeout`.
+    // None disables the switch. Setting a timeout counts as a heartbeat.
+    pub fn set_heartbeat_timeout(&mut self, timeout: Option<Duration>) {
+        self.heartbeat_timeout = timeout;
+        self.last_heartbeat = self.elapsed();
+        self.heartbeat_state = match timeout {
+            Some(_) => HeartbeatState::Alive,
+            None => HeartbeatState::Disabled,
+        };
+    }
+
+    // Check the deadline at clock reading `now` (see elapsed), cancelling
+    // the outstanding quotes once it has passed
+    pub fn check_heartbeat(&mut self, now: Duration) -> HeartbeatState {
+        if let (Some(timeout), HeartbeatState::Alive) =
+            (self.heartbeat_timeout, self.heartbeat_state)
+        {
+            if now.saturating_sub(self.last_heartbeat) > timeout {
+                self.heartbeat_state = HeartbeatState::Tripped;
+                if let Some(quotes) = self.outstanding_quotes.take() {
+                    self.quotes_pulled += 2;
+                    warn!(
+                        bid = quotes.buy_quote.price,
+                        ask = quotes.sell_quote.price,
+                        "Heartbeat missed, pulling quotes"
+                    );
+                }
+            }
+        }
+        self.heartbeat_state
+    }
+
+    pub fn heartbeat_state(&self) -> HeartbeatState {
+        self.heartbeat_state
+    }
+
+    // Quotes from the last update_quotes call, None once pulled
+    pub fn outstanding_quotes(&self) -> Option<&MarketMakerQuotes> {
+        self.outstanding_quotes.as_ref()
+    }
+
+    // update_quotes calls that kept the outstanding quotes
+    pub fn quotes_reused(&self) -> u32 {
+        self.quotes_reused
+    }
+
+    // Individual quotes cancelled by the heartbeat switch
+    pub fn quotes_pulled(&self) -> u32 {
+        self.quotes_pulled
     }
 
-    pub fn on_quote_filled(&mut self, filled_quote: &Quote, fill_price: f64, fill_quantity: u32) {
+    pub fn on_quote_filled(&mut self, filled_quote: &Quote, fill_price: f64, fill_quantity: Qty) {
         self.quotes_filled += 1;
-        self.total_volume += fill_quantity as f64;
+        // The fill consumed a quote and moved inventory, so the next update
+        // always re-quotes
+        self.outstanding_quotes = None;
+        let quantity = fill_quantity.to_base(self.params.units_per_base);
+        let notional = to_decimal(fill_price) * quantity;
+        self.total_volume += quantity;
 
         if filled_quote.is_buy_side {
             // We bought, increase base inventory, decrease quote inventory
-            self.base_inventory += fill_quantity as f64 / 100.0; // Convert from smallest unit
-            self.quote_inventory -= fill_price * fill_quantity as f64 / 100.0;
+            self.base_inventory += quantity;
+            self.quote_inventory -= notional;
 
-            println!(
-                "Buy quote filled: +{} BTC @ ${}",
-                fill_quantity as f64 / 100.0,
-                fill_price
-            );
+            info!(%quantity, price = fill_price, "Buy quote filled");
         } else {
             // We sold, decrease base inventory, increase quote inventory
-            self.base_inventory -= fill_quantity as f64 / 100.0;
-            self.quote_inventory += fill_price * fill_quantity as f64 / 100.0;
+            self.base_inventory -= quantity;
+            self.quote_inventory += notional;
 
-            println!(
-                "Sell quote filled: -{} BTC @ ${}",
-                fill_quantity as f64 / 100.0,
-                fill_price
-            );
+            info!(%quantity, price = fill_price, "Sell quote filled");
         }
 
         // Update realized PnL (simplified - assumes we can always close at midpoint)
-        let current_midpoint = self.last_midpoint;
+        let current_midpoint = to_decimal(self.last_midpoint);
         let position_value = self.base_inventory * current_midpoint + self.quote_inventory;
         let initial_value =
             self.initial_base_inventory * current_midpoint + self.initial_quote_inventory;
         self.realized_pnl = position_value - initial_value;
+
+        telemetry::set_mm_position(
+            self.base_inventory_f64(),
+            to_f64(self.quote_inventory),
+            to_f64(self.realized_pnl),
+        );
+    }
+
+    fn base_inventory_f64(&self) -> f64 {
+        to_f64(self.base_inventory)
     }
 
     pub fn is_within_risk_limits(&self) -> bool {
+        let base_inventory = self.base_inventory_f64();
+        let quote_inventory = to_f64(self.quote_inventory);
+
         // Check inventory limits
-        if self.base_inventory > self.params.max_base_inventory || self.base_inventory < 0.0 {
+        if base_inventory > self.params.max_base_inventory || base_inventory < 0.0 {
             return false;
         }
 
-        if self.quote_inventory > self.params.max_quote_inventory
-            || self.quote_inventory < -self.params.max_quote_inventory * 0.1
+        if quote_inventory > self.params.max_quote_inventory
+            || quote_inventory < -self.params.max_quote_inventory * 0.1
         {
             // Allow small negative
             return false;
@@ -300,7 +642,7 @@ impl<'a> MarketMaker<'a> {
 
         // Check position limits
         let current_midpoint = self.last_midpoint;
-        let position_value = (self.base_inventory * current_midpoint).abs();
+        let position_value = (base_inventory * current_midpoint).abs();
         let max_position_value = self.params.max_base_inventory * current_midpoint;
 
         position_value <= max_position_value * 1.1 // 10% buffer
@@ -312,12 +654,16 @@ impl<'a> MarketMaker<'a> {
             self.params.base_spread_bps *= 1.5;
             self.params.base_quote_size *= 0.5;
 
-            println!("Risk limits exceeded - adjusting parameters");
+            warn!(
+                base_spread_bps = self.params.base_spread_bps,
+                base_quote_size = self.params.base_quote_size,
+                "Risk limits exceeded - adjusting parameters"
+            );
         }
     }
 
     pub fn get_inventory_position(&self) -> InventoryPosition {
-        let current_midpoint = self.last_midpoint;
+        let current_midpoint = to_decimal(self.last_midpoint);
         let base_value = self.base_inventory * current_midpoint;
         let total_value = base_value + self.quote_inventory;
 
@@ -326,11 +672,11 @@ impl<'a> MarketMaker<'a> {
         let pnl = total_value - initial_value;
 
         InventoryPosition {
-            base_inventory: self.base_inventory,
-            quote_inventory: self.quote_inventory,
-            base_value,
-            total_value,
-            pnl,
+            base_inventory: to_f64(self.base_inventory),
+            quote_inventory: to_f64(self.quote_inventory),
+            base_value: to_f64(base_value),
+            total_value: to_f64(total_value),
+            pnl: to_f64(pnl),
         }
     }
 
@@ -339,7 +685,7 @@ impl<'a> MarketMaker<'a> {
             return 0.0;
         }
 
-        (self.base_inventory - self.params.target_base_inventory)
+        (self.base_inventory_f64() - self.params.target_base_inventory)
             / self.params.target_base_inventory
     }
 
@@ -354,40 +700,43 @@ impl<'a> MarketMaker<'a> {
     pub fn print_performance_stats(&self) {
         let duration = self.start_time.elapsed();
 
-        println!("\n=== Market Maker Performance Stats ===");
-        println!("Runtime: {} seconds", duration.as_secs());
-        println!("Quotes placed: {}", self.quotes_placed);
-        println!("Quotes filled: {}", self.quotes_filled);
-        println!("Fill rate: {:.1}%", self.get_fill_rate() * 100.0);
-        println!("Total volume: {:.2} BTC", self.total_volume / 100.0);
+        info!(
+            runtime_secs = duration.as_secs(),
+            quotes_placed = self.quotes_placed,
+            quotes_filled = self.quotes_filled,
+            fill_rate_pct = format_args!("{:.1}", self.get_fill_rate() * 100.0),
+            total_volume = %self.total_volume,
+            quotes_reused = self.quotes_reused,
+            quotes_pulled = self.quotes_pulled,
+            "Market maker activity"
+        );
 
         let pos = self.get_inventory_position();
-        println!("\nInventory Position:");
-        println!(
-            "  Base: {:.2} BTC (value: ${:.2})",
-            pos.base_inventory, pos.base_value
+        let initial_value = to_f64(
+            self.initial_base_inventory * to_decimal(self.last_midpoint)
+                + self.initial_quote_inventory,
         );
-        println!("  Quote: ${:.2}", pos.quote_inventory);
-        println!("  Total value: ${:.2}", pos.total_value);
-
-        let initial_value =
-            self.initial_base_inventory * self.last_midpoint + self.initial_quote_inventory;
-        if initial_value > 0.0 {
-            println!(
-                "  P&L: ${:.2} ({:.2}%)",
-                pos.pnl,
-                (pos.pnl / initial_value) * 100.0
-            );
+        let pnl_pct = if initial_value > 0.0 {
+            (pos.pnl / initial_value) * 100.0
         } else {
-            println!("  P&L: ${:.2}", pos.pnl);
-        }
+            0.0
+        };
+
+        info!(
+            base = format_args!("{:.2}", pos.base_inventory),
+            base_value = format_args!("{:.2}", pos.base_value),
+            quote = format_args!("{:.2}", pos.quote_inventory),
+            total_value = format_args!("{:.2}", pos.total_value),
+            pnl = format_args!("{:.2}", pos.pnl),
+            pnl_pct = format_args!("{:.2}", pnl_pct),
+            "Market maker inventory"
+        );
 
-        println!("\nCurrent Parameters:");
-        println!("  Base spread: {:.1} bps", self.params.base_spread_bps);
-        println!("  Quote size: {:.2} BTC", self.params.base_quote_size);
-        println!(
-            "  Inventory skew: {:.1}%",
-            self.calculate_inventory_skew() * 100.0
+        info!(
+            base_spread_bps = format_args!("{:.1}", self.params.base_spread_bps),
+            quote_size = format_args!("{:.2}", self.params.base_quote_size),
+            inventory_skew_pct = format_args!("{:.1}", self.calculate_inventory_skew() * 100.0),
+            "Market maker parameters"
         );
     }
 
@@ -416,6 +765,154 @@ impl<'a> MarketMaker<'a> {
     }
 
     pub fn get_realized_pnl(&self) -> f64 {
-        self.realized_pnl
+        to_f64(self.realized_pnl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_invalid(builder: MarketMakerParametersBuilder) {
+        let result = builder.build();
+        assert!(
+            matches!(result, Err(Error::InvalidConfig(_))),
+            "expected InvalidConfig, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn defaults_build() {
+        assert!(MarketMakerParameters::builder().build().is_ok());
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_fields() {
+        let setters: [fn(MarketMakerParametersBuilder, f64) -> MarketMakerParametersBuilder; 12] = [
+            MarketMakerParametersBuilder::base_spread_bps,
+            MarketMakerParametersBuilder::min_spread_bps,
+            MarketMakerParametersBuilder::max_spread_bps,
+            MarketMakerParametersBuilder::max_base_inventory,
+            MarketMakerParametersBuilder::max_quote_inventory,
+            MarketMakerParametersBuilder::target_base_inventory,
+            MarketMakerParametersBuilder::inventory_skew_factor,
+            MarketMakerParametersBuilder::volatility_adjustment,
+            MarketMakerParametersBuilder::base_quote_size,
+            MarketMakerParametersBuilder::min_quote_size,
+            MarketMakerParametersBuilder::max_quote_size,
+            MarketMakerParametersBuilder::requote_threshold_bps,
+        ];
+        for set in setters {
+            for value in [-1.0, f64::NAN, f64::INFINITY] {
+                assert_invalid(set(MarketMakerParameters::builder(), value));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_min_spread_above_max_spread() {
+        assert_invalid(
+            MarketMakerParameters::builder()
+                .min_spread_bps(60.0)
+                .max_spread_bps(50.0),
+        );
+    }
+
+    #[test]
+    fn rejects_base_spread_outside_its_range() {
+        // Below min_spread_bps (5.0) and above max_spread_bps (50.0)
+        assert_invalid(MarketMakerParameters::builder().base_spread_bps(4.0));
+        assert_invalid(MarketMakerParameters::builder().base_spread_bps(51.0));
+    }
+
+    #[test]
+    fn rejects_min_quote_size_above_max_quote_size() {
+        assert_invalid(
+            MarketMakerParameters::builder()
+                .min_quote_size(2.0)
+                .max_quote_size(1.0),
+        );
+    }
+
+    #[test]
+    fn rejects_base_quote_size_outside_its_range() {
+        // Below min_quote_size (0.01) and above max_quote_size (1.0)
+        assert_invalid(MarketMakerParameters::builder().base_quote_size(0.001));
+        assert_invalid(MarketMakerParameters::builder().base_quote_size(2.0));
+    }
+
+    #[test]
+    fn rejects_zero_units_per_base() {
+        assert_invalid(MarketMakerParameters::builder().units_per_base(0));
+    }
+
+    #[test]
+    fn rejects_zero_max_base_inventory() {
+        assert_invalid(
+            MarketMakerParameters::builder()
+                .target_base_inventory(0.0)
+                .max_base_inventory(0.0),
+        );
+    }
+
+    #[test]
+    fn rejects_target_inventory_above_max() {
+        assert_invalid(MarketMakerParameters::builder().target_base_inventory(11.0));
+    }
+
+    #[test]
+    fn units_round_trip_at_satoshi_scale() {
+        let params = MarketMakerParameters::builder()
+            .units_per_base(100_000_000)
+            .build()
+            .unwrap();
+        assert_eq!(params.to_units(0.12345678), Qty(12_345_678));
+        assert_eq!(params.from_units(Qty(12_345_678)), 0.12345678);
+        // Sub-satoshi remainders are truncated
+        assert_eq!(params.to_units(0.123456789), Qty(12_345_678));
+
+        // Fills are booked at the same scale
+        let sor = SmartOrderRouter::new(false, false);
+        let mut mm = MarketMaker::new(&sor, params);
+        mm.initialize(1.0, 10_000.0);
+        let quote = Quote::new(100.0, Qty(12_345_678), true, ExchangeID::Binance);
+        mm.on_quote_filled(&quote, 100.0, Qty(12_345_678));
+        let position = mm.get_inventory_position();
+        assert_eq!(position.base_inventory, 1.12345678);
+        assert_eq!(position.quote_inventory, 9_987.654322);
+    }
+
+    #[test]
+    fn units_round_trip_at_a_coarse_scale() {
+        // 0.1 base units per lot
+        let params = MarketMakerParameters::builder()
+            .units_per_base(10)
+            .min_quote_size(0.1)
+            .build()
+            .unwrap();
+        assert_eq!(params.to_units(0.5), Qty(5));
+        assert_eq!(params.to_units(0.59), Qty(5));
+        assert_eq!(params.from_units(Qty(5)), 0.5);
+    }
+
+    #[test]
+    fn inventory_accrues_exactly_over_many_fills() {
+        let sor = SmartOrderRouter::new(false, false);
+        let mut mm = MarketMaker::new(&sor, MarketMakerParameters::default());
+        mm.initialize(0.0, 10_000.0);
+        let buy = Quote::new(100.01, Qty(1), true, ExchangeID::Binance);
+
+        // 0.1 then 0.2 BTC is exactly 0.3
+        mm.on_quote_filled(&buy, 100.01, Qty(10));
+        mm.on_quote_filled(&buy, 100.01, Qty(20));
+        assert_eq!(mm.get_inventory_position().base_inventory, 0.3);
+
+        // Then a thousand 0.01 BTC fills at 100.01
+        for _ in 0..1_000 {
+            mm.on_quote_filled(&buy, 100.01, Qty(1));
+        }
+        let position = mm.get_inventory_position();
+        assert_eq!(position.base_inventory, 10.3);
+        assert_eq!(position.quote_inventory, 10_000.0 - 1_030.103);
     }
 }
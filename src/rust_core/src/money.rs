@@ -0,0 +1,172 @@
+// Exact decimal arithmetic for prices, quantities, fees and inventory.
+// Book keys and Qty are integers, and the running ledgers (market maker
+// inventory, SOR venue positions and volume, backtest balances and fees)
+// are Decimal. The public APIs of market_maker, smart_order_router and the
+// backtest still take and return f64: inputs are converted on the way in
+// with these helpers and results on the way out, so accumulated sums never
+// drift, but a value read back as f64 carries the usual rounding.
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+pub use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+// Prices are keyed in cents; quantities default to hundredths of a base unit
+pub const PRICE_DECIMALS: u32 = 2;
+pub const QUANTITY_DECIMALS: u32 = 2;
+
+// Shortest decimal that round-trips to `value`, so 100.01 becomes exactly
+// 100.01. NaN and infinities map to zero.
+pub fn to_decimal(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or_default()
+}
+
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(0.0)
+}
+
+// Default price scale: keys in cents
+pub const PRICE_SCALE: u64 = 10u64.pow(PRICE_DECIMALS);
+
+// Integer tick key for map ordering at the default scale. Sub-tick digits
+// are truncated, as before, but 100.01 no longer lands on 10000 because of
+// float error.
+pub fn price_to_tick(price: f64) -> u64 {
+    price_to_key(price, PRICE_SCALE)
+}
+
+// Tick key at `scale` ticks per unit of price (at least 1), e.g.
+// 100_000_000 for prices quoted to 1e-8
+// Zero means the price has no key: below one tick, or out of range.
+pub fn price_to_key(price: f64, scale: u64) -> u64 {
+    to_decimal(price)
+        .checked_mul(Decimal::from(scale.max(1)))
+        .and_then(|scaled| scaled.trunc().to_u64())
+        .unwrap_or(0)
+}
+
+pub fn key_to_price(key: u64, scale: u64) -> f64 {
+    key as f64 / scale.max(1) as f64
+}
+
+// Default Qty scale: hundredths of a base unit, the old u32 convention
+pub const QUANTITY_SCALE: u64 = 10u64.pow(QUANTITY_DECIMALS);
+
+// Fixed-point quantity: a whole number of lots, each 1 / scale of a base
+// unit. The scale is not stored in the value; it belongs to the book
+// (OrderBook::quantity_scale) or the strategy (units_per_base) that issued it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Qty(pub u64);
+
+impl Qty {
+    pub const ZERO: Qty = Qty(0);
+
+    // Whole lots in `base` base units, truncated, so 0.005 at scale 1000 is
+    // exactly 5 lots. Negative, NaN and infinite sizes map to zero.
+    pub fn from_base(base: f64, scale: u64) -> Self {
+        Qty::from_base_decimal(to_decimal(base), scale)
+    }
+
+    // from_base for an amount that is already exact
+    pub fn from_base_decimal(base: Decimal, scale: u64) -> Self {
+        Qty((base * Decimal::from(scale.max(1)))
+            .trunc()
+            .to_u64()
+            .unwrap_or(0))
+    }
+
+    pub fn to_base(self, scale: u64) -> Decimal {
+        Decimal::from(self.0) / Decimal::from(scale.max(1))
+    }
+
+    pub fn to_base_f64(self, scale: u64) -> f64 {
+        to_f64(self.to_base(scale))
+    }
+
+    pub fn lots(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn saturating_sub(self, other: Qty) -> Qty {
+        Qty(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<u32> for Qty {
+    fn from(lots: u32) -> Self {
+        Qty(lots as u64)
+    }
+}
+
+impl fmt::Display for Qty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Add for Qty {
+    type Output = Qty;
+
+    fn add(self, other: Qty) -> Qty {
+        Qty(self.0 + other.0)
+    }
+}
+
+impl Sub for Qty {
+    type Output = Qty;
+
+    fn sub(self, other: Qty) -> Qty {
+        Qty(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Qty {
+    fn add_assign(&mut self, other: Qty) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Qty {
+    fn sub_assign(&mut self, other: Qty) {
+        self.0 -= other.0;
+    }
+}
+
+impl Sum for Qty {
+    fn sum<I: Iterator<Item = Qty>>(iter: I) -> Qty {
+        Qty(iter.map(|qty| qty.0).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_sums_do_not_drift() {
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert_eq!(to_decimal(0.1) + to_decimal(0.2), to_decimal(0.3));
+
+        // A cent a hundred thousand times
+        let float: f64 = (0..100_000).map(|_| 0.01).sum();
+        let exact: Decimal = (0..100_000).map(|_| to_decimal(0.01)).sum();
+        assert_ne!(float, 1_000.0);
+        assert_eq!(exact, Decimal::from(1_000));
+    }
+
+    #[test]
+    fn price_keys_ignore_float_error() {
+        // 100.01 * 100 is 10000.999999999998 in f64
+        assert_eq!(price_to_tick(100.01), 10_001);
+        assert_eq!(price_to_key(0.1 + 0.2, 10), 3);
+        assert_eq!(Qty::from_base(0.29, QUANTITY_SCALE), Qty(29));
+    }
+}
@@ -1,62 +1,589 @@
+use crate::error::{Error, Result};
+use crate::money::{
+    key_to_price, price_to_key, price_to_tick, to_decimal, to_f64, Decimal, Qty, PRICE_SCALE,
+    QUANTITY_SCALE,
+};
+use crate::rate_limit::RateLimiter;
+use serde::{Deserialize, Serialize};
 use std::cmp::{min, Reverse};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub trade_id: u32,
     pub price: f64,
-    pub quantity: u32,
+    pub quantity: Qty,
     pub buy_order_id: u32,
     pub sell_order_id: u32,
+    // Side of the incoming order that took liquidity
+    #[serde(default)]
+    pub aggressor_is_buy: bool,
+    // The resting order is always the maker and the incoming one the taker;
+    // each is also one of buy_order_id/sell_order_id
+    #[serde(default)]
+    pub maker_order_id: u32,
+    #[serde(default)]
+    pub taker_order_id: u32,
+    // Execution time in nanoseconds, the incoming order's arrival time. All
+    // trades from one submission share it.
+    #[serde(default)]
+    pub timestamp: u64,
 }
 
 impl Trade {
     pub fn new(
         trade_id: u32,
         price: f64,
-        quantity: u32,
+        quantity: Qty,
         buy_order_id: u32,
         sell_order_id: u32,
+        aggressor_is_buy: bool,
+        timestamp: u64,
     ) -> Self {
+        let (taker_order_id, maker_order_id) = if aggressor_is_buy {
+            (buy_order_id, sell_order_id)
+        } else {
+            (sell_order_id, buy_order_id)
+        };
         Trade {
             trade_id,
             price,
             quantity,
             buy_order_id,
             sell_order_id,
+            aggressor_is_buy,
+            maker_order_id,
+            taker_order_id,
+            timestamp,
+        }
+    }
+}
+
+// Buy- vs sell-initiated volume over a set of trades
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeFlowSummary {
+    pub buy_volume: Qty,
+    pub sell_volume: Qty,
+    // Volume-weighted average price; 0.0 when there were no trades
+    pub vwap: f64,
+    pub trade_count: usize,
+    notional: f64,
+}
+
+impl TradeFlowSummary {
+    // Fold one more trade in, for callers that see trades in batches
+    pub fn record(&mut self, trade: &Trade) {
+        if trade.aggressor_is_buy {
+            self.buy_volume += trade.quantity;
+        } else {
+            self.sell_volume += trade.quantity;
+        }
+        self.trade_count += 1;
+        self.notional += trade.price * trade.quantity.lots() as f64;
+
+        let total_volume = self.total_volume();
+        if !total_volume.is_zero() {
+            self.vwap = self.notional / total_volume.lots() as f64;
+        }
+    }
+
+    pub fn total_volume(&self) -> Qty {
+        self.buy_volume + self.sell_volume
+    }
+}
+
+// Whether the best bid or best ask price moved. Quantity changes at an
+// unchanged best price don't count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookDelta {
+    pub bid_changed: bool,
+    pub ask_changed: bool,
+}
+
+impl BookDelta {
+    pub fn touch_changed(&self) -> bool {
+        self.bid_changed || self.ask_changed
+    }
+}
+
+// Execution report for one order, recorded when the event log is enabled.
+// Fill quantities are cumulative over the order's life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExecutionEvent {
+    // Accepted with its full quantity, before any matching
+    Added {
+        order_id: u32,
+        quantity: Qty,
+    },
+    PartiallyFilled {
+        order_id: u32,
+        filled: Qty,
+        remaining: Qty,
+    },
+    Filled {
+        order_id: u32,
+        filled: Qty,
+    },
+    // Cancelled by hand, pruned, cancelled as an OCO sibling, or the
+    // unfilled remainder of an IOC or FOK order
+    Cancelled {
+        order_id: u32,
+        remaining: Qty,
+    },
+}
+
+// Bounded buffer behind take_events. Once full, the oldest events are
+// dropped and counted.
+#[derive(Debug, Clone)]
+struct EventLog {
+    events: VecDeque<ExecutionEvent>,
+    capacity: usize,
+    dropped: u64,
+    // Cumulative fills of live orders that have traded
+    filled: HashMap<u32, Qty>,
+}
+
+impl EventLog {
+    fn new(capacity: usize) -> Self {
+        EventLog {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+            filled: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, event: ExecutionEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+
+    fn fill(&mut self, order_id: u32, quantity: Qty, remaining: Qty) {
+        let filled = *self
+            .filled
+            .entry(order_id)
+            .and_modify(|filled| *filled += quantity)
+            .or_insert(quantity);
+        if remaining.is_zero() {
+            self.filled.remove(&order_id);
+            self.push(ExecutionEvent::Filled { order_id, filled });
+        } else {
+            self.push(ExecutionEvent::PartiallyFilled {
+                order_id,
+                filled,
+                remaining,
+            });
         }
     }
+
+    fn cancel(&mut self, order_id: u32, remaining: Qty) {
+        self.filled.remove(&order_id);
+        self.push(ExecutionEvent::Cancelled {
+            order_id,
+            remaining,
+        });
+    }
 }
 
+// Most recent trades, oldest first. Stored with room for twice the
+// capacity so the newest `capacity` trades are always one contiguous slice;
+// the older half is dropped in one go when that fills.
 #[derive(Debug, Clone)]
+struct TradeTape {
+    trades: Vec<Trade>,
+    capacity: usize,
+}
+
+impl TradeTape {
+    fn new(capacity: usize) -> Self {
+        TradeTape {
+            trades: Vec::with_capacity(capacity * 2),
+            capacity,
+        }
+    }
+
+    fn extend(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            if self.trades.len() == self.capacity * 2 {
+                self.trades.drain(..self.capacity);
+            }
+            self.trades.push(trade.clone());
+        }
+    }
+
+    fn recent(&self, n: usize) -> &[Trade] {
+        let start = self.trades.len().saturating_sub(min(n, self.capacity));
+        &self.trades[start..]
+    }
+}
+
+pub fn summarize_trades(trades: &[Trade]) -> TradeFlowSummary {
+    let mut summary = TradeFlowSummary::default();
+    for trade in trades {
+        summary.record(trade);
+    }
+    summary
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     #[allow(dead_code)]
     pub order_id: u32,
     #[allow(dead_code)]
     pub price: f64,
-    pub quantity: u32,
+    pub quantity: Qty,
     #[allow(dead_code)]
     pub is_buy_side: bool,
+    // Good-til-date orders are removed by prune_expired once this passes
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    // Account for self-trade prevention; None never self-matches
+    #[serde(default)]
+    pub owner_id: Option<u32>,
+    // Iceberg slice size, zero for a fully displayed order. `quantity` is
+    // the visible slice; the reserve behind it is hidden_quantity.
+    #[serde(default)]
+    pub display_quantity: Qty,
+    #[serde(default)]
+    pub hidden_quantity: Qty,
+    // Arrival time in nanoseconds; each level keeps its orders oldest first
+    #[serde(default)]
+    pub timestamp: u64,
+    // Only ever reduces the owner's net position; trimmed as it shrinks
+    #[serde(default)]
+    pub reduce_only: bool,
+    // Fills at the midpoint under PriceImprovementMode::Midpoint; `price`
+    // is its limit
+    #[serde(default)]
+    pub midpoint_peg: bool,
 }
 
 impl Order {
-    pub fn new(order_id: u32, price: f64, quantity: u32, is_buy_side: bool) -> Self {
+    pub fn new(order_id: u32, price: f64, quantity: Qty, is_buy_side: bool) -> Self {
         Order {
             order_id,
             price,
             quantity,
             is_buy_side,
+            expires_at: None,
+            owner_id: None,
+            display_quantity: Qty::ZERO,
+            hidden_quantity: Qty::ZERO,
+            timestamp: 0,
+            reduce_only: false,
+            midpoint_peg: false,
+        }
+    }
+
+    // Visible plus hidden quantity
+    pub fn total_quantity(&self) -> Qty {
+        self.quantity + self.hidden_quantity
+    }
+
+    // Split `total` into a visible slice of up to display_quantity and the
+    // hidden rest
+    fn set_remaining(&mut self, total: Qty) {
+        self.quantity = if self.display_quantity.is_zero() {
+            total
+        } else {
+            min(self.display_quantity, total)
+        };
+        self.hidden_quantity = total - self.quantity;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeInForce {
+    // Rest until filled or cancelled
+    #[default]
+    GTC,
+    // Match what is available now, cancel the remainder
+    IOC,
+    // Fill the whole quantity immediately or do nothing
+    FOK,
+    // Rest only; rejected if it would take liquidity
+    PostOnly,
+    // Like GTC, but pruned once `expiry` has passed
+    GTD {
+        expiry: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub order_id: u32,
+    pub price: f64,
+    pub quantity: Qty,
+    pub is_buy_side: bool,
+    pub time_in_force: TimeInForce,
+    // Arrival time in nanoseconds; None stamps it from the book's clock
+    pub timestamp: Option<u64>,
+    pub owner_id: Option<u32>,
+    // Iceberg slice size; None displays the whole order
+    pub display_quantity: Option<Qty>,
+    // Capped at the owner's open position; requires an owner
+    pub reduce_only: bool,
+    pub midpoint_peg: bool,
+}
+
+impl OrderRequest {
+    pub fn new(order_id: u32, price: f64, quantity: Qty, is_buy_side: bool) -> Self {
+        OrderRequest {
+            order_id,
+            price,
+            quantity,
+            is_buy_side,
+            time_in_force: TimeInForce::GTC,
+            timestamp: None,
+            owner_id: None,
+            display_quantity: None,
+            reduce_only: false,
+            midpoint_peg: false,
+        }
+    }
+
+    // Rest as an iceberg showing `display_quantity` at a time. Matching on
+    // entry still uses the full quantity.
+    pub fn with_display_quantity(mut self, display_quantity: Qty) -> Self {
+        self.display_quantity = Some(display_quantity);
+        self
+    }
+
+    // Tag the order with an account for self-trade prevention
+    pub fn with_owner(mut self, owner_id: u32) -> Self {
+        self.owner_id = Some(owner_id);
+        self
+    }
+
+    // Trade and rest only against the owner's open position: a sell covers
+    // at most the long, a buy at most the short. Set an owner as well.
+    pub fn with_reduce_only(mut self) -> Self {
+        self.reduce_only = true;
+        self
+    }
+
+    // Once resting, fill at the lit midpoint when the book matches in
+    // PriceImprovementMode::Midpoint, never past `price`
+    pub fn with_midpoint_peg(mut self) -> Self {
+        self.midpoint_peg = true;
+        self
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    // Explicit arrival time, e.g. from a merged feed, for time priority
+    pub fn with_timestamp(mut self, timestamp_ns: u64) -> Self {
+        self.timestamp = Some(timestamp_ns);
+        self
+    }
+}
+
+// Outcome of a single submission
+#[derive(Debug, Clone)]
+pub struct OrderResult {
+    pub trades: Vec<Trade>,
+    pub filled_qty: Qty,
+    pub resting_qty: Qty, // Quantity left on the book
+    pub fully_filled: bool,
+    // Refused by the price band; nothing traded or rested
+    pub halted: bool,
+}
+
+impl OrderResult {
+    // Rejected before touching the book (invalid input, FOK short of
+    // liquidity, crossing post-only, an id that is already resting)
+    fn rejected() -> Self {
+        OrderResult {
+            trades: Vec::new(),
+            filled_qty: Qty::ZERO,
+            resting_qty: Qty::ZERO,
+            fully_filled: false,
+            halted: false,
         }
     }
+
+    fn halted() -> Self {
+        OrderResult {
+            halted: true,
+            ..Self::rejected()
+        }
+    }
+}
+
+pub type OcoId = u32;
+
+// A resting order priced `offset_ticks` behind the opposite touch: a bid
+// below the best ask, an ask above the best bid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peg {
+    pub offset_ticks: u64,
+    pub is_buy_side: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OcoLink {
+    oco_id: OcoId,
+    sibling: u32,
+}
+
+// Inactive until a trade prints at or through trigger_price (at or above
+// for a buy stop, at or below for a sell stop), then submitted as a limit
+// order at limit_price, or with no limit as a market order that sweeps
+// what the opposite side holds and never rests
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopOrder {
+    pub order_id: u32,
+    pub trigger_price: f64,
+    pub limit_price: Option<f64>,
+    pub quantity: Qty,
+    pub is_buy_side: bool,
+}
+
+impl StopOrder {
+    fn triggered_by(&self, trade_price: f64) -> bool {
+        if self.is_buy_side {
+            trade_price >= self.trigger_price
+        } else {
+            trade_price <= self.trigger_price
+        }
+    }
+}
+
+// (price, total quantity) per level, in priority order
+pub type DepthLevels = Vec<(f64, Qty)>;
+
+// Outcome of apply_depth_update
+#[derive(Debug, Clone, Default)]
+pub struct DepthUpdate {
+    // First id not used by this update; pass it to the next one
+    pub next_order_id: u32,
+    // Levels that crossed the other side trade like add_order
+    pub trades: Vec<Trade>,
+    // (price, quantity, is_buy_side) of levels a strict book refused
+    // because they would lock or cross; those prices are left empty
+    pub rejected: Vec<(f64, Qty, bool)>,
+}
+
+// One price level with the number of distinct orders resting on it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelInfo {
+    pub price: f64,
+    pub quantity: Qty,
+    pub order_count: usize,
+}
+
+// Serializable copy of the resting book. Orders are stored bids first then
+// asks, each in price-time priority, so restoring preserves queue position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub orders: Vec<Order>,
+    pub next_trade_id: u32,
+    // Snapshots taken before quantities carried a scale used hundredths
+    #[serde(default = "default_quantity_scale")]
+    pub quantity_scale: u64,
+    #[serde(default = "default_price_scale")]
+    pub price_scale: u64,
+}
+
+fn default_quantity_scale() -> u64 {
+    QUANTITY_SCALE
+}
+
+fn default_price_scale() -> u64 {
+    PRICE_SCALE
+}
+
+// Limit-up/limit-down band: trades more than `pct` percent from
+// `reference` are refused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceBand {
+    pub reference: f64,
+    pub pct: f64,
+}
+
+impl PriceBand {
+    pub fn contains(&self, price: f64) -> bool {
+        (price - self.reference).abs() <= self.reference * self.pct / 100.0
+    }
+}
+
+// What happens when an incoming order would trade against a resting order
+// with the same owner_id. Self-matched quantity never trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTradePrevention {
+    // Cancel the resting order and keep matching
+    CancelResting,
+    // Cancel whatever is left of the incoming order
+    CancelIncoming,
+    // Take the overlap out of both orders; either may keep the rest
+    DecrementBoth,
+}
+
+// Execution price when an incoming order crosses the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriceImprovementMode {
+    // Trade at the resting order's price
+    #[default]
+    MakerPrice,
+    // Resting midpoint-pegged orders trade at the midpoint of the lit touch
+    // (the best levels holding a non-pegged order), rounded to the tick in
+    // the resting order's favour. They fall back to their own price when a
+    // lit side is empty or the midpoint is outside either order's limit.
+    // Other resting orders always trade at their own price.
+    Midpoint,
 }
 
+// Called with each trade as it prints, before the submitting call returns
+pub type TradeObserver = Box<dyn FnMut(&Trade) + Send + Sync>;
+
 pub struct OrderBook {
-    buy_levels: BTreeMap<Reverse<u64>, u32>, // Price (as fixed point) -> Total quantity
-    sell_levels: BTreeMap<u64, u32>,         // Price (as fixed point) -> Total quantity
+    buy_levels: BTreeMap<Reverse<u64>, Qty>, // Price (as fixed point) -> Total quantity
+    sell_levels: BTreeMap<u64, Qty>,         // Price (as fixed point) -> Total quantity
     buy_orders_at_level: BTreeMap<Reverse<u64>, Vec<u32>>, // Price -> Order IDs
     sell_orders_at_level: BTreeMap<u64, Vec<u32>>, // Price -> Order IDs
     orders: HashMap<u32, Order>,             // Order ID -> Order details
     next_trade_id: u32,
+    // Lots per base unit for every quantity in this book
+    quantity_scale: u64,
+    // Price ticks per unit of price for the level keys
+    price_scale: u64,
+    // Applied to the checked entry points only
+    rate_limiter: Option<RateLimiter>,
+    strict: bool,
+    price_improvement: PriceImprovementMode,
+    // Sweep protection: price levels one incoming order may trade through
+    max_levels_per_match: Option<usize>,
+    // Circuit breaker; aggressive orders that would trade outside it halt
+    price_band: Option<PriceBand>,
+    // Off by default: owners may trade with themselves
+    self_trade_prevention: Option<SelfTradePrevention>,
+    // Touch moves since the last take_touch_delta
+    touch_delta: BookDelta,
+    // Off unless enabled with with_event_log
+    event_log: Option<EventLog>,
+    // Off unless enabled with with_trade_tape
+    trade_tape: Option<TradeTape>,
+    // OCO leg -> its sibling, both directions
+    oco_links: HashMap<u32, OcoLink>,
+    next_oco_id: OcoId,
+    // Untriggered stops, in arrival order
+    stop_orders: Vec<StopOrder>,
+    // Pegged order id -> its peg; moved by reprice_pegs
+    pegs: HashMap<u32, Peg>,
+    last_trade_price: Option<f64>,
+    // Net filled lots per owner, long positive, for reduce-only orders
+    positions: HashMap<u32, i64>,
+    trade_observer: Option<TradeObserver>,
+    // Monotonic clock for orders submitted without a timestamp
+    clock_origin: Instant,
+    last_timestamp: u64,
 }
 
 impl Default for OrderBook {
@@ -74,263 +601,2699 @@ impl OrderBook {
             sell_orders_at_level: BTreeMap::new(),
             orders: HashMap::new(),
             next_trade_id: 1,
+            quantity_scale: QUANTITY_SCALE,
+            price_scale: PRICE_SCALE,
+            rate_limiter: None,
+            strict: false,
+            price_improvement: PriceImprovementMode::default(),
+            max_levels_per_match: None,
+            price_band: None,
+            self_trade_prevention: None,
+            touch_delta: BookDelta::default(),
+            event_log: None,
+            trade_tape: None,
+            oco_links: HashMap::new(),
+            next_oco_id: 1,
+            stop_orders: Vec::new(),
+            pegs: HashMap::new(),
+            last_trade_price: None,
+            positions: HashMap::new(),
+            trade_observer: None,
+            clock_origin: Instant::now(),
+            last_timestamp: 0,
         }
     }
 
-    pub fn add_order(
-        &mut self,
-        order_id: u32,
-        price: f64,
-        quantity: u32,
-        is_buy_side: bool,
-    ) -> Vec<Trade> {
-        let mut trades = Vec::new();
-        let mut remaining_quantity = quantity;
-        let price_key = (price * 100.0) as u64;
+    // Count quantities in 1 / `scale` of a base unit (at least 1) instead of
+    // the default hundredths, e.g. 100_000_000 for satoshi-sized BTC orders.
+    // The book only stores whole lots; the scale tells callers how to
+    // convert with Qty::from_base and Qty::to_base.
+    pub fn with_quantity_scale(mut self, scale: u64) -> Self {
+        self.quantity_scale = scale.max(1);
+        self
+    }
 
-        // Matching logic
-        if is_buy_side {
-            // Match with sell orders
-            let mut levels_to_update = Vec::new();
+    pub fn quantity_scale(&self) -> u64 {
+        self.quantity_scale
+    }
 
-            // Collect price levels to process
-            let sell_prices: Vec<u64> = self.sell_levels.keys().copied().collect();
+    // Lots in `base` base units at this book's scale
+    pub fn quantity_from_base(&self, base: f64) -> Qty {
+        Qty::from_base(base, self.quantity_scale)
+    }
 
-            for sell_price_key in sell_prices {
-                if remaining_quantity == 0 || price < (sell_price_key as f64 / 100.0) {
-                    break;
-                }
+    // Key prices at 1 / `scale` (at least 1) instead of cents, e.g.
+    // 100_000_000 for satoshi-priced assets or FX. Digits finer than one
+    // tick are truncated. Resting orders are rekeyed at the new scale.
+    pub fn with_price_scale(mut self, scale: u64) -> Self {
+        let orders: Vec<Order> = self.iter_orders().cloned().collect();
+        self.clear_resting();
+        self.price_scale = scale.max(1);
+        for order in orders {
+            self.rest_order(order);
+        }
+        self
+    }
 
-                let match_price = sell_price_key as f64 / 100.0;
-                let order_ids = self
-                    .sell_orders_at_level
-                    .get(&sell_price_key)
-                    .cloned()
-                    .unwrap_or_default();
-                let mut orders_to_remove = Vec::new();
+    pub fn price_scale(&self) -> u64 {
+        self.price_scale
+    }
 
-                for &passive_order_id in &order_ids {
-                    if remaining_quantity == 0 {
-                        break;
-                    }
+    fn tick_key(&self, price: f64) -> u64 {
+        price_to_key(price, self.price_scale)
+    }
 
-                    if let Some(passive_order) = self.orders.get_mut(&passive_order_id) {
-                        let trade_quantity = min(remaining_quantity, passive_order.quantity);
+    fn key_price(&self, key: u64) -> f64 {
+        key_to_price(key, self.price_scale)
+    }
 
-                        // Create trade
-                        trades.push(Trade::new(
-                            self.next_trade_id,
-                            match_price,
-                            trade_quantity,
-                            order_id,
-                            passive_order_id,
-                        ));
-                        self.next_trade_id += 1;
+    // Cap try_add_order at `max_per_sec` orders per second, bursting up to
+    // one second's allowance
+    pub fn with_rate_limit(mut self, max_per_sec: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(max_per_sec));
+        self
+    }
 
-                        // Update quantities
-                        remaining_quantity -= trade_quantity;
-                        passive_order.quantity -= trade_quantity;
+    // Mirror mode for books rebuilt from market data: the checked entry
+    // points refuse any order that would lock or cross the book instead of
+    // matching it
+    pub fn with_strict_mode(mut self) -> Self {
+        self.strict = true;
+        self
+    }
 
-                        if passive_order.quantity == 0 {
-                            orders_to_remove.push(passive_order_id);
-                        }
-                    }
-                }
+    // Model venues that give the aggressor price improvement, e.g. midpoint
+    // matching during some auction phases
+    pub fn with_price_improvement(mut self, mode: PriceImprovementMode) -> Self {
+        self.price_improvement = mode;
+        self
+    }
 
-                // Remove filled orders
-                for &order_to_remove in &orders_to_remove {
-                    self.orders.remove(&order_to_remove);
-                    if let Some(order_list) = self.sell_orders_at_level.get_mut(&sell_price_key) {
-                        order_list.retain(|&id| id != order_to_remove);
-                    }
-                }
+    pub fn price_improvement(&self) -> PriceImprovementMode {
+        self.price_improvement
+    }
 
-                // Calculate remaining level quantity
-                let level_quantity: u32 = order_ids
-                    .iter()
-                    .filter(|&&id| !orders_to_remove.contains(&id))
-                    .filter_map(|&id| self.orders.get(&id))
-                    .map(|o| o.quantity)
-                    .sum();
+    // Stop an incoming order after it has traded through `levels` price
+    // levels (at least one). IOC and FOK remainders are cancelled; resting
+    // remainders are repriced to the last level traded so they never cross
+    // the book. Unbounded by default.
+    pub fn with_max_levels_per_match(mut self, levels: usize) -> Self {
+        self.max_levels_per_match = Some(levels.max(1));
+        self
+    }
 
-                levels_to_update.push((sell_price_key, level_quantity));
-            }
+    pub fn max_levels_per_match(&self) -> Option<usize> {
+        self.max_levels_per_match
+    }
 
-            // Update levels after iteration
-            for (price_key, quantity) in levels_to_update {
-                if quantity == 0 {
-                    self.sell_levels.remove(&price_key);
-                    self.sell_orders_at_level.remove(&price_key);
-                } else {
-                    self.sell_levels.insert(price_key, quantity);
-                }
-            }
-        } else {
-            // Match with buy orders
-            let mut levels_to_update = Vec::new();
+    // Stop orders with the same owner_id from trading with each other.
+    // Orders without an owner are never affected.
+    pub fn with_self_trade_prevention(mut self, policy: SelfTradePrevention) -> Self {
+        self.self_trade_prevention = Some(policy);
+        self
+    }
 
-            // Collect price levels to process
-            let buy_prices: Vec<Reverse<u64>> = self.buy_levels.keys().copied().collect();
+    pub fn self_trade_prevention(&self) -> Option<SelfTradePrevention> {
+        self.self_trade_prevention
+    }
 
-            for Reverse(buy_price_key) in buy_prices {
-                if remaining_quantity == 0 || price > (buy_price_key as f64 / 100.0) {
-                    break;
-                }
+    // Halt any incoming order that would trade more than `pct` percent away
+    // from `reference`. The whole order is refused rather than filled up to
+    // the band edge. Call again as the reference price moves.
+    pub fn set_price_band(&mut self, reference: f64, pct: f64) {
+        self.price_band = Some(PriceBand { reference, pct });
+    }
 
-                let match_price = buy_price_key as f64 / 100.0;
-                let order_ids = self
-                    .buy_orders_at_level
-                    .get(&Reverse(buy_price_key))
-                    .cloned()
-                    .unwrap_or_default();
-                let mut orders_to_remove = Vec::new();
+    pub fn clear_price_band(&mut self) {
+        self.price_band = None;
+    }
 
-                for &passive_order_id in &order_ids {
-                    if remaining_quantity == 0 {
-                        break;
-                    }
+    pub fn price_band(&self) -> Option<PriceBand> {
+        self.price_band
+    }
 
-                    if let Some(passive_order) = self.orders.get_mut(&passive_order_id) {
-                        let trade_quantity = min(remaining_quantity, passive_order.quantity);
+    // Stream trades to `observer` as they print, including trades from
+    // triggered stops. The usual Vec<Trade> return values are unaffected.
+    // Replaces any observer already set.
+    pub fn set_trade_observer(&mut self, observer: TradeObserver) {
+        self.trade_observer = Some(observer);
+    }
 
-                        // Create trade
-                        trades.push(Trade::new(
-                            self.next_trade_id,
-                            match_price,
-                            trade_quantity,
-                            passive_order_id,
-                            order_id,
-                        ));
-                        self.next_trade_id += 1;
+    pub fn clear_trade_observer(&mut self) {
+        self.trade_observer = None;
+    }
 
-                        // Update quantities
-                        remaining_quantity -= trade_quantity;
-                        passive_order.quantity -= trade_quantity;
+    // Record an ExecutionEvent for every order accepted, filled or cancelled,
+    // keeping at most `capacity` (at least one) until take_events drains them
+    pub fn with_event_log(mut self, capacity: usize) -> Self {
+        self.event_log = Some(EventLog::new(capacity.max(1)));
+        self
+    }
 
-                        if passive_order.quantity == 0 {
-                            orders_to_remove.push(passive_order_id);
-                        }
-                    }
-                }
+    // Events since the previous call, oldest first; empty if the log is off
+    pub fn take_events(&mut self) -> Vec<ExecutionEvent> {
+        self.event_log
+            .as_mut()
+            .map(|log| log.events.drain(..).collect())
+            .unwrap_or_default()
+    }
 
-                // Remove filled orders
-                for &order_to_remove in &orders_to_remove {
-                    self.orders.remove(&order_to_remove);
-                    if let Some(order_list) =
+    // Events lost because take_events was not called often enough
+    pub fn dropped_events(&self) -> u64 {
+        self.event_log.as_ref().map_or(0, |log| log.dropped)
+    }
+
+    // Keep the last `capacity` (at least one) trades for recent_trades
+    pub fn with_trade_tape(mut self, capacity: usize) -> Self {
+        self.trade_tape = Some(TradeTape::new(capacity.max(1)));
+        self
+    }
+
+    // Up to `n` of the most recent trades, oldest first. Empty unless the
+    // tape is enabled, and never more than its capacity.
+    pub fn recent_trades(&self, n: usize) -> &[Trade] {
+        self.trade_tape.as_ref().map_or(&[], |tape| tape.recent(n))
+    }
+
+    // Price of the most recent trade, including trades from stops
+    pub fn last_trade_price(&self) -> Option<f64> {
+        self.last_trade_price
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    // Checked entry point: rejects non-finite or non-positive prices, zero
+    // quantities and ids that are already resting instead of silently
+    // corrupting the book, and enforces the rate limit and strict mode when
+    // configured
+    pub fn try_add_order(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Result<Vec<Trade>> {
+        self.check_entry(None, order_id, price, quantity, is_buy_side)?;
+        Ok(self.add_order(order_id, price, quantity, is_buy_side))
+    }
+
+    // try_add_order against a caller-supplied clock, for simulated time
+    pub fn try_add_order_at(
+        &mut self,
+        now: Duration,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Result<Vec<Trade>> {
+        self.check_entry(Some(now), order_id, price, quantity, is_buy_side)?;
+        Ok(self.add_order(order_id, price, quantity, is_buy_side))
+    }
+
+    // Checked submit for any time-in-force: the try_add_order checks, and a
+    // post-only order that would take liquidity is refused with WouldCross
+    // instead of being dropped
+    pub fn try_submit(&mut self, request: OrderRequest) -> Result<OrderResult> {
+        let (price, is_buy_side) = (request.price, request.is_buy_side);
+        self.check_entry(None, request.order_id, price, request.quantity, is_buy_side)?;
+        if request.time_in_force == TimeInForce::PostOnly && self.would_cross(price, is_buy_side) {
+            return Err(Error::WouldCross { price, is_buy_side });
+        }
+        Ok(self.submit(request))
+    }
+
+    // Checks shared by the checked entry points; `now` of None reads the
+    // wall clock
+    fn check_entry(
+        &mut self,
+        now: Option<Duration>,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Result<()> {
+        self.validate_order(price, quantity)?;
+        if self.orders.contains_key(&order_id) {
+            return Err(Error::DuplicateOrderId(order_id));
+        }
+        self.check_limits(now, price, quantity, is_buy_side)
+    }
+
+    // Price band, strict mode and rate limit, for orders entering the book
+    // and orders amended into it
+    fn check_limits(
+        &mut self,
+        now: Option<Duration>,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Result<()> {
+        if let Some(breach) = self.band_breach(price, quantity, is_buy_side) {
+            return Err(Error::Halted {
+                price: breach,
+                reference: self.price_band.map_or(0.0, |band| band.reference),
+            });
+        }
+        if self.strict && self.would_cross(price, is_buy_side) {
+            return Err(Error::WouldCross { price, is_buy_side });
+        }
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            let acquired = match now {
+                Some(now) => limiter.try_acquire_at(now),
+                None => limiter.try_acquire(),
+            };
+            if !acquired {
+                return Err(Error::RateLimited {
+                    max_per_sec: limiter.max_per_sec(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // A price must also land on a non-zero tick, or it would key the level
+    // map at 0. There is no sentinel "market" price: f64::MAX is rejected.
+    fn validate_order(&self, price: f64, quantity: Qty) -> Result<()> {
+        self.validate_price(price)?;
+        if quantity.is_zero() {
+            return Err(Error::InvalidQuantity(quantity));
+        }
+        Ok(())
+    }
+
+    fn validate_price(&self, price: f64) -> Result<()> {
+        if !price.is_finite() || price <= 0.0 {
+            return Err(Error::InvalidPrice(price));
+        }
+        if self.tick_key(price) == 0 {
+            return Err(Error::UnrepresentablePrice {
+                price,
+                scale: self.price_scale,
+            });
+        }
+        Ok(())
+    }
+
+    // Fast path without the rate limit and strict mode. Invalid prices and
+    // quantities are dropped without trading; try_add_order reports why.
+    pub fn add_order(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Vec<Trade> {
+        self.add_order_detailed(order_id, price, quantity, is_buy_side)
+            .trades
+    }
+
+    // Same as add_order, but also reports how much filled and how much rested
+    pub fn add_order_detailed(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> OrderResult {
+        self.submit(OrderRequest::new(order_id, price, quantity, is_buy_side))
+    }
+
+    // Iceberg: match `total_quantity` on entry, then rest showing at most
+    // `display_quantity`, refilled from the hidden reserve at the back of
+    // the level each time the visible slice fills
+    pub fn add_iceberg_order(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        display_quantity: Qty,
+        total_quantity: Qty,
+        is_buy_side: bool,
+    ) -> Vec<Trade> {
+        self.submit(
+            OrderRequest::new(order_id, price, total_quantity, is_buy_side)
+                .with_display_quantity(display_quantity),
+        )
+        .trades
+    }
+
+    // IOC shorthand: match what is available at `price` or better and
+    // return the trades with the cancelled remainder
+    pub fn add_order_ioc(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> (Vec<Trade>, Qty) {
+        let result = self.submit(
+            OrderRequest::new(order_id, price, quantity, is_buy_side)
+                .with_time_in_force(TimeInForce::IOC),
+        );
+        (result.trades, quantity.saturating_sub(result.filled_qty))
+    }
+
+    // Reduce-only limit order for `owner_id`: matches normally, but trades and
+    // rests no more than the owner's open position in the other direction,
+    // so it can flatten but never flip. Rejected when there is nothing to
+    // close.
+    pub fn add_reduce_only_order(
+        &mut self,
+        order_id: u32,
+        owner_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Vec<Trade> {
+        self.submit(
+            OrderRequest::new(order_id, price, quantity, is_buy_side)
+                .with_owner(owner_id)
+                .with_reduce_only(),
+        )
+        .trades
+    }
+
+    // Sweep the opposite side at any price. Nothing ever rests: returns the
+    // trades and the quantity left unfilled when the side runs out.
+    pub fn add_market_order(
+        &mut self,
+        order_id: u32,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> (Vec<Trade>, Qty) {
+        let Some(request) = self.market_request(order_id, quantity, is_buy_side) else {
+            return (Vec::new(), quantity);
+        };
+        let result = self.submit(request);
+        (result.trades, quantity.saturating_sub(result.filled_qty))
+    }
+
+    // IOC limited at the far end of the opposite side, so it can take every
+    // level; None when that side is empty
+    fn market_request(
+        &self,
+        order_id: u32,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Option<OrderRequest> {
+        let (far_price, _) = if is_buy_side {
+            self.iter_asks().next_back()?
+        } else {
+            self.iter_bids().next_back()?
+        };
+        Some(
+            OrderRequest::new(order_id, far_price, quantity, is_buy_side)
+                .with_time_in_force(TimeInForce::IOC),
+        )
+    }
+
+    // Single entry point for every time-in-force. Trades from any stops the
+    // order triggers are appended after its own.
+    pub fn submit(&mut self, request: OrderRequest) -> OrderResult {
+        let touch = self.touch_keys();
+        let mut result = self.execute(request);
+        self.fire_stops(&mut result.trades);
+        self.note_touch(touch);
+        result
+    }
+
+    fn execute(&mut self, request: OrderRequest) -> OrderResult {
+        let OrderRequest {
+            order_id,
+            price,
+            quantity,
+            is_buy_side,
+            time_in_force,
+            timestamp,
+            owner_id,
+            display_quantity,
+            reduce_only,
+            midpoint_peg,
+        } = request;
+
+        // A second live order under one id would corrupt the level totals
+        if self.orders.contains_key(&order_id) || self.validate_order(price, quantity).is_err() {
+            return OrderResult::rejected();
+        }
+        // Reduce-only orders shrink to what the owner can still close out,
+        // net of its other reduce-only orders on that side
+        let quantity = match (reduce_only, owner_id) {
+            (false, _) => quantity,
+            (true, Some(owner_id)) => min(
+                quantity,
+                self.reducible_quantity(owner_id, is_buy_side)
+                    .saturating_sub(self.reduce_only_quantity(owner_id, is_buy_side)),
+            ),
+            (true, None) => Qty::ZERO,
+        };
+        if quantity.is_zero() {
+            return OrderResult::rejected();
+        }
+        if self.band_breach(price, quantity, is_buy_side).is_some() {
+            return OrderResult::halted();
+        }
+
+        match time_in_force {
+            TimeInForce::FOK if self.crossing_quantity(price, quantity, is_buy_side) < quantity => {
+                return OrderResult::rejected();
+            }
+            TimeInForce::PostOnly if self.would_cross(price, is_buy_side) => {
+                return OrderResult::rejected();
+            }
+            _ => {}
+        }
+
+        if let Some(log) = self.event_log.as_mut() {
+            log.push(ExecutionEvent::Added { order_id, quantity });
+        }
+
+        // Stamped before matching so the trades carry the arrival time
+        let timestamp = timestamp.unwrap_or_else(|| self.next_timestamp());
+        let (trades, remaining_quantity, halted_at, self_matched) =
+            self.match_order(order_id, price, quantity, is_buy_side, owner_id, timestamp);
+
+        // Add remaining quantity to book if not fully matched
+        let mut resting_qty = Qty::ZERO;
+        let rests = !matches!(time_in_force, TimeInForce::IOC | TimeInForce::FOK);
+        if !remaining_quantity.is_zero() && rests {
+            let rest_price = halted_at.unwrap_or(price);
+            let mut order = Order::new(order_id, rest_price, remaining_quantity, is_buy_side);
+            order.timestamp = timestamp;
+            order.owner_id = owner_id;
+            order.display_quantity = display_quantity.unwrap_or_default();
+            order.reduce_only = reduce_only;
+            order.midpoint_peg = midpoint_peg;
+            order.set_remaining(remaining_quantity);
+            if let TimeInForce::GTD { expiry } = time_in_force {
+                order.expires_at = Some(expiry);
+            }
+            self.rest_order(order);
+            resting_qty = remaining_quantity;
+        } else if !remaining_quantity.is_zero() {
+            if let Some(log) = self.event_log.as_mut() {
+                log.cancel(order_id, remaining_quantity);
+            }
+        } else if !self_matched.is_zero() {
+            if let Some(log) = self.event_log.as_mut() {
+                log.cancel(order_id, self_matched);
+            }
+        }
+
+        OrderResult {
+            trades,
+            filled_qty: quantity - remaining_quantity - self_matched,
+            resting_qty,
+            fully_filled: remaining_quantity.is_zero() && self_matched.is_zero(),
+            halted: false,
+        }
+    }
+
+    // One-cancels-other pair: once either leg fully fills, the other is
+    // cancelled. The legs are submitted in order, so a take-profit that fills
+    // on entry means the stop is never placed. Legs are ordinary requests and
+    // rest like any other order; returns the pair id and any immediate trades.
+    pub fn add_oco(
+        &mut self,
+        take_profit: OrderRequest,
+        stop: OrderRequest,
+    ) -> (OcoId, Vec<Trade>) {
+        let oco_id = self.next_oco_id;
+        self.next_oco_id += 1;
+        let (take_profit_id, stop_id) = (take_profit.order_id, stop.order_id);
+
+        let first = self.submit(take_profit);
+        let mut trades = first.trades;
+        if first.fully_filled {
+            return (oco_id, trades);
+        }
+
+        let second = self.submit(stop);
+        trades.extend(second.trades);
+        if second.fully_filled {
+            self.cancel_order(take_profit_id);
+        } else if !first.resting_qty.is_zero() && !second.resting_qty.is_zero() {
+            self.oco_links.insert(
+                take_profit_id,
+                OcoLink {
+                    oco_id,
+                    sibling: stop_id,
+                },
+            );
+            self.oco_links.insert(
+                stop_id,
+                OcoLink {
+                    oco_id,
+                    sibling: take_profit_id,
+                },
+            );
+        }
+
+        (oco_id, trades)
+    }
+
+    // Park a stop (limit_price Some) or stop-market (None) order until the
+    // last trade price reaches `trigger_price`. If the last trade is already
+    // there the stop fires at once, returning its trades. Ids already in use
+    // are ignored.
+    pub fn add_stop_order(
+        &mut self,
+        order_id: u32,
+        trigger_price: f64,
+        limit_price: Option<f64>,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Vec<Trade> {
+        if self.orders.contains_key(&order_id)
+            || self
+                .stop_orders
+                .iter()
+                .any(|stop| stop.order_id == order_id)
+        {
+            return Vec::new();
+        }
+
+        self.stop_orders.push(StopOrder {
+            order_id,
+            trigger_price,
+            limit_price,
+            quantity,
+            is_buy_side,
+        });
+        let touch = self.touch_keys();
+        let mut trades = Vec::new();
+        self.fire_stops(&mut trades);
+        self.note_touch(touch);
+        trades
+    }
+
+    pub fn pending_stop_orders(&self) -> &[StopOrder] {
+        &self.stop_orders
+    }
+
+    // Rest an order `offset_ticks` behind the opposite touch and keep it
+    // there through reprice_pegs. An offset of zero joins the opposite touch
+    // and trades. Ignored when the opposite side is empty, as there is
+    // nothing to peg to.
+    pub fn add_pegged_order(
+        &mut self,
+        order_id: u32,
+        offset_ticks: u64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Vec<Trade> {
+        let peg = Peg {
+            offset_ticks,
+            is_buy_side,
+        };
+        let Some(price) = self.peg_price(peg) else {
+            return Vec::new();
+        };
+
+        let result = self.submit(OrderRequest::new(order_id, price, quantity, is_buy_side));
+        if !result.resting_qty.is_zero() {
+            self.pegs.insert(order_id, peg);
+        }
+        result.trades
+    }
+
+    pub fn peg(&self, order_id: u32) -> Option<Peg> {
+        self.pegs.get(&order_id).copied()
+    }
+
+    // Move every pegged order whose reference touch has moved to its new
+    // price, at the back of that level. Call after updates that can shift
+    // the touch, e.g. when take_touch_delta reports a change. Pegs are
+    // visited in id order; one that crosses trades like a modify_order, and
+    // the trades are returned. Pegs whose opposite side is empty stay put.
+    pub fn reprice_pegs(&mut self) -> Vec<Trade> {
+        self.pegs
+            .retain(|order_id, _| self.orders.contains_key(order_id));
+        let mut pegged: Vec<(u32, Peg)> = self.pegs.iter().map(|(&id, &peg)| (id, peg)).collect();
+        pegged.sort_unstable_by_key(|&(order_id, _)| order_id);
+
+        let mut trades = Vec::new();
+        for (order_id, peg) in pegged {
+            let (Some(order), Some(price)) = (self.orders.get(&order_id), self.peg_price(peg))
+            else {
+                continue;
+            };
+            if self.tick_key(order.price) != self.tick_key(price) {
+                let quantity = order.total_quantity();
+                trades.extend(self.modify_order(order_id, price, quantity));
+            }
+        }
+        trades
+    }
+
+    // Price `peg` should rest at given the current opposite touch
+    fn peg_price(&self, peg: Peg) -> Option<f64> {
+        let key = if peg.is_buy_side {
+            let (&ask_key, _) = self.sell_levels.iter().next()?;
+            ask_key.checked_sub(peg.offset_ticks)?
+        } else {
+            let (&Reverse(bid_key), _) = self.buy_levels.iter().next()?;
+            bid_key.checked_add(peg.offset_ticks)?
+        };
+        (key > 0).then(|| self.key_price(key))
+    }
+
+    // Submit every stop the latest trade has reached, including stops the
+    // triggered orders' own trades reach in turn
+    fn fire_stops(&mut self, trades: &mut Vec<Trade>) {
+        let Some(mut last_price) = self.last_trade_price else {
+            return;
+        };
+
+        while let Some(index) = self
+            .stop_orders
+            .iter()
+            .position(|stop| stop.triggered_by(last_price))
+        {
+            let stop = self.stop_orders.remove(index);
+            let request = match stop.limit_price {
+                Some(limit_price) => {
+                    OrderRequest::new(stop.order_id, limit_price, stop.quantity, stop.is_buy_side)
+                }
+                None => {
+                    let Some(request) =
+                        self.market_request(stop.order_id, stop.quantity, stop.is_buy_side)
+                    else {
+                        continue;
+                    };
+                    request
+                }
+            };
+
+            let result = self.execute(request);
+            if let Some(last) = result.trades.last() {
+                last_price = last.price;
+            }
+            trades.extend(result.trades);
+        }
+    }
+
+    // Pair id of a resting OCO leg
+    pub fn oco_id(&self, order_id: u32) -> Option<OcoId> {
+        self.oco_links.get(&order_id).map(|link| link.oco_id)
+    }
+
+    // Cancel the sibling of every OCO leg the given trades filled completely
+    fn cancel_filled_oco_siblings(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            let passive_id = trade.maker_order_id;
+            if self.orders.contains_key(&passive_id) {
+                continue;
+            }
+            if let Some(link) = self.oco_links.remove(&passive_id) {
+                self.oco_links.remove(&link.sibling);
+                self.cancel_order(link.sibling);
+            }
+        }
+    }
+
+    fn would_cross(&self, price: f64, is_buy_side: bool) -> bool {
+        if is_buy_side {
+            self.get_best_ask().is_some_and(|ask| price >= ask)
+        } else {
+            self.get_best_bid().is_some_and(|bid| price <= bid)
+        }
+    }
+
+    // Opposite-side quantity an order at `price` could take, counted up to
+    // `limit`
+    fn crossing_quantity(&self, price: f64, limit: Qty, is_buy_side: bool) -> Qty {
+        let max_levels = self.max_levels_per_match.unwrap_or(usize::MAX);
+        let mut available = Qty::ZERO;
+        if is_buy_side {
+            for (&price_key, &quantity) in self.sell_levels.iter().take(max_levels) {
+                if available >= limit || price < self.key_price(price_key) {
+                    break;
+                }
+                available += quantity;
+            }
+        } else {
+            for (&Reverse(price_key), &quantity) in self.buy_levels.iter().take(max_levels) {
+                if available >= limit || price > self.key_price(price_key) {
+                    break;
+                }
+                available += quantity;
+            }
+        }
+        available
+    }
+
+    // Deepest level price an order at `price` would trade at, if that is
+    // outside the price band
+    fn band_breach(&self, price: f64, quantity: Qty, is_buy_side: bool) -> Option<f64> {
+        let band = self.price_band?;
+        let max_levels = self.max_levels_per_match.unwrap_or(usize::MAX);
+        let mut available = Qty::ZERO;
+        let mut deepest = None;
+        for (level_price, level_quantity) in self.iter_levels(!is_buy_side).take(max_levels) {
+            let crosses = if is_buy_side {
+                price >= level_price
+            } else {
+                price <= level_price
+            };
+            if available >= quantity || !crosses {
+                break;
+            }
+            available += level_quantity;
+            deepest = Some(level_price);
+        }
+        deepest.filter(|&level_price| !band.contains(level_price))
+    }
+
+    // Best bid and ask keys among levels holding at least one order that
+    // isn't midpoint-pegged; None if either side has no such level
+    fn lit_touch_keys(&self) -> Option<(u64, u64)> {
+        let lit = |ids: &Vec<u32>| {
+            ids.iter()
+                .any(|id| self.orders.get(id).is_some_and(|order| !order.midpoint_peg))
+        };
+        let (&Reverse(bid_key), _) = self.buy_orders_at_level.iter().find(|(_, ids)| lit(ids))?;
+        let (&ask_key, _) = self.sell_orders_at_level.iter().find(|(_, ids)| lit(ids))?;
+        Some((bid_key, ask_key))
+    }
+
+    // Midpoint of the lit touch for a midpoint-pegged resting order at
+    // `resting_key`, or None if it would trade past the resting order's
+    // limit or the incoming order's `limit`
+    fn midpoint_fill_price(
+        &self,
+        lit_touch: Option<(u64, u64)>,
+        resting_key: u64,
+        limit: f64,
+        resting_is_buy: bool,
+    ) -> Option<f64> {
+        let (bid_key, ask_key) = lit_touch?;
+        let mid_key = if resting_is_buy {
+            (bid_key + ask_key) / 2
+        } else {
+            (bid_key + ask_key).div_ceil(2)
+        };
+        let mid = self.key_price(mid_key);
+        let within_limits = if resting_is_buy {
+            mid_key <= resting_key && mid >= limit
+        } else {
+            mid_key >= resting_key && mid <= limit
+        };
+        within_limits.then_some(mid)
+    }
+
+    // Match an incoming order against the opposite side, returning the
+    // trades, the unmatched quantity, the price of the last level traded if
+    // max_levels_per_match stopped the sweep, and the incoming quantity
+    // removed by self-trade prevention. Trades are stamped with `timestamp`.
+    // Nothing is rested.
+    fn match_order(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+        owner_id: Option<u32>,
+        timestamp: u64,
+    ) -> (Vec<Trade>, Qty, Option<f64>, Qty) {
+        let mut trades = Vec::new();
+        let mut remaining_quantity = quantity;
+        let max_levels = self.max_levels_per_match.unwrap_or(usize::MAX);
+        let mut halted_at = None;
+
+        // Self-trade prevention only applies to owned orders
+        let stp = self.self_trade_prevention.filter(|_| owner_id.is_some());
+        let mut self_matched = Qty::ZERO;
+        // Resting orders removed by self-trade prevention, with the
+        // quantity they held
+        let mut self_cancelled = Vec::new();
+        // Fills of owned resting orders, for position tracking
+        let mut maker_fills = Vec::new();
+
+        // Lit touch before matching, for midpoint-pegged fills
+        let lit_touch = match self.price_improvement {
+            PriceImprovementMode::MakerPrice => None,
+            PriceImprovementMode::Midpoint => self.lit_touch_keys(),
+        };
+
+        // Matching logic
+        if is_buy_side {
+            // Match with sell orders
+            let mut levels_to_update = Vec::new();
+
+            // Collect price levels to process
+            let sell_prices: Vec<u64> = self.sell_levels.keys().copied().collect();
+
+            for sell_price_key in sell_prices {
+                if remaining_quantity.is_zero() || price < self.key_price(sell_price_key) {
+                    break;
+                }
+                if let Some(&(last_key, _)) = levels_to_update.last() {
+                    if levels_to_update.len() >= max_levels {
+                        halted_at = Some(self.key_price(last_key));
+                        break;
+                    }
+                }
+
+                let level_price = self.key_price(sell_price_key);
+                let peg_price = self
+                    .midpoint_fill_price(lit_touch, sell_price_key, price, false)
+                    .unwrap_or(level_price);
+                let mut order_ids = self
+                    .sell_orders_at_level
+                    .get(&sell_price_key)
+                    .cloned()
+                    .unwrap_or_default();
+                let mut orders_to_remove = Vec::new();
+
+                let mut next = 0;
+                while next < order_ids.len() {
+                    let passive_order_id = order_ids[next];
+                    next += 1;
+                    if remaining_quantity.is_zero() {
+                        break;
+                    }
+                    let mut refill = false;
+
+                    if let Some(passive_order) = self.orders.get_mut(&passive_order_id) {
+                        if let Some(policy) = stp.filter(|_| passive_order.owner_id == owner_id) {
+                            match policy {
+                                SelfTradePrevention::CancelResting => {
+                                    self_cancelled
+                                        .push((passive_order_id, passive_order.total_quantity()));
+                                    orders_to_remove.push(passive_order_id);
+                                }
+                                SelfTradePrevention::CancelIncoming => {
+                                    self_matched += remaining_quantity;
+                                    remaining_quantity = Qty::ZERO;
+                                }
+                                SelfTradePrevention::DecrementBoth => {
+                                    let overlap = min(remaining_quantity, passive_order.quantity);
+                                    remaining_quantity -= overlap;
+                                    self_matched += overlap;
+                                    passive_order.quantity -= overlap;
+                                    if passive_order.quantity.is_zero() {
+                                        if passive_order.hidden_quantity.is_zero() {
+                                            self_cancelled.push((passive_order_id, overlap));
+                                            orders_to_remove.push(passive_order_id);
+                                        } else {
+                                            refill = true;
+                                        }
+                                    }
+                                }
+                            }
+                            // An iceberg keeps its reserve, as after a fill
+                            if refill {
+                                self.replenish(passive_order_id);
+                                order_ids.push(passive_order_id);
+                            }
+                            continue;
+                        }
+
+                        let trade_quantity = min(remaining_quantity, passive_order.quantity);
+                        let match_price = if passive_order.midpoint_peg {
+                            peg_price
+                        } else {
+                            level_price
+                        };
+
+                        // Create trade
+                        trades.push(Trade::new(
+                            self.next_trade_id,
+                            match_price,
+                            trade_quantity,
+                            order_id,
+                            passive_order_id,
+                            true,
+                            timestamp,
+                        ));
+                        self.next_trade_id += 1;
+                        if let (Some(observer), Some(trade)) =
+                            (self.trade_observer.as_mut(), trades.last())
+                        {
+                            observer(trade);
+                        }
+                        if let Some(maker_owner) = passive_order.owner_id {
+                            maker_fills.push((maker_owner, trade_quantity));
+                        }
+
+                        // Update quantities
+                        remaining_quantity -= trade_quantity;
+                        passive_order.quantity -= trade_quantity;
+
+                        if passive_order.quantity.is_zero() {
+                            if passive_order.hidden_quantity.is_zero() {
+                                orders_to_remove.push(passive_order_id);
+                            } else {
+                                refill = true;
+                            }
+                        }
+                    }
+
+                    // The next iceberg slice joins the back of the level and
+                    // can still match this order
+                    if refill {
+                        self.replenish(passive_order_id);
+                        order_ids.push(passive_order_id);
+                    }
+                }
+
+                // Remove filled orders
+                for &order_to_remove in &orders_to_remove {
+                    self.orders.remove(&order_to_remove);
+                    if let Some(order_list) = self.sell_orders_at_level.get_mut(&sell_price_key) {
+                        order_list.retain(|&id| id != order_to_remove);
+                    }
+                }
+
+                // Calculate remaining level quantity
+                let level_quantity: Qty = self
+                    .sell_orders_at_level
+                    .get(&sell_price_key)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|id| self.orders.get(id))
+                    .map(|o| o.quantity)
+                    .sum();
+
+                levels_to_update.push((sell_price_key, level_quantity));
+            }
+
+            // Update levels after iteration
+            for (price_key, quantity) in levels_to_update {
+                if quantity.is_zero() {
+                    self.sell_levels.remove(&price_key);
+                    self.sell_orders_at_level.remove(&price_key);
+                } else {
+                    self.sell_levels.insert(price_key, quantity);
+                }
+            }
+        } else {
+            // Match with buy orders
+            let mut levels_to_update = Vec::new();
+
+            // Collect price levels to process
+            let buy_prices: Vec<Reverse<u64>> = self.buy_levels.keys().copied().collect();
+
+            for Reverse(buy_price_key) in buy_prices {
+                if remaining_quantity.is_zero() || price > self.key_price(buy_price_key) {
+                    break;
+                }
+                if let Some(&(Reverse(last_key), _)) = levels_to_update.last() {
+                    if levels_to_update.len() >= max_levels {
+                        halted_at = Some(self.key_price(last_key));
+                        break;
+                    }
+                }
+
+                let level_price = self.key_price(buy_price_key);
+                let peg_price = self
+                    .midpoint_fill_price(lit_touch, buy_price_key, price, true)
+                    .unwrap_or(level_price);
+                let mut order_ids = self
+                    .buy_orders_at_level
+                    .get(&Reverse(buy_price_key))
+                    .cloned()
+                    .unwrap_or_default();
+                let mut orders_to_remove = Vec::new();
+
+                let mut next = 0;
+                while next < order_ids.len() {
+                    let passive_order_id = order_ids[next];
+                    next += 1;
+                    if remaining_quantity.is_zero() {
+                        break;
+                    }
+                    let mut refill = false;
+
+                    if let Some(passive_order) = self.orders.get_mut(&passive_order_id) {
+                        if let Some(policy) = stp.filter(|_| passive_order.owner_id == owner_id) {
+                            match policy {
+                                SelfTradePrevention::CancelResting => {
+                                    self_cancelled
+                                        .push((passive_order_id, passive_order.total_quantity()));
+                                    orders_to_remove.push(passive_order_id);
+                                }
+                                SelfTradePrevention::CancelIncoming => {
+                                    self_matched += remaining_quantity;
+                                    remaining_quantity = Qty::ZERO;
+                                }
+                                SelfTradePrevention::DecrementBoth => {
+                                    let overlap = min(remaining_quantity, passive_order.quantity);
+                                    remaining_quantity -= overlap;
+                                    self_matched += overlap;
+                                    passive_order.quantity -= overlap;
+                                    if passive_order.quantity.is_zero() {
+                                        if passive_order.hidden_quantity.is_zero() {
+                                            self_cancelled.push((passive_order_id, overlap));
+                                            orders_to_remove.push(passive_order_id);
+                                        } else {
+                                            refill = true;
+                                        }
+                                    }
+                                }
+                            }
+                            // An iceberg keeps its reserve, as after a fill
+                            if refill {
+                                self.replenish(passive_order_id);
+                                order_ids.push(passive_order_id);
+                            }
+                            continue;
+                        }
+
+                        let trade_quantity = min(remaining_quantity, passive_order.quantity);
+                        let match_price = if passive_order.midpoint_peg {
+                            peg_price
+                        } else {
+                            level_price
+                        };
+
+                        // Create trade
+                        trades.push(Trade::new(
+                            self.next_trade_id,
+                            match_price,
+                            trade_quantity,
+                            passive_order_id,
+                            order_id,
+                            false,
+                            timestamp,
+                        ));
+                        self.next_trade_id += 1;
+                        if let (Some(observer), Some(trade)) =
+                            (self.trade_observer.as_mut(), trades.last())
+                        {
+                            observer(trade);
+                        }
+                        if let Some(maker_owner) = passive_order.owner_id {
+                            maker_fills.push((maker_owner, trade_quantity));
+                        }
+
+                        // Update quantities
+                        remaining_quantity -= trade_quantity;
+                        passive_order.quantity -= trade_quantity;
+
+                        if passive_order.quantity.is_zero() {
+                            if passive_order.hidden_quantity.is_zero() {
+                                orders_to_remove.push(passive_order_id);
+                            } else {
+                                refill = true;
+                            }
+                        }
+                    }
+
+                    // The next iceberg slice joins the back of the level and
+                    // can still match this order
+                    if refill {
+                        self.replenish(passive_order_id);
+                        order_ids.push(passive_order_id);
+                    }
+                }
+
+                // Remove filled orders
+                for &order_to_remove in &orders_to_remove {
+                    self.orders.remove(&order_to_remove);
+                    if let Some(order_list) =
                         self.buy_orders_at_level.get_mut(&Reverse(buy_price_key))
                     {
                         order_list.retain(|&id| id != order_to_remove);
                     }
                 }
 
-                // Calculate remaining level quantity
-                let level_quantity: u32 = order_ids
-                    .iter()
-                    .filter(|&&id| !orders_to_remove.contains(&id))
-                    .filter_map(|&id| self.orders.get(&id))
-                    .map(|o| o.quantity)
-                    .sum();
+                // Calculate remaining level quantity
+                let level_quantity: Qty = self
+                    .buy_orders_at_level
+                    .get(&Reverse(buy_price_key))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|id| self.orders.get(id))
+                    .map(|o| o.quantity)
+                    .sum();
+
+                levels_to_update.push((Reverse(buy_price_key), level_quantity));
+            }
+
+            // Update levels after iteration
+            for (price_key, quantity) in levels_to_update {
+                if quantity.is_zero() {
+                    self.buy_levels.remove(&price_key);
+                    self.buy_orders_at_level.remove(&price_key);
+                } else {
+                    self.buy_levels.insert(price_key, quantity);
+                }
+            }
+        }
+
+        self.log_fills(&trades, quantity);
+        for (cancelled_id, cancelled_quantity) in self_cancelled {
+            if let Some(log) = self.event_log.as_mut() {
+                log.cancel(cancelled_id, cancelled_quantity);
+            }
+            if let Some(link) = self.oco_links.remove(&cancelled_id) {
+                self.oco_links.remove(&link.sibling);
+            }
+        }
+        if !self.oco_links.is_empty() {
+            self.cancel_filled_oco_siblings(&trades);
+        }
+        if let Some(last) = trades.last() {
+            self.last_trade_price = Some(last.price);
+        }
+        if let Some(tape) = self.trade_tape.as_mut() {
+            tape.extend(&trades);
+        }
+        let taker_filled = quantity - remaining_quantity - self_matched;
+        if !maker_fills.is_empty() || (owner_id.is_some() && !taker_filled.is_zero()) {
+            self.update_positions(owner_id, is_buy_side, taker_filled, maker_fills);
+        }
+
+        (trades, remaining_quantity, halted_at, self_matched)
+    }
+
+    // Move owners' net positions by one incoming order's fills, then shrink
+    // any reduce-only orders the new positions no longer cover
+    fn update_positions(
+        &mut self,
+        taker: Option<u32>,
+        taker_is_buy: bool,
+        taker_filled: Qty,
+        maker_fills: Vec<(u32, Qty)>,
+    ) {
+        let mut changed = Vec::with_capacity(maker_fills.len() + 1);
+        if let Some(owner_id) = taker.filter(|_| !taker_filled.is_zero()) {
+            self.apply_fill(owner_id, taker_is_buy, taker_filled);
+            changed.push(owner_id);
+        }
+        for (owner_id, quantity) in maker_fills {
+            self.apply_fill(owner_id, !taker_is_buy, quantity);
+            changed.push(owner_id);
+        }
+
+        changed.sort_unstable();
+        changed.dedup();
+        for owner_id in changed {
+            self.trim_reduce_only(owner_id);
+        }
+    }
+
+    fn apply_fill(&mut self, owner_id: u32, is_buy_side: bool, quantity: Qty) {
+        let position = self.positions.entry(owner_id).or_insert(0);
+        let lots = quantity.lots() as i64;
+        if is_buy_side {
+            *position += lots;
+        } else {
+            *position -= lots;
+        }
+    }
+
+    // Open position an order on `is_buy_side` could close: the long for a
+    // sell, the short for a buy
+    fn reducible_quantity(&self, owner_id: u32, is_buy_side: bool) -> Qty {
+        let position = self.position(owner_id);
+        let open = if is_buy_side { -position } else { position };
+        Qty(open.max(0) as u64)
+    }
+
+    // Resting reduce-only quantity, hidden reserve included
+    fn reduce_only_quantity(&self, owner_id: u32, is_buy_side: bool) -> Qty {
+        self.orders
+            .values()
+            .filter(|order| {
+                order.reduce_only
+                    && order.owner_id == Some(owner_id)
+                    && order.is_buy_side == is_buy_side
+            })
+            .map(Order::total_quantity)
+            .sum()
+    }
+
+    // Cut an owner's reduce-only orders, newest first, until together they
+    // no longer exceed the position they can close
+    fn trim_reduce_only(&mut self, owner_id: u32) {
+        for is_buy_side in [true, false] {
+            let mut excess = self
+                .reduce_only_quantity(owner_id, is_buy_side)
+                .saturating_sub(self.reducible_quantity(owner_id, is_buy_side));
+            if excess.is_zero() {
+                continue;
+            }
+
+            let mut resting: Vec<(u64, u32, f64, Qty)> = self
+                .orders
+                .values()
+                .filter(|order| {
+                    order.reduce_only
+                        && order.owner_id == Some(owner_id)
+                        && order.is_buy_side == is_buy_side
+                })
+                .map(|order| {
+                    (
+                        order.timestamp,
+                        order.order_id,
+                        order.price,
+                        order.total_quantity(),
+                    )
+                })
+                .collect();
+            resting.sort_unstable_by_key(|&(timestamp, ..)| Reverse(timestamp));
+
+            for (_, order_id, price, total) in resting {
+                if excess.is_zero() {
+                    break;
+                }
+                let cut = min(excess, total);
+                self.modify_order(order_id, price, total - cut);
+                excess -= cut;
+            }
+        }
+    }
+
+    // Fill events for both sides of each trade of one incoming order
+    fn log_fills(&mut self, trades: &[Trade], quantity: Qty) {
+        let Some(log) = self.event_log.as_mut() else {
+            return;
+        };
+
+        let mut remaining = quantity;
+        for trade in trades {
+            let maker_remaining = self
+                .orders
+                .get(&trade.maker_order_id)
+                .map_or(Qty::ZERO, Order::total_quantity);
+            log.fill(trade.maker_order_id, trade.quantity, maker_remaining);
+
+            remaining -= trade.quantity;
+            log.fill(trade.taker_order_id, trade.quantity, remaining);
+        }
+    }
+
+    // Show the next slice of an iceberg whose visible quantity is used up,
+    // moving it to the back of its level. The caller recomputes the level
+    // total.
+    fn replenish(&mut self, order_id: u32) {
+        let timestamp = self.next_timestamp();
+        let Some(order) = self.orders.get_mut(&order_id) else {
+            return;
+        };
+        let hidden = order.hidden_quantity;
+        order.set_remaining(hidden);
+        order.timestamp = timestamp;
+
+        let price_key = price_to_key(order.price, self.price_scale);
+        let queue = if order.is_buy_side {
+            self.buy_orders_at_level.get_mut(&Reverse(price_key))
+        } else {
+            self.sell_orders_at_level.get_mut(&price_key)
+        };
+        if let Some(queue) = queue {
+            queue.retain(|&id| id != order_id);
+            queue.push(order_id);
+        }
+    }
+
+    // Nanoseconds since the book was created, strictly increasing and past
+    // any timestamp already resting
+    fn next_timestamp(&mut self) -> u64 {
+        let now = self.clock_origin.elapsed().as_nanos() as u64;
+        self.last_timestamp = now.max(self.last_timestamp + 1);
+        self.last_timestamp
+    }
+
+    // Place an order in its price level without matching, behind every
+    // order with the same or an earlier timestamp
+    fn rest_order(&mut self, order: Order) {
+        let price_key = self.tick_key(order.price);
+        self.last_timestamp = self.last_timestamp.max(order.timestamp);
+
+        let orders = &self.orders;
+        let queue = if order.is_buy_side {
+            *self.buy_levels.entry(Reverse(price_key)).or_default() += order.quantity;
+            self.buy_orders_at_level
+                .entry(Reverse(price_key))
+                .or_default()
+        } else {
+            *self.sell_levels.entry(price_key).or_default() += order.quantity;
+            self.sell_orders_at_level.entry(price_key).or_default()
+        };
+        let position = queue.partition_point(|id| {
+            orders
+                .get(id)
+                .is_some_and(|resting| resting.timestamp <= order.timestamp)
+        });
+        queue.insert(position, order.order_id);
+
+        self.orders.insert(order.order_id, order);
+    }
+
+    pub fn to_snapshot(&self) -> BookSnapshot {
+        BookSnapshot {
+            orders: self.iter_orders().cloned().collect(),
+            next_trade_id: self.next_trade_id,
+            quantity_scale: self.quantity_scale,
+            price_scale: self.price_scale,
+        }
+    }
+
+    // Rebuild a book from a snapshot. Orders are rested directly, in snapshot
+    // order, so no matching takes place.
+    pub fn from_snapshot(snapshot: BookSnapshot) -> Self {
+        let mut book = OrderBook::new()
+            .with_quantity_scale(snapshot.quantity_scale)
+            .with_price_scale(snapshot.price_scale);
+        for order in snapshot.orders {
+            book.rest_order(order);
+        }
+        book.next_trade_id = snapshot.next_trade_id;
+        book
+    }
+
+    // Apply one L2 depth update: every listed level replaces whatever rests
+    // at its price with a single order of the new quantity, and a zero
+    // quantity clears the level. Bids are applied before asks. New orders
+    // take ids counting up from `next_order_id`, skipping ids still resting.
+    // Every price is checked first, so an invalid one rejects the whole
+    // update and leaves the book untouched.
+    pub fn apply_depth_update(
+        &mut self,
+        bids: &[(f64, Qty)],
+        asks: &[(f64, Qty)],
+        next_order_id: u32,
+    ) -> Result<DepthUpdate> {
+        for &(price, _) in bids.iter().chain(asks) {
+            self.validate_price(price)?;
+        }
+
+        let mut update = DepthUpdate {
+            next_order_id,
+            ..DepthUpdate::default()
+        };
+        let levels = bids
+            .iter()
+            .map(|&(price, quantity)| (price, quantity, true))
+            .chain(
+                asks.iter()
+                    .map(|&(price, quantity)| (price, quantity, false)),
+            );
+        for (price, quantity, is_buy_side) in levels {
+            let resting = if is_buy_side {
+                self.get_bid_orders_at(price)
+            } else {
+                self.get_ask_orders_at(price)
+            };
+            for order_id in resting {
+                self.cancel_order(order_id);
+            }
+            if quantity.is_zero() {
+                continue;
+            }
+            if self.strict && self.would_cross(price, is_buy_side) {
+                update.rejected.push((price, quantity, is_buy_side));
+                continue;
+            }
+
+            while self.orders.contains_key(&update.next_order_id) {
+                update.next_order_id += 1;
+            }
+            let trades = self.add_order(update.next_order_id, price, quantity, is_buy_side);
+            update.trades.extend(trades);
+            update.next_order_id += 1;
+        }
+        Ok(update)
+    }
+
+    // Id the next trade will be given. Within one book, trade ids start at 1
+    // and go up by one per trade, never repeating. To keep ids unique across a
+    // session that replaces its book (resync, reseed, restore), carry the
+    // counter over to the new book.
+    pub fn trade_id_counter(&self) -> u32 {
+        self.next_trade_id
+    }
+
+    // Moving the counter backwards can reissue ids that were already used
+    pub fn set_trade_id_counter(&mut self, next_trade_id: u32) {
+        self.next_trade_id = next_trade_id;
+    }
+
+    // Bulk-load aggregated L2 levels as one resting order per entry. Ids are
+    // assigned 1, 2, ... in input order, bids first, so callers can track
+    // them. Levels are rested directly without matching, and a snapshot
+    // whose best bid is at or above its best ask is rejected.
+    pub fn from_levels(bids: &[(f64, Qty)], asks: &[(f64, Qty)]) -> Result<Self> {
+        let mut book = OrderBook::new();
+        for &(price, quantity) in bids.iter().chain(asks) {
+            book.validate_order(price, quantity)?;
+        }
+
+        let best_bid = bids.iter().map(|&(price, _)| price_to_tick(price)).max();
+        let best_ask = asks.iter().map(|&(price, _)| price_to_tick(price)).min();
+        if let (Some(bid), Some(ask)) = (best_bid, best_ask) {
+            if bid >= ask {
+                return Err(Error::CrossedLevels {
+                    best_bid: key_to_price(bid, PRICE_SCALE),
+                    best_ask: key_to_price(ask, PRICE_SCALE),
+                });
+            }
+        }
+
+        book.orders.reserve(bids.len() + asks.len());
+        let levels = bids
+            .iter()
+            .map(|&(price, quantity)| (price, quantity, true))
+            .chain(
+                asks.iter()
+                    .map(|&(price, quantity)| (price, quantity, false)),
+            );
+        for (order_id, (price, quantity, is_buy_side)) in (1..).zip(levels) {
+            let mut order = Order::new(order_id, price, quantity, is_buy_side);
+            order.timestamp = book.next_timestamp();
+            book.rest_order(order);
+        }
+        Ok(book)
+    }
+
+    // Returns the order as it rested, or None for an unknown id. Also
+    // cancels an untriggered stop, returned priced at its limit (or its
+    // trigger, for a stop-market).
+    pub fn cancel_order(&mut self, order_id: u32) -> Option<Order> {
+        if let Some(index) = self
+            .stop_orders
+            .iter()
+            .position(|stop| stop.order_id == order_id)
+        {
+            let stop = self.stop_orders.remove(index);
+            let price = stop.limit_price.unwrap_or(stop.trigger_price);
+            return Some(Order::new(order_id, price, stop.quantity, stop.is_buy_side));
+        }
+
+        let touch = self.touch_keys();
+        let cancelled = self.remove_order(order_id);
+        self.note_touch(touch);
+        cancelled
+    }
+
+    // cancel_order that reports an id with nothing to cancel
+    pub fn try_cancel_order(&mut self, order_id: u32) -> Result<Order> {
+        self.cancel_order(order_id)
+            .ok_or(Error::OrderNotFound(order_id))
+    }
+
+    // cancel_order for callers that only need to know whether it was live
+    pub fn cancel_order_exists(&mut self, order_id: u32) -> bool {
+        self.cancel_order(order_id).is_some()
+    }
+
+    // Empty the book for reuse, e.g. between backtest runs, keeping its
+    // configuration and allocations. Orders are dropped without cancel
+    // events and owner positions reset to flat. Trade ids carry on from
+    // where they were; call set_trade_id_counter(1) to restart them.
+    pub fn clear(&mut self) {
+        let touch = self.touch_keys();
+        self.clear_resting();
+        self.oco_links.clear();
+        self.stop_orders.clear();
+        self.pegs.clear();
+        self.last_trade_price = None;
+        if let Some(tape) = self.trade_tape.as_mut() {
+            tape.trades.clear();
+        }
+        self.positions.clear();
+        self.note_touch(touch);
+    }
+
+    fn clear_resting(&mut self) {
+        self.buy_levels.clear();
+        self.sell_levels.clear();
+        self.buy_orders_at_level.clear();
+        self.sell_orders_at_level.clear();
+        self.orders.clear();
+    }
+
+    // Cancel every resting order and pending stop on one side, e.g. to pull
+    // quotes on a risk event. Returns the cancelled ids.
+    pub fn cancel_all_for_side(&mut self, is_buy_side: bool) -> Vec<u32> {
+        let mut cancelled: Vec<u32> = self
+            .stop_orders
+            .iter()
+            .filter(|stop| stop.is_buy_side == is_buy_side)
+            .map(|stop| stop.order_id)
+            .collect();
+        self.stop_orders
+            .retain(|stop| stop.is_buy_side != is_buy_side);
+
+        let resting: Vec<u32> = self
+            .iter_orders()
+            .filter(|order| order.is_buy_side == is_buy_side)
+            .map(|order| order.order_id)
+            .collect();
+        let touch = self.touch_keys();
+        for &order_id in &resting {
+            self.remove_order(order_id);
+        }
+        self.note_touch(touch);
+
+        cancelled.extend(resting);
+        cancelled
+    }
+
+    fn remove_order(&mut self, order_id: u32) -> Option<Order> {
+        // Cancelling one OCO leg by hand leaves the other as a plain order
+        if let Some(link) = self.oco_links.remove(&order_id) {
+            self.oco_links.remove(&link.sibling);
+        }
+
+        let order = self.unrest_order(order_id)?;
+        if let Some(log) = self.event_log.as_mut() {
+            log.cancel(order_id, order.total_quantity());
+        }
+        Some(order)
+    }
+
+    // Take a resting order off its price level, undoing rest_order
+    fn unrest_order(&mut self, order_id: u32) -> Option<Order> {
+        let order = self.orders.remove(&order_id)?;
+        let price_key = self.tick_key(order.price);
 
-                levels_to_update.push((Reverse(buy_price_key), level_quantity));
+        if order.is_buy_side {
+            if let Some(level) = self.buy_levels.get_mut(&Reverse(price_key)) {
+                *level = level.saturating_sub(order.quantity);
+                if level.is_zero() {
+                    self.buy_levels.remove(&Reverse(price_key));
+                }
             }
 
-            // Update levels after iteration
-            for (price_key, quantity) in levels_to_update {
-                if quantity == 0 {
-                    self.buy_levels.remove(&price_key);
-                    self.buy_orders_at_level.remove(&price_key);
-                } else {
-                    self.buy_levels.insert(price_key, quantity);
+            if let Some(order_list) = self.buy_orders_at_level.get_mut(&Reverse(price_key)) {
+                order_list.retain(|&id| id != order_id);
+                if order_list.is_empty() {
+                    self.buy_orders_at_level.remove(&Reverse(price_key));
+                }
+            }
+        } else {
+            if let Some(level) = self.sell_levels.get_mut(&price_key) {
+                *level = level.saturating_sub(order.quantity);
+                if level.is_zero() {
+                    self.sell_levels.remove(&price_key);
+                }
+            }
+
+            if let Some(order_list) = self.sell_orders_at_level.get_mut(&price_key) {
+                order_list.retain(|&id| id != order_id);
+                if order_list.is_empty() {
+                    self.sell_orders_at_level.remove(&price_key);
                 }
             }
         }
+        Some(order)
+    }
 
-        // Add remaining quantity to book if not fully matched
-        if remaining_quantity > 0 {
-            let order = Order::new(order_id, price, remaining_quantity, is_buy_side);
-
-            if is_buy_side {
-                *self.buy_levels.entry(Reverse(price_key)).or_insert(0) += remaining_quantity;
-                self.buy_orders_at_level
-                    .entry(Reverse(price_key))
-                    .or_default()
-                    .push(order_id);
+    // Amend a resting order in place, keeping its id. Reducing the size at
+    // the same price keeps its queue position; a price change or size
+    // increase sends it to the back of the new level, matching first if the
+    // new price crosses. A zero quantity cancels. Like add_order this is the
+    // fast path: unknown ids and invalid prices are ignored, and the rate
+    // limit and strict mode don't apply; try_modify_order reports why.
+    pub fn modify_order(&mut self, order_id: u32, new_price: f64, new_quantity: Qty) -> Vec<Trade> {
+        let Some(order) = self.orders.get(&order_id) else {
+            return Vec::new();
+        };
+        if new_quantity.is_zero() {
+            self.cancel_order(order_id);
+            return Vec::new();
+        }
+        if self.validate_price(new_price).is_err() {
+            return Vec::new();
+        }
+
+        let touch = self.touch_keys();
+        let price_key = self.tick_key(order.price);
+        let is_buy_side = order.is_buy_side;
+
+        if self.tick_key(new_price) == price_key && new_quantity <= order.total_quantity() {
+            // Icebergs give up hidden reserve before visible quantity
+            let cut = order.total_quantity() - new_quantity;
+            let from_hidden = min(cut, order.hidden_quantity);
+            let reduction = cut - from_hidden;
+            if let Some(order) = self.orders.get_mut(&order_id) {
+                order.hidden_quantity -= from_hidden;
+                order.quantity -= reduction;
+            }
+            let level = if is_buy_side {
+                self.buy_levels.get_mut(&Reverse(price_key))
             } else {
-                *self.sell_levels.entry(price_key).or_insert(0) += remaining_quantity;
-                self.sell_orders_at_level
-                    .entry(price_key)
-                    .or_default()
-                    .push(order_id);
+                self.sell_levels.get_mut(&price_key)
+            };
+            if let Some(level) = level {
+                *level = level.saturating_sub(reduction);
             }
-
-            self.orders.insert(order_id, order);
+            self.note_touch(touch);
+            return Vec::new();
         }
 
+        let Some(order) = self.unrest_order(order_id) else {
+            return Vec::new();
+        };
+        let timestamp = self.next_timestamp();
+        let (mut trades, remaining_quantity, halted_at, _) = self.match_order(
+            order_id,
+            new_price,
+            new_quantity,
+            is_buy_side,
+            order.owner_id,
+            timestamp,
+        );
+        if !remaining_quantity.is_zero() {
+            let mut order = Order {
+                price: halted_at.unwrap_or(new_price),
+                timestamp,
+                ..order
+            };
+            order.set_remaining(remaining_quantity);
+            let reduce_only_owner = order.owner_id.filter(|_| order.reduce_only);
+            self.rest_order(order);
+            if let Some(owner_id) = reduce_only_owner {
+                self.trim_reduce_only(owner_id);
+            }
+        }
+        self.fire_stops(&mut trades);
+        self.note_touch(touch);
         trades
     }
 
-    #[allow(dead_code)]
-    pub fn cancel_order(&mut self, order_id: u32) -> bool {
-        if let Some(order) = self.orders.remove(&order_id) {
-            let price_key = (order.price * 100.0) as u64;
-
-            if order.is_buy_side {
-                if let Some(level) = self.buy_levels.get_mut(&Reverse(price_key)) {
-                    *level = level.saturating_sub(order.quantity);
-                    if *level == 0 {
-                        self.buy_levels.remove(&Reverse(price_key));
-                    }
-                }
+    // Checked modify_order: reports unknown ids and invalid prices instead of
+    // ignoring them, and applies the try_add_order limits to any amendment
+    // that keeps the order live. Cancelling with a zero quantity is never
+    // limited.
+    pub fn try_modify_order(
+        &mut self,
+        order_id: u32,
+        new_price: f64,
+        new_quantity: Qty,
+    ) -> Result<Vec<Trade>> {
+        let Some(order) = self.orders.get(&order_id) else {
+            return Err(Error::OrderNotFound(order_id));
+        };
+        let is_buy_side = order.is_buy_side;
+        self.validate_price(new_price)?;
+        if !new_quantity.is_zero() {
+            self.check_limits(None, new_price, new_quantity, is_buy_side)?;
+        }
+        Ok(self.modify_order(order_id, new_price, new_quantity))
+    }
 
-                if let Some(order_list) = self.buy_orders_at_level.get_mut(&Reverse(price_key)) {
-                    order_list.retain(|&id| id != order_id);
-                    if order_list.is_empty() {
-                        self.buy_orders_at_level.remove(&Reverse(price_key));
-                    }
-                }
-            } else {
-                if let Some(level) = self.sell_levels.get_mut(&price_key) {
-                    *level = level.saturating_sub(order.quantity);
-                    if *level == 0 {
-                        self.sell_levels.remove(&price_key);
-                    }
-                }
+    // Cancel every good-til-date order whose expiry is at or before `now`,
+    // returning the removed ids
+    pub fn prune_expired(&mut self, now: u64) -> Vec<u32> {
+        let expired: Vec<u32> = self
+            .orders
+            .values()
+            .filter(|order| order.expires_at.is_some_and(|expiry| expiry <= now))
+            .map(|order| order.order_id)
+            .collect();
 
-                if let Some(order_list) = self.sell_orders_at_level.get_mut(&price_key) {
-                    order_list.retain(|&id| id != order_id);
-                    if order_list.is_empty() {
-                        self.sell_orders_at_level.remove(&price_key);
-                    }
-                }
-            }
-            true
-        } else {
-            false
+        for &order_id in &expired {
+            self.cancel_order(order_id);
+        }
+        expired
+    }
+
+    // Whether the best bid or ask moved since the previous call (or since the
+    // book was created), across every order added, matched, cancelled or
+    // pruned in between. Take it before and after a single call to learn
+    // whether that call moved the touch.
+    pub fn take_touch_delta(&mut self) -> BookDelta {
+        std::mem::take(&mut self.touch_delta)
+    }
+
+    fn touch_keys(&self) -> (Option<u64>, Option<u64>) {
+        (
+            self.buy_levels.keys().next().map(|&Reverse(key)| key),
+            self.sell_levels.keys().next().copied(),
+        )
+    }
+
+    fn note_touch(&mut self, (bid_before, ask_before): (Option<u64>, Option<u64>)) {
+        let (bid, ask) = self.touch_keys();
+        self.touch_delta.bid_changed |= bid != bid_before;
+        self.touch_delta.ask_changed |= ask != ask_before;
+    }
+
+    // Best bid at or above best ask. Matching never leaves the book in this
+    // state; it shows up in books loaded or mirrored from outside data.
+    pub fn is_crossed(&self) -> bool {
+        match (
+            self.buy_levels.first_key_value(),
+            self.sell_levels.first_key_value(),
+        ) {
+            (Some((Reverse(bid), _)), Some((ask, _))) => bid >= ask,
+            _ => false,
         }
     }
 
     pub fn get_best_bid(&self) -> Option<f64> {
         self.buy_levels
             .first_key_value()
-            .map(|(Reverse(price_key), _)| *price_key as f64 / 100.0)
+            .map(|(Reverse(price_key), _)| self.key_price(*price_key))
     }
 
     pub fn get_best_ask(&self) -> Option<f64> {
         self.sell_levels
             .first_key_value()
-            .map(|(price_key, _)| *price_key as f64 / 100.0)
+            .map(|(price_key, _)| self.key_price(*price_key))
+    }
+
+    // Top `levels` price levels per side as (price, total quantity): bids
+    // highest first, asks lowest first
+    pub fn get_depth(&self, levels: usize) -> (DepthLevels, DepthLevels) {
+        let bids = self.iter_levels(true).take(levels).collect();
+        let asks = self.iter_levels(false).take(levels).collect();
+        (bids, asks)
+    }
+
+    // As get_depth, with the length of each level's order queue
+    pub fn get_depth_with_counts(&self, levels: usize) -> (Vec<LevelInfo>, Vec<LevelInfo>) {
+        let bids = self
+            .buy_orders_at_level
+            .iter()
+            .take(levels)
+            .map(|(&Reverse(price_key), order_list)| LevelInfo {
+                price: self.key_price(price_key),
+                quantity: self
+                    .buy_levels
+                    .get(&Reverse(price_key))
+                    .copied()
+                    .unwrap_or_default(),
+                order_count: order_list.len(),
+            })
+            .collect();
+        let asks = self
+            .sell_orders_at_level
+            .iter()
+            .take(levels)
+            .map(|(&price_key, order_list)| LevelInfo {
+                price: self.key_price(price_key),
+                quantity: self
+                    .sell_levels
+                    .get(&price_key)
+                    .copied()
+                    .unwrap_or_default(),
+                order_count: order_list.len(),
+            })
+            .collect();
+        (bids, asks)
+    }
+
+    // Kraken's book checksum: CRC32 over the top `levels` asks, best first,
+    // then the top `levels` bids, best first. Each level is its price then
+    // its quantity with the decimal point and leading zeros removed, which
+    // is the price in whole ticks followed by the quantity in whole lots
+    // (45285.2 x 0.001 is "452852100000" at scales 10 and 100_000_000). It
+    // only matches the venue when the book's scales are the pair's
+    // precisions.
+    pub fn checksum(&self, levels: usize) -> u32 {
+        let asks = self
+            .sell_levels
+            .iter()
+            .map(|(&key, &quantity)| (key, quantity));
+        let bids = self
+            .buy_levels
+            .iter()
+            .map(|(&Reverse(key), &quantity)| (key, quantity));
+        let mut text = String::new();
+        for (price_key, quantity) in asks.take(levels).chain(bids.take(levels)) {
+            text.push_str(&format!("{price_key}{}", quantity.lots()));
+        }
+        crc32(text.as_bytes())
+    }
+
+    // Every resting order, bids then asks, each in price-time priority
+    pub fn iter_orders(&self) -> impl Iterator<Item = &Order> {
+        self.buy_orders_at_level
+            .values()
+            .chain(self.sell_orders_at_level.values())
+            .flatten()
+            .filter_map(|order_id| self.orders.get(order_id))
+    }
+
+    // Price and total quantity per bid level, highest price first. Borrows
+    // the book and converts keys to prices lazily, so it never allocates.
+    pub fn iter_bids(&self) -> impl DoubleEndedIterator<Item = (f64, Qty)> + '_ {
+        self.buy_levels
+            .iter()
+            .map(|(Reverse(price_key), &quantity)| (self.key_price(*price_key), quantity))
+    }
+
+    // Ask levels, lowest price first
+    pub fn iter_asks(&self) -> impl DoubleEndedIterator<Item = (f64, Qty)> + '_ {
+        self.sell_levels
+            .iter()
+            .map(|(&price_key, &quantity)| (self.key_price(price_key), quantity))
+    }
+
+    // Either side, best first, for callers that pick the side at runtime
+    pub fn iter_levels(&self, is_buy_side: bool) -> Box<dyn Iterator<Item = (f64, Qty)> + '_> {
+        if is_buy_side {
+            Box::new(self.iter_bids())
+        } else {
+            Box::new(self.iter_asks())
+        }
+    }
+
+    // Depth grouped into `tick_bucket`-wide price buckets, up to `levels`
+    // buckets per side. Bids are floored and asks ceiled to the bucket
+    // boundary, so a bucket never claims a better price than its contents.
+    pub fn get_aggregated_depth_by_tick(
+        &self,
+        levels: usize,
+        tick_bucket: f64,
+    ) -> (DepthLevels, DepthLevels) {
+        let bucket = self.tick_key(tick_bucket).max(1);
+
+        let mut bids = Vec::new();
+        let mut next_bid = self.buy_levels.keys().next().map(|&Reverse(key)| key);
+        while let Some(top) = next_bid {
+            if bids.len() == levels {
+                break;
+            }
+            let floor = top / bucket * bucket;
+            let quantity: Qty = self
+                .buy_levels
+                .range(Reverse(top)..=Reverse(floor))
+                .map(|(_, &quantity)| quantity)
+                .sum();
+            bids.push((self.key_price(floor), quantity));
+
+            next_bid = match floor.checked_sub(1) {
+                Some(below) => self
+                    .buy_levels
+                    .range(Reverse(below)..)
+                    .next()
+                    .map(|(&Reverse(key), _)| key),
+                None => None,
+            };
+        }
+
+        let mut asks = Vec::new();
+        let mut next_ask = self.sell_levels.keys().next().copied();
+        while let Some(top) = next_ask {
+            if asks.len() == levels {
+                break;
+            }
+            let ceiling = top.div_ceil(bucket) * bucket;
+            let quantity: Qty = self
+                .sell_levels
+                .range(top..=ceiling)
+                .map(|(_, &quantity)| quantity)
+                .sum();
+            asks.push((self.key_price(ceiling), quantity));
+
+            next_ask = self
+                .sell_levels
+                .range(ceiling + 1..)
+                .next()
+                .map(|(&key, _)| key);
+        }
+
+        (bids, asks)
+    }
+
+    // Quantity and notional an aggressor would have to take to move the
+    // touch to `target_price`: a buy sweeps every ask below the target, a sell
+    // every bid above it. Notional is in quote currency at this book's
+    // quantity scale. (0, 0.0) if the touch is already there; None if the
+    // side runs out before any level at or beyond the target.
+    pub fn cost_to_move(&self, target_price: f64, is_buy_side: bool) -> Option<(Qty, f64)> {
+        let target_key = self.tick_key(target_price);
+        let mut quantity = Qty::ZERO;
+        let mut notional = Decimal::ZERO;
+
+        for (price, level_quantity) in self.iter_levels(!is_buy_side) {
+            let reached = if is_buy_side {
+                self.tick_key(price) >= target_key
+            } else {
+                self.tick_key(price) <= target_key
+            };
+            if reached {
+                return Some((quantity, to_f64(notional)));
+            }
+
+            quantity += level_quantity;
+            notional += to_decimal(price) * level_quantity.to_base(self.quantity_scale);
+        }
+        None
     }
 
-    pub fn get_bid_quantity_at(&self, price: f64) -> u32 {
-        let price_key = (price * 100.0) as u64;
+    // Quote notional an aggressor pays (buy) or receives (sell) to fill
+    // `quantity` against the opposite side, walking levels best first. None
+    // if the side holds less than `quantity`.
+    pub fn cost_to_fill(&self, quantity: Qty, is_buy_side: bool) -> Option<f64> {
+        self.fill_notional(quantity, is_buy_side).map(to_f64)
+    }
+
+    // Volume-weighted average price of the same sweep
+    pub fn vwap_to_fill(&self, quantity: Qty, is_buy_side: bool) -> Option<f64> {
+        if quantity.is_zero() {
+            return None;
+        }
+        let notional = self.fill_notional(quantity, is_buy_side)?;
+        Some(to_f64(notional / quantity.to_base(self.quantity_scale)))
+    }
+
+    // How far the same sweep's VWAP lands from the opposite touch, as a
+    // fraction of the touch: positive is worse for the aggressor, so 0.001 is
+    // 10 bps paid over the best price. 0.0 if the touch level covers
+    // `quantity`; None if the side can't fill it.
+    pub fn estimated_slippage(&self, quantity: Qty, is_buy_side: bool) -> Option<f64> {
+        let (touch, _) = self.iter_levels(!is_buy_side).next()?;
+        let vwap = self.vwap_to_fill(quantity, is_buy_side)?;
+        let slippage = if is_buy_side {
+            vwap - touch
+        } else {
+            touch - vwap
+        };
+        Some(slippage / touch)
+    }
+
+    fn fill_notional(&self, quantity: Qty, is_buy_side: bool) -> Option<Decimal> {
+        let mut remaining = quantity;
+        let mut notional = Decimal::ZERO;
+        for (price, level_quantity) in self.iter_levels(!is_buy_side) {
+            if remaining.is_zero() {
+                break;
+            }
+            let taken = min(remaining, level_quantity);
+            notional += to_decimal(price) * taken.to_base(self.quantity_scale);
+            remaining -= taken;
+        }
+        remaining.is_zero().then_some(notional)
+    }
+
+    // Resting size across every bid level
+    pub fn total_bid_quantity(&self) -> Qty {
+        self.buy_levels.values().copied().sum()
+    }
+
+    pub fn total_ask_quantity(&self) -> Qty {
+        self.sell_levels.values().copied().sum()
+    }
+
+    pub fn bid_order_count(&self) -> usize {
+        self.buy_orders_at_level.values().map(Vec::len).sum()
+    }
+
+    pub fn ask_order_count(&self) -> usize {
+        self.sell_orders_at_level.values().map(Vec::len).sum()
+    }
+
+    // Halfway between the touches; None unless both sides are quoted
+    pub fn midpoint(&self) -> Option<f64> {
+        Some((self.get_best_bid()? + self.get_best_ask()?) / 2.0)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.get_best_ask()? - self.get_best_bid()?)
+    }
+
+    // Spread in basis points of the midpoint
+    pub fn spread_bps(&self) -> Option<f64> {
+        Some(self.spread()? / self.midpoint()? * 10000.0)
+    }
+
+    // Size-weighted mid: leans toward the side with less resting size, where
+    // the next trade is more likely to move the price
+    pub fn get_microprice(&self) -> Option<f64> {
+        let (&Reverse(bid_key), &bid_qty) = self.buy_levels.first_key_value()?;
+        let (&ask_key, &ask_qty) = self.sell_levels.first_key_value()?;
+        let (bid_qty, ask_qty) = (bid_qty.lots() as f64, ask_qty.lots() as f64);
+        let total = bid_qty + ask_qty;
+        if total == 0.0 {
+            return None;
+        }
+
+        let bid = self.key_price(bid_key);
+        let ask = self.key_price(ask_key);
+        Some((bid * ask_qty + ask * bid_qty) / total)
+    }
+
+    pub fn get_bid_quantity_at(&self, price: f64) -> Qty {
+        let price_key = self.tick_key(price);
         self.buy_levels
             .get(&Reverse(price_key))
             .copied()
-            .unwrap_or(0)
+            .unwrap_or_default()
+    }
+
+    pub fn get_ask_quantity_at(&self, price: f64) -> Qty {
+        let price_key = self.tick_key(price);
+        self.sell_levels
+            .get(&price_key)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Ids resting at one bid price, first in the queue first. Empty if
+    // nothing rests there.
+    pub fn get_bid_orders_at(&self, price: f64) -> Vec<u32> {
+        let price_key = self.tick_key(price);
+        self.buy_orders_at_level
+            .get(&Reverse(price_key))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn get_ask_orders_at(&self, price: f64) -> Vec<u32> {
+        let price_key = self.tick_key(price);
+        self.sell_orders_at_level
+            .get(&price_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Net lots `owner_id` has bought minus sold in this book, plus any
+    // position seeded with set_position
+    pub fn position(&self, owner_id: u32) -> i64 {
+        self.positions.get(&owner_id).copied().unwrap_or(0)
+    }
+
+    // Seed a position held elsewhere, e.g. inventory carried into the
+    // session. Reduce-only orders the new position no longer covers are
+    // trimmed.
+    pub fn set_position(&mut self, owner_id: u32, lots: i64) {
+        self.positions.insert(owner_id, lots);
+        self.trim_reduce_only(owner_id);
+    }
+
+    // Read-only view of a resting order
+    pub fn get_order(&self, order_id: u32) -> Option<&Order> {
+        self.orders.get(&order_id)
+    }
+
+    pub fn contains_order(&self, order_id: u32) -> bool {
+        self.orders.contains_key(&order_id)
+    }
+
+    // Arrival time of a resting order, in nanoseconds
+    pub fn get_order_timestamp(&self, order_id: u32) -> Option<u64> {
+        self.orders.get(&order_id).map(|order| order.timestamp)
+    }
+
+    // Resting quantity at the order's level that will fill before it, or
+    // None if the order is not resting
+    pub fn queue_ahead(&self, order_id: u32) -> Option<Qty> {
+        let order = self.orders.get(&order_id)?;
+        let price_key = self.tick_key(order.price);
+        let queue = if order.is_buy_side {
+            self.buy_orders_at_level.get(&Reverse(price_key))?
+        } else {
+            self.sell_orders_at_level.get(&price_key)?
+        };
+
+        Some(
+            queue
+                .iter()
+                .take_while(|&&id| id != order_id)
+                .filter_map(|id| self.orders.get(id))
+                .map(|ahead| ahead.quantity)
+                .sum(),
+        )
+    }
+}
+
+// CRC-32 (IEEE 802.3, as used by zlib and Kraken's book checksum)
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(trades: &[Trade]) -> Vec<f64> {
+        trades.iter().map(|trade| trade.price).collect()
+    }
+
+    // Lit 99.00 / 101.00 with a midpoint-pegged ask limited at 99.50
+    fn pegged_book(mode: PriceImprovementMode) -> OrderBook {
+        let mut book = OrderBook::new().with_price_improvement(mode);
+        book.add_order(1, 99.00, Qty(10), true);
+        book.add_order(2, 101.00, Qty(10), false);
+        book.submit(OrderRequest::new(3, 99.50, Qty(5), false).with_midpoint_peg());
+        book
+    }
+
+    #[test]
+    fn price_improvement_modes_price_the_same_cross() {
+        let maker =
+            pegged_book(PriceImprovementMode::MakerPrice).add_order(4, 101.00, Qty(8), true);
+        assert_eq!(prices(&maker), vec![99.50, 101.00]);
+
+        // Only the pegged order moves to the lit midpoint
+        let midpoint =
+            pegged_book(PriceImprovementMode::Midpoint).add_order(4, 101.00, Qty(8), true);
+        assert_eq!(prices(&midpoint), vec![100.00, 101.00]);
+    }
+
+    #[test]
+    fn midpoint_never_fills_a_resting_order_past_its_limit() {
+        let mut book = OrderBook::new().with_price_improvement(PriceImprovementMode::Midpoint);
+        book.add_order(1, 99.00, Qty(10), true);
+        book.add_order(2, 101.00, Qty(10), false);
+        assert_eq!(
+            prices(&book.add_order(3, 101.00, Qty(5), true)),
+            vec![101.00]
+        );
+
+        // A peg limited above the midpoint keeps its limit
+        let mut book = OrderBook::new().with_price_improvement(PriceImprovementMode::Midpoint);
+        book.add_order(1, 99.00, Qty(10), true);
+        book.add_order(2, 101.00, Qty(10), false);
+        book.submit(OrderRequest::new(3, 100.50, Qty(5), false).with_midpoint_peg());
+        assert_eq!(
+            prices(&book.add_order(4, 100.50, Qty(5), true)),
+            vec![100.50]
+        );
+    }
+
+    #[test]
+    fn midpoint_respects_the_incoming_limit() {
+        let mut book = pegged_book(PriceImprovementMode::Midpoint);
+        assert_eq!(prices(&book.add_order(4, 99.80, Qty(5), true)), vec![99.50]);
+    }
+
+    #[test]
+    fn cancel_returns_the_resting_order() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 100.25, Qty(300), true);
+        book.add_order(2, 101.00, Qty(50), false);
+
+        let cancelled = book.cancel_order(1).unwrap();
+        assert_eq!(cancelled.order_id, 1);
+        assert_eq!(cancelled.price, 100.25);
+        assert_eq!(cancelled.quantity, Qty(300));
+        assert!(cancelled.is_buy_side);
+        assert_eq!(book.get_best_bid(), None);
+
+        // Gone now, and never there
+        assert!(book.cancel_order(1).is_none());
+        assert!(book.cancel_order(99).is_none());
+
+        assert!(book.cancel_order_exists(2));
+        assert!(!book.cancel_order_exists(2));
+    }
+
+    #[test]
+    fn reduce_only_sell_stops_at_the_long_position() {
+        let mut book = OrderBook::new();
+        // Owner 7 goes long 500 lots by lifting an ask
+        book.add_order(1, 100.0, Qty(500), false);
+        book.submit(OrderRequest::new(2, 100.0, Qty(500), true).with_owner(7));
+        assert_eq!(book.position(7), 500);
+
+        // Plenty of bids to hit, but only the long may be sold
+        book.add_order(3, 99.0, Qty(2_000), true);
+        let trades = book.add_reduce_only_order(4, 7, 99.0, Qty(800), false);
+
+        assert_eq!(
+            trades.iter().map(|trade| trade.quantity).sum::<Qty>(),
+            Qty(500)
+        );
+        assert_eq!(book.position(7), 0);
+        assert!(!book.contains_order(4));
+        assert_eq!(book.get_bid_quantity_at(99.0), Qty(1_500));
+
+        // Flat now, so another reduce-only sell has nothing to close
+        assert!(book
+            .add_reduce_only_order(5, 7, 99.0, Qty(100), false)
+            .is_empty());
+        assert_eq!(book.position(7), 0);
+    }
+
+    #[test]
+    fn satoshi_price_scale_reads_prices_back_exactly() {
+        let mut book = OrderBook::new().with_price_scale(100_000_000);
+        book.add_order(1, 45000.12345, Qty(10), true);
+        book.add_order(2, 45000.12346, Qty(20), true);
+        book.add_order(3, 45000.12347, Qty(30), false);
+
+        assert_eq!(book.get_best_bid(), Some(45000.12346));
+        assert_eq!(book.get_best_ask(), Some(45000.12347));
+        // One cent apart would have been one level; here they stay apart
+        assert_eq!(book.get_bid_quantity_at(45000.12345), Qty(10));
+        assert_eq!(book.get_bid_quantity_at(45000.12346), Qty(20));
+        assert_eq!(book.get_ask_quantity_at(45000.12347), Qty(30));
+
+        assert!(book.cancel_order(2).is_some());
+        assert_eq!(book.get_best_bid(), Some(45000.12345));
+    }
+
+    #[test]
+    fn checksum_uses_krakens_level_format() {
+        // BTC/USD at Kraken's precisions: prices to 0.1, quantities to 1e-8
+        let asks = [
+            (45285.2, 100_000),
+            (45286.4, 154_582_015),
+            (45286.6, 154_592_586),
+            (45289.6, 154_628_740),
+            (45290.2, 7_799_233),
+            (45291.8, 154_667_011),
+            (45293.1, 100_000),
+            (45293.3, 100_000),
+            (45294.6, 2_005_000),
+            (45294.8, 58_917_002),
+            // Past the 10 checksummed levels
+            (45300.0, 100_000),
+        ];
+        let bids = [
+            (45283.5, 100_000),
+            (45283.4, 154_582_015),
+            (45282.1, 100_000),
+            (45281.0, 100_000),
+            (45280.3, 154_592_586),
+            (45279.0, 7_990_000),
+            (45277.6, 3_310_103),
+            (45277.5, 100_000),
+            (45276.7, 100_000),
+            (45274.0, 100_000),
+        ];
+        let lots = |levels: &[(f64, u64)]| -> DepthLevels {
+            levels
+                .iter()
+                .map(|&(price, lots)| (price, Qty(lots)))
+                .collect()
+        };
+        let book = OrderBook::from_levels(&lots(&bids), &lots(&asks))
+            .unwrap()
+            .with_price_scale(10)
+            .with_quantity_scale(100_000_000);
+
+        // CRC32 of "452852100000452864154582015...452740100000": asks then
+        // bids, best first, each price then quantity with the decimal point
+        // and leading zeros removed
+        assert_eq!(book.checksum(10), 746_069_961);
+
+        // One ask alone is CRC32 of "452852100000"
+        let touch = OrderBook::from_levels(&[], &lots(&asks[..1]))
+            .unwrap()
+            .with_price_scale(10)
+            .with_quantity_scale(100_000_000);
+        assert_eq!(touch.checksum(10), 0x7d21_8207);
+    }
+
+    // Owner 1 rests a 30-lot iceberg ask showing 10 at a time
+    fn stp_book(policy: SelfTradePrevention) -> OrderBook {
+        let mut book = OrderBook::new()
+            .with_self_trade_prevention(policy)
+            .with_event_log(64);
+        book.submit(
+            OrderRequest::new(1, 100.0, Qty(30), false)
+                .with_owner(1)
+                .with_display_quantity(Qty(10)),
+        );
+        book.take_events();
+        book
+    }
+
+    #[test]
+    fn cancel_resting_reports_the_hidden_reserve() {
+        let mut book = stp_book(SelfTradePrevention::CancelResting);
+        book.add_order(2, 100.0, Qty(5), false);
+
+        let result = book.submit(OrderRequest::new(3, 100.0, Qty(8), true).with_owner(1));
+        // The iceberg goes and the order behind it trades
+        assert_eq!(result.filled_qty, Qty(5));
+        assert_eq!(result.resting_qty, Qty(3));
+        assert!(!book.contains_order(1));
+        assert!(book.take_events().contains(&ExecutionEvent::Cancelled {
+            order_id: 1,
+            remaining: Qty(30),
+        }));
+    }
+
+    #[test]
+    fn cancel_incoming_leaves_the_resting_order() {
+        let mut book = stp_book(SelfTradePrevention::CancelIncoming);
+
+        let result = book.submit(OrderRequest::new(2, 100.0, Qty(8), true).with_owner(1));
+        assert!(result.trades.is_empty());
+        assert_eq!(result.resting_qty, Qty::ZERO);
+        assert!(!result.fully_filled);
+        assert_eq!(book.get_order(1).unwrap().total_quantity(), Qty(30));
+        assert_eq!(
+            book.take_events(),
+            vec![
+                ExecutionEvent::Added {
+                    order_id: 2,
+                    quantity: Qty(8),
+                },
+                ExecutionEvent::Cancelled {
+                    order_id: 2,
+                    remaining: Qty(8),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn decrement_both_shrinks_a_plain_order() {
+        let mut book =
+            OrderBook::new().with_self_trade_prevention(SelfTradePrevention::DecrementBoth);
+        book.submit(OrderRequest::new(1, 100.0, Qty(10), false).with_owner(1));
+
+        let result = book.submit(OrderRequest::new(2, 100.0, Qty(4), true).with_owner(1));
+        assert!(result.trades.is_empty());
+        assert_eq!(book.get_ask_quantity_at(100.0), Qty(6));
+
+        // The larger side keeps the difference
+        let result = book.submit(OrderRequest::new(3, 100.0, Qty(9), true).with_owner(1));
+        assert_eq!(result.resting_qty, Qty(3));
+        assert!(!book.contains_order(1));
+        assert_eq!(book.get_bid_quantity_at(100.0), Qty(3));
+    }
+
+    #[test]
+    fn decrement_both_replenishes_an_iceberg() {
+        let mut book = stp_book(SelfTradePrevention::DecrementBoth);
+
+        // Uses up the first slice and half of the next
+        let result = book.submit(OrderRequest::new(2, 100.0, Qty(15), true).with_owner(1));
+        assert!(result.trades.is_empty());
+        assert_eq!(result.resting_qty, Qty::ZERO);
+        let iceberg = book.get_order(1).unwrap();
+        assert_eq!(iceberg.quantity, Qty(5));
+        assert_eq!(iceberg.hidden_quantity, Qty(10));
+        assert_eq!(book.get_ask_quantity_at(100.0), Qty(5));
+        assert!(!book
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, ExecutionEvent::Cancelled { order_id: 1, .. })));
+
+        // More than the whole reserve takes the iceberg out
+        let result = book.submit(OrderRequest::new(3, 100.0, Qty(20), true).with_owner(1));
+        assert_eq!(result.resting_qty, Qty(5));
+        assert!(!book.contains_order(1));
+        assert_eq!(book.get_best_ask(), None);
+    }
+
+    #[test]
+    fn from_levels_loads_a_deep_snapshot() {
+        // 50 levels a side, a cent apart, around a 99.99 / 100.01 touch
+        let bids: DepthLevels = (0..50)
+            .map(|i| ((9999 - i) as f64 / 100.0, Qty(100 + i)))
+            .collect();
+        let asks: DepthLevels = (0..50)
+            .map(|i| ((10001 + i) as f64 / 100.0, Qty(200 + i)))
+            .collect();
+        let book = OrderBook::from_levels(&bids, &asks).unwrap();
+
+        assert_eq!(book.get_best_bid(), Some(99.99));
+        assert_eq!(book.get_best_ask(), Some(100.01));
+        assert_eq!(book.get_bid_quantity_at(99.50), Qty(149));
+        assert_eq!(book.get_ask_quantity_at(100.50), Qty(249));
+        let (depth_bids, depth_asks) = book.get_depth(100);
+        assert_eq!((depth_bids.len(), depth_asks.len()), (50, 50));
+        // Ids follow input order, bids first
+        assert_eq!(book.get_bid_orders_at(99.99), vec![1]);
+        assert_eq!(book.get_ask_orders_at(100.01), vec![51]);
+
+        // A bid through the best ask is refused
+        let mut crossed = bids.clone();
+        crossed.push((100.02, Qty(1)));
+        assert!(matches!(
+            OrderBook::from_levels(&crossed, &asks),
+            Err(Error::CrossedLevels { best_bid, best_ask })
+                if best_bid == 100.02 && best_ask == 100.01
+        ));
+    }
+
+    #[test]
+    fn detailed_add_reports_a_full_fill() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 100.0, Qty(5), false);
+        book.add_order(2, 100.5, Qty(5), false);
+
+        let result = book.add_order_detailed(3, 100.5, Qty(8), true);
+        assert_eq!(result.trades.len(), 2);
+        assert_eq!(result.filled_qty, Qty(8));
+        assert_eq!(result.resting_qty, Qty::ZERO);
+        assert!(result.fully_filled);
+        assert!(!book.contains_order(3));
+        assert_eq!(book.get_ask_quantity_at(100.5), Qty(2));
+    }
+
+    #[test]
+    fn detailed_add_reports_a_partial_fill() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 100.0, Qty(5), false);
+
+        let result = book.add_order_detailed(2, 100.0, Qty(12), true);
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.filled_qty, Qty(5));
+        assert_eq!(result.resting_qty, Qty(7));
+        assert!(!result.fully_filled);
+        assert_eq!(book.get_bid_quantity_at(100.0), Qty(7));
+        assert_eq!(book.get_best_ask(), None);
+    }
+
+    #[test]
+    fn detailed_add_reports_no_fill() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 101.0, Qty(5), false);
+
+        let result = book.add_order_detailed(2, 100.0, Qty(12), true);
+        assert!(result.trades.is_empty());
+        assert_eq!(result.filled_qty, Qty::ZERO);
+        assert_eq!(result.resting_qty, Qty(12));
+        assert!(!result.fully_filled);
+        assert!(!result.halted);
+        assert_eq!(book.get_bid_orders_at(100.0), vec![2]);
+    }
+
+    // 5 lots offered at 100.00 and 5 more at 100.50
+    fn two_level_asks() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.add_order(1, 100.0, Qty(5), false);
+        book.add_order(2, 100.5, Qty(5), false);
+        book
+    }
+
+    fn request(order_id: u32, price: f64, lots: u64, tif: TimeInForce) -> OrderRequest {
+        OrderRequest::new(order_id, price, Qty(lots), true).with_time_in_force(tif)
+    }
+
+    #[test]
+    fn gtc_rests_the_remainder() {
+        let mut book = two_level_asks();
+        let result = book.submit(request(3, 100.0, 8, TimeInForce::GTC));
+        assert_eq!(result.filled_qty, Qty(5));
+        assert_eq!(result.resting_qty, Qty(3));
+        assert_eq!(book.get_bid_quantity_at(100.0), Qty(3));
+    }
+
+    #[test]
+    fn ioc_cancels_the_remainder() {
+        let mut book = two_level_asks();
+        let result = book.submit(request(3, 100.0, 8, TimeInForce::IOC));
+        assert_eq!(result.filled_qty, Qty(5));
+        assert_eq!(result.resting_qty, Qty::ZERO);
+        assert!(!result.fully_filled);
+        assert!(!book.contains_order(3));
+        assert_eq!(book.get_best_bid(), None);
+    }
+
+    #[test]
+    fn fok_fills_completely_or_not_at_all() {
+        let mut book = two_level_asks();
+        // Only 5 lots at or below 100.00
+        let result = book.submit(request(3, 100.0, 8, TimeInForce::FOK));
+        assert!(result.trades.is_empty());
+        assert_eq!(result.filled_qty, Qty::ZERO);
+        assert_eq!(book.get_ask_quantity_at(100.0), Qty(5));
+
+        // 10 lots at or below 100.50
+        let result = book.submit(request(4, 100.5, 8, TimeInForce::FOK));
+        assert!(result.fully_filled);
+        assert_eq!(result.filled_qty, Qty(8));
+        assert_eq!(book.get_ask_quantity_at(100.5), Qty(2));
+    }
+
+    #[test]
+    fn post_only_is_rejected_when_it_would_take() {
+        let mut book = two_level_asks();
+        let result = book.submit(request(3, 100.0, 8, TimeInForce::PostOnly));
+        assert!(result.trades.is_empty());
+        assert_eq!(result.resting_qty, Qty::ZERO);
+        assert!(!book.contains_order(3));
+
+        // Below the ask it rests untouched
+        let result = book.submit(request(4, 99.5, 8, TimeInForce::PostOnly));
+        assert_eq!(result.resting_qty, Qty(8));
+        assert_eq!(book.get_best_bid(), Some(99.5));
+    }
+
+    #[test]
+    fn gtd_rests_until_pruned_at_expiry() {
+        let mut book = two_level_asks();
+        let result = book.submit(request(3, 100.0, 8, TimeInForce::GTD { expiry: 1_000 }));
+        assert_eq!(result.filled_qty, Qty(5));
+        assert_eq!(result.resting_qty, Qty(3));
+        assert_eq!(book.get_order(3).unwrap().expires_at, Some(1_000));
+
+        assert!(book.prune_expired(999).is_empty());
+        assert_eq!(book.prune_expired(1_000), vec![3]);
+        assert_eq!(book.get_best_bid(), None);
+    }
+
+    #[test]
+    fn rate_limit_rejects_the_excess_of_a_burst() {
+        let mut book = OrderBook::new().with_rate_limit(3);
+        let now = Duration::from_secs(1);
+        for order_id in 1..=3 {
+            assert!(book
+                .try_add_order_at(now, order_id, 99.0, Qty(1), true)
+                .is_ok());
+        }
+        assert!(matches!(
+            book.try_add_order_at(now, 4, 99.0, Qty(1), true),
+            Err(Error::RateLimited { max_per_sec: 3 })
+        ));
+        assert!(!book.contains_order(4));
+
+        // A third of a second later there is room for one more
+        let later = now + Duration::from_millis(334);
+        assert!(book.try_add_order_at(later, 4, 99.0, Qty(1), true).is_ok());
+        assert!(book.try_add_order_at(later, 5, 99.0, Qty(1), true).is_err());
+    }
+
+    #[test]
+    fn tick_buckets_collapse_levels_toward_the_far_side() {
+        let bids: DepthLevels = vec![
+            (100.04, Qty(1)),
+            (100.02, Qty(2)),
+            (100.00, Qty(3)),
+            (99.99, Qty(4)),
+            (99.80, Qty(5)),
+        ];
+        let asks: DepthLevels = vec![
+            (100.06, Qty(10)),
+            (100.09, Qty(20)),
+            (100.10, Qty(30)),
+            (100.11, Qty(40)),
+        ];
+        let book = OrderBook::from_levels(&bids, &asks).unwrap();
+
+        // Bids floor to the nickel below, asks ceil to the one above
+        let (bid_buckets, ask_buckets) = book.get_aggregated_depth_by_tick(10, 0.05);
+        assert_eq!(
+            bid_buckets,
+            vec![(100.00, Qty(6)), (99.95, Qty(4)), (99.80, Qty(5))]
+        );
+        assert_eq!(ask_buckets, vec![(100.10, Qty(60)), (100.15, Qty(40))]);
+
+        // Capped at `levels` buckets, not levels
+        let (bid_buckets, ask_buckets) = book.get_aggregated_depth_by_tick(1, 0.05);
+        assert_eq!(bid_buckets, vec![(100.00, Qty(6))]);
+        assert_eq!(ask_buckets, vec![(100.10, Qty(60))]);
     }
 
-    pub fn get_ask_quantity_at(&self, price: f64) -> u32 {
-        let price_key = (price * 100.0) as u64;
-        self.sell_levels.get(&price_key).copied().unwrap_or(0)
+    #[test]
+    fn microprice_leans_toward_the_thinner_side() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 100.0, Qty(30), true);
+        book.add_order(2, 101.0, Qty(10), false);
+        // Thin ask: three quarters of the way up the spread
+        assert_eq!(book.get_microprice(), Some(100.75));
+
+        // Balanced sizes give the plain midpoint
+        book.add_order(3, 101.0, Qty(20), false);
+        assert_eq!(book.get_microprice(), book.midpoint());
+
+        // Thin bid pulls it down
+        book.add_order(4, 101.0, Qty(60), false);
+        assert_eq!(book.get_microprice(), Some(100.25));
+
+        book.cancel_order(1);
+        assert_eq!(book.get_microprice(), None);
+    }
+
+    #[test]
+    fn trade_summary_splits_flow_by_aggressor() {
+        let trades = [
+            Trade::new(1, 100.0, Qty(10), 1, 2, true, 0),
+            Trade::new(2, 101.0, Qty(30), 3, 4, true, 0),
+            Trade::new(3, 99.0, Qty(20), 5, 6, false, 0),
+        ];
+        let summary = summarize_trades(&trades);
+
+        assert_eq!(summary.buy_volume, Qty(40));
+        assert_eq!(summary.sell_volume, Qty(20));
+        assert_eq!(summary.total_volume(), Qty(60));
+        assert_eq!(summary.trade_count, 3);
+        // (100 * 10 + 101 * 30 + 99 * 20) / 60
+        assert!((summary.vwap - 6010.0 / 60.0).abs() < 1e-9);
+
+        // Recording trade by trade gives the same summary
+        let mut incremental = TradeFlowSummary::default();
+        for trade in &trades {
+            incremental.record(trade);
+        }
+        assert_eq!(incremental, summary);
+
+        assert_eq!(summarize_trades(&[]).vwap, 0.0);
+    }
+
+    #[test]
+    fn modify_ignores_invalid_prices() {
+        let mut book = OrderBook::new();
+        book.add_order(1, 99.0, Qty(10), true);
+        for price in [f64::NAN, 0.0, -1.0, 0.001] {
+            assert!(book.modify_order(1, price, Qty(5)).is_empty());
+        }
+        assert_eq!(book.get_order(1).unwrap().price, 99.0);
+        assert_eq!(book.get_bid_quantity_at(99.0), Qty(10));
+    }
+
+    #[test]
+    fn checked_modify_applies_the_entry_limits() {
+        let mut book = OrderBook::new().with_strict_mode();
+        book.add_order(1, 99.0, Qty(10), true);
+        book.add_order(2, 101.0, Qty(10), false);
+
+        assert!(matches!(
+            book.try_modify_order(1, f64::NAN, Qty(5)),
+            Err(Error::InvalidPrice(_))
+        ));
+        assert!(matches!(
+            book.try_modify_order(3, 99.0, Qty(5)),
+            Err(Error::OrderNotFound(3))
+        ));
+        // Strict mode refuses an amendment that would take liquidity
+        assert!(matches!(
+            book.try_modify_order(1, 101.0, Qty(5)),
+            Err(Error::WouldCross {
+                price: 101.0,
+                is_buy_side: true,
+            })
+        ));
+        assert_eq!(book.get_order(1).unwrap().price, 99.0);
+        assert!(book.try_modify_order(1, 100.0, Qty(5)).unwrap().is_empty());
+
+        // So does the rate limit, except for a cancel
+        let mut book = OrderBook::new().with_rate_limit(1);
+        book.add_order(1, 99.0, Qty(10), true);
+        assert!(book.try_modify_order(1, 99.0, Qty(8)).is_ok());
+        assert!(matches!(
+            book.try_modify_order(1, 99.0, Qty(6)),
+            Err(Error::RateLimited { max_per_sec: 1 })
+        ));
+        assert!(book.try_modify_order(1, 99.0, Qty::ZERO).is_ok());
+        assert!(!book.contains_order(1));
     }
 }
@@ -0,0 +1,130 @@
+// Python bindings for the matching engine, built with maturin:
+//
+//   maturin develop --release
+//
+// from src/rust_core. The `python` feature is enabled by pyproject.toml.
+// src/python/tests/test_rust_order_book.py exercises the module.
+
+// pyo3 0.22's #[pymethods] expansion trips this lint on PyResult returns
+#![allow(clippy::useless_conversion)]
+
+use crate::money::Qty;
+use crate::order_book::{self, DepthLevels, OrderBook};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+// One side of get_depth: (price, quantity in lots), best price first
+type PyDepthLevels = Vec<(f64, u64)>;
+
+#[pyclass(name = "Trade", module = "rust_core", frozen, get_all)]
+#[derive(Clone)]
+pub struct PyTrade {
+    trade_id: u32,
+    price: f64,
+    quantity: u64,
+    buy_order_id: u32,
+    sell_order_id: u32,
+    aggressor_is_buy: bool,
+    maker_order_id: u32,
+    taker_order_id: u32,
+    timestamp: u64,
+}
+
+impl From<order_book::Trade> for PyTrade {
+    fn from(trade: order_book::Trade) -> Self {
+        PyTrade {
+            trade_id: trade.trade_id,
+            price: trade.price,
+            quantity: trade.quantity.lots(),
+            buy_order_id: trade.buy_order_id,
+            sell_order_id: trade.sell_order_id,
+            aggressor_is_buy: trade.aggressor_is_buy,
+            maker_order_id: trade.maker_order_id,
+            taker_order_id: trade.taker_order_id,
+            timestamp: trade.timestamp,
+        }
+    }
+}
+
+#[pymethods]
+impl PyTrade {
+    fn __repr__(&self) -> String {
+        format!(
+            "Trade(trade_id={}, price={:?}, quantity={}, buy_order_id={}, sell_order_id={}, \
+             aggressor_is_buy={}, timestamp={})",
+            self.trade_id,
+            self.price,
+            self.quantity,
+            self.buy_order_id,
+            self.sell_order_id,
+            if self.aggressor_is_buy {
+                "True"
+            } else {
+                "False"
+            },
+            self.timestamp
+        )
+    }
+}
+
+#[pyclass(name = "OrderBook", module = "rust_core")]
+pub struct PyOrderBook {
+    book: OrderBook,
+}
+
+#[pymethods]
+impl PyOrderBook {
+    #[new]
+    fn new() -> Self {
+        PyOrderBook {
+            book: OrderBook::new(),
+        }
+    }
+
+    // Invalid prices and quantities raise ValueError rather than reaching
+    // the unchecked fast path
+    fn add_order(
+        &mut self,
+        order_id: u32,
+        price: f64,
+        quantity: u64,
+        is_buy_side: bool,
+    ) -> PyResult<Vec<PyTrade>> {
+        let trades = self
+            .book
+            .try_add_order(order_id, price, Qty(quantity), is_buy_side)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(trades.into_iter().map(PyTrade::from).collect())
+    }
+
+    fn cancel_order(&mut self, order_id: u32) -> bool {
+        self.book.cancel_order_exists(order_id)
+    }
+
+    fn get_best_bid(&self) -> Option<f64> {
+        self.book.get_best_bid()
+    }
+
+    fn get_best_ask(&self) -> Option<f64> {
+        self.book.get_best_ask()
+    }
+
+    // Returns (bids, asks) as lists of (price, quantity) tuples
+    #[pyo3(signature = (levels = 10))]
+    fn get_depth(&self, levels: usize) -> (PyDepthLevels, PyDepthLevels) {
+        let lots = |side: DepthLevels| {
+            side.into_iter()
+                .map(|(price, quantity)| (price, quantity.lots()))
+                .collect()
+        };
+        let (bids, asks) = self.book.get_depth(levels);
+        (lots(bids), lots(asks))
+    }
+}
+
+#[pymodule]
+fn rust_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOrderBook>()?;
+    m.add_class::<PyTrade>()?;
+    Ok(())
+}
@@ -0,0 +1,84 @@
+// Token-bucket rate limiter modelling exchange order-entry limits. The bucket
+// holds up to one second's worth of tokens, so a client may burst up to
+// `max_per_sec` orders and is then held to the steady rate.
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    max_per_sec: u32,
+    tokens: f64,
+    // Clock reading at the last refill, relative to `origin`
+    last_refill: Duration,
+    origin: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_sec: u32) -> Self {
+        RateLimiter {
+            max_per_sec,
+            tokens: max_per_sec as f64,
+            last_refill: Duration::ZERO,
+            origin: Instant::now(),
+        }
+    }
+
+    pub fn max_per_sec(&self) -> u32 {
+        self.max_per_sec
+    }
+
+    // Take one token against the wall clock
+    pub fn try_acquire(&mut self) -> bool {
+        let now = self.origin.elapsed();
+        self.try_acquire_at(now)
+    }
+
+    // Take one token at an explicit clock reading, for simulated time such as
+    // a backtest's market data timestamps. Readings earlier than the last one
+    // seen are treated as no time having passed.
+    pub fn try_acquire_at(&mut self, now: Duration) -> bool {
+        if now > self.last_refill {
+            let elapsed = (now - self.last_refill).as_secs_f64();
+            self.tokens =
+                (self.tokens + elapsed * self.max_per_sec as f64).min(self.max_per_sec as f64);
+            self.last_refill = now;
+        }
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_to_the_limit_then_refills_at_the_steady_rate() {
+        let mut limiter = RateLimiter::new(5);
+        let start = Duration::from_secs(1);
+        assert_eq!((0..8).filter(|_| limiter.try_acquire_at(start)).count(), 5);
+
+        // One token every 200ms
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(199)));
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(200)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(200)));
+
+        // A long pause refills only up to one second's worth
+        let later = start + Duration::from_secs(60);
+        assert_eq!((0..8).filter(|_| limiter.try_acquire_at(later)).count(), 5);
+    }
+
+    #[test]
+    fn earlier_readings_add_no_tokens() {
+        let mut limiter = RateLimiter::new(2);
+        let now = Duration::from_secs(10);
+        assert!(limiter.try_acquire_at(now));
+        assert!(limiter.try_acquire_at(now));
+        assert!(!limiter.try_acquire_at(Duration::from_secs(5)));
+        assert!(!limiter.try_acquire_at(now));
+    }
+}
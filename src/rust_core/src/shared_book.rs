@@ -0,0 +1,195 @@
+// Single-writer order book with lock-free readers. The writer mutates a
+// private OrderBook and publishes an immutable top-of-book view after each
+// batch; readers load the latest view through an ArcSwap and never block the
+// writer.
+//
+// Staleness: a reader sees the state as of the last `publish` (or the end of
+// the last `write` batch), never a partially applied batch. Changes made
+// through `book_mut` stay invisible until the next publish.
+use crate::order_book::{DepthLevels, OrderBook};
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+
+// Immutable view of the book as of one publish
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookView {
+    // Incremented on every publish, so readers can tell views apart
+    pub sequence: u64,
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    pub bids: DepthLevels,
+    pub asks: DepthLevels,
+}
+
+impl BookView {
+    pub fn spread(&self) -> Option<f64> {
+        match (self.best_bid, self.best_ask) {
+            (Some(bid), Some(ask)) => Some(ask - bid),
+            _ => None,
+        }
+    }
+}
+
+// Cheap to clone; hand one to each strategy thread
+#[derive(Clone)]
+pub struct BookReader {
+    view: Arc<ArcSwap<BookView>>,
+}
+
+impl BookReader {
+    // Latest published view. Holding the Arc keeps that view alive even after
+    // the writer publishes a newer one.
+    pub fn load(&self) -> Arc<BookView> {
+        self.view.load_full()
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.view.load().best_bid
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.view.load().best_ask
+    }
+}
+
+pub struct SharedOrderBook {
+    book: OrderBook,
+    view: Arc<ArcSwap<BookView>>,
+    // Depth levels per side copied into each view
+    depth_levels: usize,
+    sequence: u64,
+}
+
+impl SharedOrderBook {
+    pub fn new(book: OrderBook, depth_levels: usize) -> Self {
+        let mut shared = SharedOrderBook {
+            book,
+            view: Arc::new(ArcSwap::from_pointee(BookView::default())),
+            depth_levels,
+            sequence: 0,
+        };
+        shared.publish();
+        shared
+    }
+
+    pub fn reader(&self) -> BookReader {
+        BookReader {
+            view: Arc::clone(&self.view),
+        }
+    }
+
+    // Apply a batch of mutations, then publish once
+    pub fn write<R>(&mut self, batch: impl FnOnce(&mut OrderBook) -> R) -> R {
+        let result = batch(&mut self.book);
+        self.publish();
+        result
+    }
+
+    // Direct access for callers that batch across several calls; remember to
+    // publish afterwards
+    pub fn book_mut(&mut self) -> &mut OrderBook {
+        &mut self.book
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn publish(&mut self) {
+        self.sequence += 1;
+        let (bids, asks) = self.book.get_depth(self.depth_levels);
+        self.view.store(Arc::new(BookView {
+            sequence: self.sequence,
+            best_bid: self.book.get_best_bid(),
+            best_ask: self.book.get_best_ask(),
+            bids,
+            asks,
+        }));
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Qty;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    const BATCHES: u32 = 2_000;
+
+    // Views must match the book they were taken from: the touch is the
+    // first level on each side, levels are best first, and bids stay below
+    // asks
+    fn assert_consistent(view: &BookView) {
+        assert_eq!(view.best_bid, view.bids.first().map(|&(price, _)| price));
+        assert_eq!(view.best_ask, view.asks.first().map(|&(price, _)| price));
+        assert!(view.bids.windows(2).all(|pair| pair[0].0 > pair[1].0));
+        assert!(view.asks.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        if let Some(spread) = view.spread() {
+            assert!(spread > 0.0, "crossed view: {view:?}");
+        }
+    }
+
+    #[test]
+    fn readers_see_consistent_views_while_the_writer_runs() {
+        let mut shared = SharedOrderBook::new(OrderBook::new(), 5);
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = shared.reader();
+                let done = Arc::clone(&done);
+                thread::spawn(move || {
+                    let mut last_sequence = 0;
+                    let mut views = 0;
+                    loop {
+                        // Read once more after the writer stops, so every
+                        // reader sees the final view
+                        let finished = done.load(Ordering::Acquire);
+                        let view = reader.load();
+                        assert!(view.sequence >= last_sequence, "sequence went backwards");
+                        last_sequence = view.sequence;
+                        assert_consistent(&view);
+                        views += 1;
+                        if finished {
+                            break;
+                        }
+                    }
+                    (last_sequence, views)
+                })
+            })
+            .collect();
+
+        // Each batch moves the quote: replace both sides, then add depth
+        // behind them, so a half-applied batch would be visible as a
+        // missing or crossed touch
+        for batch in 0..BATCHES {
+            let mid = 100.0 + f64::from(batch % 50) * 0.1;
+            let id = batch * 4;
+            shared.write(|book| {
+                book.cancel_order(id.wrapping_sub(4));
+                book.cancel_order(id.wrapping_sub(3));
+                book.cancel_order(id.wrapping_sub(2));
+                book.cancel_order(id.wrapping_sub(1));
+                book.add_order(id, mid - 0.05, Qty(10), true);
+                book.add_order(id + 1, mid - 0.15, Qty(20), true);
+                book.add_order(id + 2, mid + 0.05, Qty(10), false);
+                book.add_order(id + 3, mid + 0.15, Qty(20), false);
+            });
+        }
+        done.store(true, Ordering::Release);
+
+        let final_sequence = shared.sequence();
+        for reader in readers {
+            let (last_sequence, views) = reader.join().unwrap();
+            assert_eq!(last_sequence, final_sequence);
+            assert!(views > 0);
+        }
+        assert_eq!(final_sequence, u64::from(BATCHES) + 1);
+        assert_eq!(shared.book().get_depth(usize::MAX).0.len(), 2);
+    }
+}
@@ -1,31 +1,87 @@
+use crate::error::Error;
+use crate::money::{price_to_tick, to_decimal, to_f64, Decimal, Qty, QUANTITY_SCALE};
 use crate::order_book::OrderBook;
+use crate::telemetry;
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{PoisonError, RwLock};
 use std::time::Duration;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+// Levels per side shown by print_routing_stats
+const STATS_DEPTH_LEVELS: usize = 3;
+
+// Ordered built-ins first, then custom venues by registration order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ExchangeID {
     Binance,
     Coinbase,
     Kraken,
-    FTX,
+    // Any other venue, created with ExchangeID::custom
+    Custom(u16),
     Unknown,
 }
 
+// Names of custom venues, indexed by their Custom id. A poisoned lock is
+// still usable: names are only ever appended.
+static CUSTOM_EXCHANGES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+// Names custom venues can't take, compared case-insensitively
+const BUILT_IN_NAMES: [&str; 4] = ["Binance", "Coinbase", "Kraken", "Unknown"];
+
+impl ExchangeID {
+    // Id for a venue outside the built-in list, e.g. ExchangeID::custom("OKX").
+    // Registering a name again returns the id it was first given. Built-in
+    // names are rejected so a custom id never displays as a built-in one.
+    pub fn custom(name: &str) -> Result<ExchangeID, Error> {
+        if BUILT_IN_NAMES
+            .iter()
+            .any(|built_in| built_in.eq_ignore_ascii_case(name))
+        {
+            return Err(Error::ReservedExchangeName(name.to_string()));
+        }
+
+        let mut names = CUSTOM_EXCHANGES
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = names.iter().position(|existing| existing == name) {
+            // Only indices that fit in a u16 are ever stored
+            return Ok(ExchangeID::Custom(index as u16));
+        }
+        let index = u16::try_from(names.len())
+            .map_err(|_| Error::TooManyExchanges(usize::from(u16::MAX) + 1))?;
+        names.push(name.to_string());
+        Ok(ExchangeID::Custom(index))
+    }
+}
+
 impl fmt::Display for ExchangeID {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ExchangeID::Binance => write!(f, "Binance"),
             ExchangeID::Coinbase => write!(f, "Coinbase"),
             ExchangeID::Kraken => write!(f, "Kraken"),
-            ExchangeID::FTX => write!(f, "FTX"),
+            ExchangeID::Custom(index) => {
+                let names = CUSTOM_EXCHANGES
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                match names.get(usize::from(*index)) {
+                    Some(name) => write!(f, "{name}"),
+                    // Built directly rather than through ExchangeID::custom
+                    None => write!(f, "Custom({index})"),
+                }
+            }
             ExchangeID::Unknown => write!(f, "Unknown"),
         }
     }
 }
 
+// Rates are fractions of notional. A positive rate is charged; a negative
+// rate is a rebate paid out, which lowers a buy's cost and raises a sell's
+// proceeds. Venues usually only rebate maker flow.
 #[derive(Debug, Clone)]
 pub struct FeeSchedule {
-    pub maker_fee: f64, // Fee as percentage (e.g., 0.001 = 0.1%)
+    pub maker_fee: f64, // Fee as percentage (e.g., 0.001 = 0.1%); negative = rebate
     pub taker_fee: f64, // Fee as percentage
 }
 
@@ -47,13 +103,65 @@ impl Default for FeeSchedule {
     }
 }
 
+// The rates a venue charges given the notional volume already traded there
+// (as booked through SmartOrderRouter::record_fill)
+pub trait FeeModel: Send + Sync {
+    fn schedule(&self, traded_volume: f64) -> FeeSchedule;
+}
+
+impl FeeModel for FeeSchedule {
+    fn schedule(&self, _traded_volume: f64) -> FeeSchedule {
+        self.clone()
+    }
+}
+
+// Rates that drop as traded volume grows. Each tier applies from its
+// volume threshold up to the next one; the base rates apply below the first.
+#[derive(Debug, Clone)]
+pub struct TieredFeeSchedule {
+    // (minimum volume, rates), ascending by volume; starts with (0.0, base)
+    tiers: Vec<(f64, FeeSchedule)>,
+}
+
+impl TieredFeeSchedule {
+    pub fn new(base: FeeSchedule) -> Self {
+        TieredFeeSchedule {
+            tiers: vec![(0.0, base)],
+        }
+    }
+
+    // Tiers can be added in any order; a repeated threshold replaces the
+    // earlier rates
+    pub fn with_tier(mut self, min_volume: f64, maker: f64, taker: f64) -> Self {
+        let rates = FeeSchedule::new(maker, taker);
+        let index = self
+            .tiers
+            .partition_point(|(threshold, _)| *threshold < min_volume);
+        match self.tiers.get_mut(index) {
+            Some((threshold, existing)) if *threshold == min_volume => *existing = rates,
+            _ => self.tiers.insert(index, (min_volume, rates)),
+        }
+        self
+    }
+}
+
+impl FeeModel for TieredFeeSchedule {
+    fn schedule(&self, traded_volume: f64) -> FeeSchedule {
+        let tier = self
+            .tiers
+            .partition_point(|(threshold, _)| *threshold <= traded_volume);
+        // The base tier's threshold is 0, so only negative volume finds none
+        self.tiers[tier.saturating_sub(1)].1.clone()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RoutingDecision {
     pub exchange_id: ExchangeID,
     pub expected_price: f64,
-    pub expected_fee: f64,
-    pub total_cost: f64, // For buys: price + fee, For sells: price - fee
-    pub available_quantity: u32,
+    pub expected_fee: f64, // Negative when the venue pays a maker rebate
+    pub total_cost: f64,   // For buys: price + fee, For sells: price - fee
+    pub available_quantity: Qty,
     pub is_maker: bool,
 }
 
@@ -64,12 +172,40 @@ impl Default for RoutingDecision {
             expected_price: 0.0,
             expected_fee: 0.0,
             total_cost: 0.0,
-            available_quantity: 0,
+            available_quantity: Qty::ZERO,
             is_maker: false,
         }
     }
 }
 
+// Why route_order found nowhere to send an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RoutingError {
+    #[error("no active exchanges")]
+    NoActiveExchanges,
+    #[error("no liquidity on the opposite side at any active exchange")]
+    NoLiquidity,
+    #[error(
+        "quantity {quantity} is below the minimum order size at every exchange with liquidity"
+    )]
+    BelowMinSize { quantity: Qty },
+    #[error("order would cross the book at every active exchange")]
+    WouldCross,
+}
+
+// How route_order chooses between venues with exactly equal total_cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieBreaker {
+    // The venue added to the router first
+    #[default]
+    FirstAdded,
+    LowestLatency,
+    HighestFillRate,
+    // Rotate through the tied venues in the order they were added, to
+    // spread flow across them
+    RoundRobin,
+}
+
 #[derive(Debug, Clone)]
 pub struct ExchangeMetrics {
     pub avg_latency: Duration,
@@ -109,19 +245,123 @@ pub trait Exchange: Send + Sync {
     fn get_metrics(&self) -> ExchangeMetrics {
         ExchangeMetrics::default()
     }
+    // Smallest quantity the venue accepts
+    fn min_order_size(&self) -> Qty {
+        Qty(1)
+    }
+}
+
+// Weights for exchange_quality. Each factor is first mapped onto [0, 1]:
+//   spread:  1 / (1 + spread_bps / spread_reference_bps), 0 for a one-sided book
+//   depth:   d / (d + depth_reference), where d is the quantity resting within
+//            depth_band_bps of the midpoint on both sides
+//   latency: 1 / (1 + latency_ms / latency_reference_ms)
+//   fill rate and uptime are used as-is
+// The score is the weighted mean of the factors, so it also lies in [0, 1].
+#[derive(Debug, Clone)]
+pub struct QualityWeights {
+    pub spread: f64,
+    pub depth: f64,
+    pub fill_rate: f64,
+    pub latency: f64,
+    pub uptime: f64,
+    pub spread_reference_bps: f64,
+    pub depth_band_bps: f64,
+    pub depth_reference: f64,
+    pub latency_reference_ms: f64,
+}
+
+impl Default for QualityWeights {
+    fn default() -> Self {
+        QualityWeights {
+            spread: 0.3,
+            depth: 0.3,
+            fill_rate: 0.2,
+            latency: 0.1,
+            uptime: 0.1,
+            spread_reference_bps: 1.0,
+            depth_band_bps: 10.0,
+            depth_reference: 20.0,
+            latency_reference_ms: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExchangeQuality {
+    pub exchange_id: ExchangeID,
+    pub spread_bps: Option<f64>,
+    pub depth_within_band: Qty,
+    pub fill_rate: f64,
+    pub latency_ms: f64,
+    pub uptime: f64,
+    pub score: f64, // Higher is better
 }
 
 // Container for exchange info
 struct ExchangeInfo {
     exchange: Box<dyn Exchange>,
-    fees: FeeSchedule,
+    fees: Box<dyn FeeModel>,
     is_active: bool,
 }
 
+// Net position and realized P&L at one venue, average-cost basis, in base
+// units. Fees are not included.
+#[derive(Debug, Clone, Default)]
+struct VenuePosition {
+    position: Decimal, // Positive = long
+    avg_price: Decimal,
+    realized_pnl: Decimal,
+    volume: Decimal, // Notional traded, for volume-tiered fees
+}
+
+impl VenuePosition {
+    fn apply_fill(&mut self, price: Decimal, quantity: Decimal, is_buy: bool) {
+        let signed_qty = if is_buy { quantity } else { -quantity };
+
+        if self.position.is_zero() || self.position.is_sign_positive() == is_buy {
+            // Opening or adding: blend into the average price
+            let total = self.position.abs() + quantity;
+            self.avg_price = (self.avg_price * self.position.abs() + price * quantity) / total;
+            self.position += signed_qty;
+            return;
+        }
+
+        // Reducing, and possibly flipping through flat
+        let closed = quantity.min(self.position.abs());
+        let pnl_per_unit = if self.position.is_sign_positive() {
+            price - self.avg_price
+        } else {
+            self.avg_price - price
+        };
+        self.realized_pnl += pnl_per_unit * closed;
+        self.position += signed_qty;
+
+        if self.position.is_zero() {
+            self.avg_price = Decimal::ZERO;
+        } else if closed < quantity {
+            // The remainder opened a position on the other side
+            self.avg_price = price;
+        }
+    }
+}
+
 pub struct SmartOrderRouter {
     exchanges: Vec<ExchangeInfo>,
     consider_latency: bool,
     consider_fees: bool,
+    positions: HashMap<ExchangeID, VenuePosition>,
+    quality_weights: QualityWeights,
+    // Never route at a price worse than the consolidated best bid/offer
+    trade_through_protection: bool,
+    tie_breaker: TieBreaker,
+    // Index of the venue last picked by TieBreaker::RoundRobin; usize::MAX
+    // until the first pick
+    round_robin_last: AtomicUsize,
+    // How strongly fill_rate inflates cost: 0 ignores it, 1 divides cost by it
+    fill_rate_weight: f64,
+    // Venues reporting uptime below this are not routed to
+    min_uptime: f64,
 }
 
 impl SmartOrderRouter {
@@ -130,29 +370,132 @@ impl SmartOrderRouter {
             exchanges: Vec::new(),
             consider_latency,
             consider_fees,
+            positions: HashMap::new(),
+            quality_weights: QualityWeights::default(),
+            trade_through_protection: false,
+            tie_breaker: TieBreaker::default(),
+            round_robin_last: AtomicUsize::new(usize::MAX),
+            fill_rate_weight: 0.0,
+            min_uptime: 0.0,
         }
     }
 
-    pub fn add_exchange(&mut self, exchange: Box<dyn Exchange>, fees: FeeSchedule) {
+    // `fees` is a flat FeeSchedule or any other FeeModel, such as a
+    // TieredFeeSchedule
+    pub fn add_exchange(&mut self, exchange: Box<dyn Exchange>, fees: impl FeeModel + 'static) {
         self.exchanges.push(ExchangeInfo {
             exchange,
-            fees,
+            fees: Box::new(fees),
             is_active: true,
         });
     }
 
+    // The rates a venue charges right now, given the volume booked there
+    fn fees_for(&self, exchange_info: &ExchangeInfo) -> FeeSchedule {
+        exchange_info
+            .fees
+            .schedule(self.traded_volume(exchange_info.exchange.get_id()))
+    }
+
+    // Notional and fee in exact decimal arithmetic. `quantity` is in lots
+    // of `quantity_scale` per base unit, as on the venue's book.
+    fn notional_and_fee(
+        price: f64,
+        quantity: Qty,
+        fee_rate: f64,
+        quantity_scale: u64,
+    ) -> (Decimal, Decimal) {
+        let notional = to_decimal(price) * quantity.to_base(quantity_scale);
+        (notional, notional * to_decimal(fee_rate))
+    }
+
     // Calculate the effective cost for a buy order
-    fn calculate_buy_cost(&self, price: f64, quantity: u32, fee_rate: f64) -> f64 {
-        let notional = price * quantity as f64;
-        let fee = notional * fee_rate;
-        notional + fee // Total cost including fees
+    fn calculate_buy_cost(&self, price: f64, quantity: Qty, fee_rate: f64, scale: u64) -> f64 {
+        let (notional, fee) = Self::notional_and_fee(price, quantity, fee_rate, scale);
+        to_f64(notional + fee) // Total cost including fees
     }
 
     // Calculate the effective proceeds for a sell order
-    fn calculate_sell_proceeds(&self, price: f64, quantity: u32, fee_rate: f64) -> f64 {
-        let notional = price * quantity as f64;
-        let fee = notional * fee_rate;
-        notional - fee // Net proceeds after fees
+    fn calculate_sell_proceeds(&self, price: f64, quantity: Qty, fee_rate: f64, scale: u64) -> f64 {
+        let (notional, fee) = Self::notional_and_fee(price, quantity, fee_rate, scale);
+        to_f64(notional - fee) // Net proceeds after fees
+    }
+
+    // Lots per base unit on a venue's book; the default scale for a venue
+    // the router doesn't know
+    fn quantity_scale(&self, id: ExchangeID) -> u64 {
+        self.exchanges
+            .iter()
+            .find(|exchange_info| exchange_info.exchange.get_id() == id)
+            .map_or(QUANTITY_SCALE, |exchange_info| {
+                exchange_info.exchange.get_order_book().quantity_scale()
+            })
+    }
+
+    // With protection on, route_order only considers venues quoting the
+    // consolidated best price, and route_order_split walks the consolidated
+    // book best price first instead of picking venues one at a time
+    pub fn set_trade_through_protection(&mut self, enabled: bool) {
+        self.trade_through_protection = enabled;
+    }
+
+    pub fn set_tie_breaker(&mut self, tie_breaker: TieBreaker) {
+        self.tie_breaker = tie_breaker;
+    }
+
+    pub fn tie_breaker(&self) -> TieBreaker {
+        self.tie_breaker
+    }
+
+    // Penalize venues that often leave orders unfilled: buy costs are
+    // multiplied and sell proceeds divided by 1 + weight * (1 / fill_rate - 1)
+    pub fn set_fill_rate_weight(&mut self, weight: f64) {
+        self.fill_rate_weight = weight;
+    }
+
+    pub fn set_min_uptime(&mut self, min_uptime: f64) {
+        self.min_uptime = min_uptime;
+    }
+
+    // Whether a venue can be routed to at all right now
+    fn is_routable(&self, exchange_info: &ExchangeInfo) -> bool {
+        exchange_info.is_active
+            && exchange_info.exchange.is_available()
+            && exchange_info.exchange.get_metrics().uptime >= self.min_uptime
+    }
+
+    fn fill_rate_penalty(&self, metrics: &ExchangeMetrics) -> f64 {
+        1.0 + self.fill_rate_weight * (1.0 / metrics.fill_rate - 1.0)
+    }
+
+    // Pick one of the venues tied on total_cost; `ties` is in insertion order
+    fn break_tie(&self, ties: Vec<(usize, RoutingDecision)>) -> Option<RoutingDecision> {
+        let metrics = |index: usize| self.exchanges[index].exchange.get_metrics();
+        let chosen = match self.tie_breaker {
+            TieBreaker::FirstAdded => ties.into_iter().next(),
+            TieBreaker::LowestLatency => ties
+                .into_iter()
+                .min_by_key(|(index, _)| metrics(*index).avg_latency),
+            // min_by keeps the first of equal elements, unlike max_by
+            TieBreaker::HighestFillRate => ties
+                .into_iter()
+                .min_by(|(a, _), (b, _)| metrics(*b).fill_rate.total_cmp(&metrics(*a).fill_rate)),
+            // The first tied venue after the last one picked, wrapping around
+            TieBreaker::RoundRobin => {
+                let last = self.round_robin_last.load(Ordering::Relaxed);
+                let next = ties
+                    .iter()
+                    .position(|(index, _)| *index > last)
+                    .unwrap_or(0);
+                let chosen = ties.into_iter().nth(next);
+                if let Some((index, _)) = &chosen {
+                    self.round_robin_last.store(*index, Ordering::Relaxed);
+                }
+                chosen
+            }
+        };
+
+        chosen.map(|(_, decision)| decision)
     }
 
     // Check if this would be a maker or taker order
@@ -176,62 +519,91 @@ impl SmartOrderRouter {
         &self,
         _order_id: u32,
         price: f64,
-        quantity: u32,
+        quantity: Qty,
         is_buy_side: bool,
-    ) -> RoutingDecision {
-        let mut best_decision = RoutingDecision::default();
+    ) -> Result<RoutingDecision, RoutingError> {
+        // Venues sharing the best total_cost, with their index in `exchanges`
+        let mut ties: Vec<(usize, RoutingDecision)> = Vec::new();
+        // How far candidate venues got, to explain a failure
+        let mut any_active = false;
+        let mut any_liquidity = false;
+        let nbbo = self
+            .trade_through_protection
+            .then(|| self.get_aggregated_market_data());
 
         if is_buy_side {
             // For buy orders, find lowest effective cost (price + fees)
             let mut best_cost = f64::MAX;
 
-            for exchange_info in &self.exchanges {
-                if !exchange_info.is_active || !exchange_info.exchange.is_available() {
+            for (index, exchange_info) in self.exchanges.iter().enumerate() {
+                if !self.is_routable(exchange_info) {
                     continue;
                 }
+                any_active = true;
 
                 let book = exchange_info.exchange.get_order_book();
                 let best_ask = match book.get_best_ask() {
                     Some(ask) => ask,
                     None => continue,
                 };
+                if let Some(nbbo) = &nbbo {
+                    if price_to_tick(best_ask) > price_to_tick(nbbo.best_ask) {
+                        continue;
+                    }
+                }
 
-                // Check available quantity
-                let available_qty = book.get_ask_quantity_at(best_ask);
-                if available_qty == 0 {
+                // Price the fill across depth, not just the touch
+                let available_qty: Qty = book.iter_levels(false).map(|(_, qty)| qty).sum();
+                if available_qty.is_zero() {
                     continue;
                 }
+                any_liquidity = true;
+
+                let fill_qty = quantity.min(available_qty);
+                if fill_qty < exchange_info.exchange.min_order_size() {
+                    continue;
+                }
+                let Some(fill_price) = book.vwap_to_fill(fill_qty, true) else {
+                    continue;
+                };
 
                 // Determine if maker or taker
                 let is_maker = self.would_be_maker_order(book, price, is_buy_side);
+                let fees = self.fees_for(exchange_info);
                 let fee_rate = if is_maker {
-                    exchange_info.fees.maker_fee
+                    fees.maker_fee
                 } else {
-                    exchange_info.fees.taker_fee
+                    fees.taker_fee
                 };
 
                 // Calculate total cost
-                let fill_qty = quantity.min(available_qty);
+                let scale = book.quantity_scale();
                 let mut total_cost = if self.consider_fees {
-                    self.calculate_buy_cost(best_ask, fill_qty, fee_rate)
+                    self.calculate_buy_cost(fill_price, fill_qty, fee_rate, scale)
                 } else {
-                    best_ask * fill_qty as f64
+                    fill_price * fill_qty.to_base_f64(scale)
                 };
 
+                let metrics = exchange_info.exchange.get_metrics();
                 // Consider latency if enabled
                 if self.consider_latency {
-                    let metrics = exchange_info.exchange.get_metrics();
                     // Add a small penalty for high latency exchanges
                     total_cost *= 1.0 + metrics.avg_latency.as_millis() as f64 / 10000.0;
                 }
+                total_cost *= self.fill_rate_penalty(&metrics);
 
-                if total_cost < best_cost {
-                    best_cost = total_cost;
-                    best_decision = RoutingDecision {
+                // Venues can fill different sizes, so rank per unit
+                let unit_cost = total_cost / fill_qty.to_base_f64(scale);
+                if unit_cost <= best_cost {
+                    if unit_cost < best_cost {
+                        best_cost = unit_cost;
+                        ties.clear();
+                    }
+                    let decision = RoutingDecision {
                         exchange_id: exchange_info.exchange.get_id(),
-                        expected_price: best_ask,
+                        expected_price: fill_price,
                         expected_fee: if self.consider_fees {
-                            best_ask * fill_qty as f64 * fee_rate
+                            to_f64(Self::notional_and_fee(fill_price, fill_qty, fee_rate, scale).1)
                         } else {
                             0.0
                         },
@@ -239,59 +611,81 @@ impl SmartOrderRouter {
                         available_quantity: available_qty,
                         is_maker,
                     };
+                    ties.push((index, decision));
                 }
             }
         } else {
             // For sell orders, find highest effective proceeds (price - fees)
             let mut best_proceeds = f64::MIN;
 
-            for exchange_info in &self.exchanges {
-                if !exchange_info.is_active || !exchange_info.exchange.is_available() {
+            for (index, exchange_info) in self.exchanges.iter().enumerate() {
+                if !self.is_routable(exchange_info) {
                     continue;
                 }
+                any_active = true;
 
                 let book = exchange_info.exchange.get_order_book();
                 let best_bid = match book.get_best_bid() {
                     Some(bid) => bid,
                     None => continue,
                 };
+                if let Some(nbbo) = &nbbo {
+                    if price_to_tick(best_bid) < price_to_tick(nbbo.best_bid) {
+                        continue;
+                    }
+                }
 
-                // Check available quantity
-                let available_qty = book.get_bid_quantity_at(best_bid);
-                if available_qty == 0 {
+                // Price the fill across depth, not just the touch
+                let available_qty: Qty = book.iter_levels(true).map(|(_, qty)| qty).sum();
+                if available_qty.is_zero() {
+                    continue;
+                }
+                any_liquidity = true;
+
+                let fill_qty = quantity.min(available_qty);
+                if fill_qty < exchange_info.exchange.min_order_size() {
                     continue;
                 }
+                let Some(fill_price) = book.vwap_to_fill(fill_qty, false) else {
+                    continue;
+                };
 
                 // Determine if maker or taker
                 let is_maker = self.would_be_maker_order(book, price, is_buy_side);
+                let fees = self.fees_for(exchange_info);
                 let fee_rate = if is_maker {
-                    exchange_info.fees.maker_fee
+                    fees.maker_fee
                 } else {
-                    exchange_info.fees.taker_fee
+                    fees.taker_fee
                 };
 
                 // Calculate net proceeds
-                let fill_qty = quantity.min(available_qty);
+                let scale = book.quantity_scale();
                 let mut net_proceeds = if self.consider_fees {
-                    self.calculate_sell_proceeds(best_bid, fill_qty, fee_rate)
+                    self.calculate_sell_proceeds(fill_price, fill_qty, fee_rate, scale)
                 } else {
-                    best_bid * fill_qty as f64
+                    fill_price * fill_qty.to_base_f64(scale)
                 };
 
+                let metrics = exchange_info.exchange.get_metrics();
                 // Consider latency if enabled
                 if self.consider_latency {
-                    let metrics = exchange_info.exchange.get_metrics();
                     // Reduce proceeds slightly for high latency exchanges
                     net_proceeds *= 1.0 - metrics.avg_latency.as_millis() as f64 / 10000.0;
                 }
+                net_proceeds /= self.fill_rate_penalty(&metrics);
 
-                if net_proceeds > best_proceeds {
-                    best_proceeds = net_proceeds;
-                    best_decision = RoutingDecision {
+                let unit_proceeds = net_proceeds / fill_qty.to_base_f64(scale);
+                if unit_proceeds >= best_proceeds {
+                    if unit_proceeds > best_proceeds {
+                        best_proceeds = unit_proceeds;
+                        ties.clear();
+                    }
+                    let decision = RoutingDecision {
                         exchange_id: exchange_info.exchange.get_id(),
-                        expected_price: best_bid,
+                        expected_price: fill_price,
                         expected_fee: if self.consider_fees {
-                            best_bid * fill_qty as f64 * fee_rate
+                            to_f64(Self::notional_and_fee(fill_price, fill_qty, fee_rate, scale).1)
                         } else {
                             0.0
                         },
@@ -299,19 +693,122 @@ impl SmartOrderRouter {
                         available_quantity: available_qty,
                         is_maker,
                     };
+                    ties.push((index, decision));
                 }
             }
         }
 
-        best_decision
+        match self.break_tie(ties) {
+            Some(decision) => {
+                telemetry::record_routing_decision(decision.exchange_id);
+                Ok(decision)
+            }
+            None if !any_active => Err(RoutingError::NoActiveExchanges),
+            None if !any_liquidity => Err(RoutingError::NoLiquidity),
+            None => Err(RoutingError::BelowMinSize { quantity }),
+        }
+    }
+
+    // Route a post-only order that rests at `price` instead of crossing.
+    // Only venues where it would not cross are considered, and each is
+    // priced at the limit price plus its maker fee, so a venue paying a
+    // maker rebate beats an otherwise identical venue that doesn't.
+    pub fn route_maker_order(
+        &self,
+        _order_id: u32,
+        price: f64,
+        quantity: Qty,
+        is_buy_side: bool,
+    ) -> Result<RoutingDecision, RoutingError> {
+        // Venues sharing the best unit cost, with their index in `exchanges`
+        let mut ties: Vec<(usize, RoutingDecision)> = Vec::new();
+        let mut best_cost = f64::MAX;
+        let mut any_active = false;
+        let mut any_resting = false;
+
+        for (index, exchange_info) in self.exchanges.iter().enumerate() {
+            if !self.is_routable(exchange_info) {
+                continue;
+            }
+            any_active = true;
+
+            let book = exchange_info.exchange.get_order_book();
+            if !self.would_be_maker_order(book, price, is_buy_side) {
+                continue;
+            }
+            any_resting = true;
+
+            if quantity < exchange_info.exchange.min_order_size() {
+                continue;
+            }
+
+            let fee_rate = self.fees_for(exchange_info).maker_fee;
+            let scale = book.quantity_scale();
+            let (notional, fee) = Self::notional_and_fee(price, quantity, fee_rate, scale);
+            let fee = if self.consider_fees {
+                fee
+            } else {
+                Decimal::ZERO
+            };
+            let total_cost = if is_buy_side {
+                to_f64(notional + fee)
+            } else {
+                to_f64(notional - fee)
+            };
+
+            // Rank buys by cost and sells by negated proceeds, both
+            // penalized the same way route_order penalizes them
+            let metrics = exchange_info.exchange.get_metrics();
+            let latency_penalty = if self.consider_latency {
+                metrics.avg_latency.as_millis() as f64 / 10000.0
+            } else {
+                0.0
+            };
+            let adjusted_cost = if is_buy_side {
+                total_cost * (1.0 + latency_penalty) * self.fill_rate_penalty(&metrics)
+            } else {
+                -total_cost * (1.0 - latency_penalty) / self.fill_rate_penalty(&metrics)
+            };
+            let unit_cost = adjusted_cost / quantity.to_base_f64(scale);
+
+            if unit_cost <= best_cost {
+                if unit_cost < best_cost {
+                    best_cost = unit_cost;
+                    ties.clear();
+                }
+                ties.push((
+                    index,
+                    RoutingDecision {
+                        exchange_id: exchange_info.exchange.get_id(),
+                        expected_price: price,
+                        expected_fee: to_f64(fee),
+                        total_cost,
+                        available_quantity: quantity,
+                        is_maker: true,
+                    },
+                ));
+            }
+        }
+
+        match self.break_tie(ties) {
+            Some(decision) => {
+                telemetry::record_routing_decision(decision.exchange_id);
+                Ok(decision)
+            }
+            None if !any_active => Err(RoutingError::NoActiveExchanges),
+            None if !any_resting => Err(RoutingError::WouldCross),
+            None => Err(RoutingError::BelowMinSize { quantity }),
+        }
     }
 
     pub fn get_aggregated_market_data(&self) -> AggregatedMarketData {
         let mut data = AggregatedMarketData {
             best_bid: f64::MIN,
             best_ask: f64::MAX,
-            total_bid_quantity: 0,
-            total_ask_quantity: 0,
+            total_bid_quantity: Qty::ZERO,
+            total_ask_quantity: Qty::ZERO,
+            touch_bid_quantity: Qty::ZERO,
+            touch_ask_quantity: Qty::ZERO,
             best_bid_exchange: ExchangeID::Unknown,
             best_ask_exchange: ExchangeID::Unknown,
         };
@@ -329,8 +826,9 @@ impl SmartOrderRouter {
                     data.best_bid = bid;
                     data.best_bid_exchange = exchange_info.exchange.get_id();
                 }
-                data.total_bid_quantity += book.get_bid_quantity_at(bid);
+                data.touch_bid_quantity += book.get_bid_quantity_at(bid);
             }
+            data.total_bid_quantity += book.total_bid_quantity();
 
             // Check best ask
             if let Some(ask) = book.get_best_ask() {
@@ -338,49 +836,463 @@ impl SmartOrderRouter {
                     data.best_ask = ask;
                     data.best_ask_exchange = exchange_info.exchange.get_id();
                 }
-                data.total_ask_quantity += book.get_ask_quantity_at(ask);
+                data.touch_ask_quantity += book.get_ask_quantity_at(ask);
             }
+            data.total_ask_quantity += book.total_ask_quantity();
         }
 
         data
     }
 
+    // Find the most profitable crossed market: buy at one venue's best ask
+    // and sell at another's best bid, both legs paying taker fees
+    pub fn detect_arbitrage(&self) -> Option<ArbitrageOpportunity> {
+        // (id, touch price, touch quantity, taker fee, quantity scale) per
+        // active venue
+        let mut asks = Vec::new();
+        let mut bids = Vec::new();
+        for exchange_info in &self.exchanges {
+            if !exchange_info.is_active || !exchange_info.exchange.is_available() {
+                continue;
+            }
+
+            let book = exchange_info.exchange.get_order_book();
+            let id = exchange_info.exchange.get_id();
+            let taker_fee = if self.consider_fees {
+                self.fees_for(exchange_info).taker_fee
+            } else {
+                0.0
+            };
+
+            let scale = book.quantity_scale();
+            if let Some(ask) = book.get_best_ask() {
+                asks.push((id, ask, book.get_ask_quantity_at(ask), taker_fee, scale));
+            }
+            if let Some(bid) = book.get_best_bid() {
+                bids.push((id, bid, book.get_bid_quantity_at(bid), taker_fee, scale));
+            }
+        }
+
+        let mut best: Option<ArbitrageOpportunity> = None;
+        for &(buy_exchange, ask, ask_qty, buy_fee, buy_scale) in &asks {
+            for &(sell_exchange, bid, bid_qty, sell_fee, sell_scale) in &bids {
+                if buy_exchange == sell_exchange || bid <= ask {
+                    continue;
+                }
+
+                // The venues may count lots differently, so size both legs
+                // in base units and convert back to each venue's lots
+                let (buy_quantity, sell_quantity) =
+                    Self::matched_lots(ask_qty, buy_scale, bid_qty, sell_scale);
+                if buy_quantity.is_zero() || sell_quantity.is_zero() {
+                    continue;
+                }
+
+                let cost = self.calculate_buy_cost(ask, buy_quantity, buy_fee, buy_scale);
+                let proceeds =
+                    self.calculate_sell_proceeds(bid, sell_quantity, sell_fee, sell_scale);
+                let expected_profit = proceeds - cost;
+                if expected_profit <= 0.0 {
+                    continue; // Fees eat the edge
+                }
+
+                if best
+                    .as_ref()
+                    .is_none_or(|b| expected_profit > b.expected_profit)
+                {
+                    best = Some(ArbitrageOpportunity {
+                        buy_exchange,
+                        sell_exchange,
+                        buy_price: ask,
+                        sell_price: bid,
+                        buy_quantity,
+                        sell_quantity,
+                        edge_per_unit: expected_profit / buy_quantity.to_base_f64(buy_scale),
+                        expected_profit,
+                    });
+                }
+            }
+        }
+
+        best
+    }
+
+    // The largest base amount both venues can trade, as lots at each one's
+    // scale. The smaller size is truncated to both lot grids, so the legs
+    // always cover the same base amount.
+    fn matched_lots(buy_lots: Qty, buy_scale: u64, sell_lots: Qty, sell_scale: u64) -> (Qty, Qty) {
+        let mut base = buy_lots
+            .to_base(buy_scale)
+            .min(sell_lots.to_base(sell_scale));
+        for scale in [buy_scale, sell_scale] {
+            base = base.min(Qty::from_base_decimal(base, scale).to_base(scale));
+        }
+        (
+            Qty::from_base_decimal(base, buy_scale),
+            Qty::from_base_decimal(base, sell_scale),
+        )
+    }
+
+    // Split across the resting levels on every active venue that `price`
+    // reaches. The levels are merged into one ladder ranked by per-unit
+    // cost after taker fees and the latency penalty, and the cheapest
+    // marginal liquidity is consumed first. If the combined books can't
+    // fill total_quantity within the limit the split is partial;
+    // route_order_split_execution reports the remainder.
     pub fn route_order_split(
         &self,
-        order_id: u32,
+        _order_id: u32,
         price: f64,
-        mut total_quantity: u32,
+        mut total_quantity: Qty,
         is_buy_side: bool,
     ) -> Vec<SplitOrder> {
+        let ladder = self.split_ladder(price, is_buy_side);
+
         let mut splits = Vec::new();
+        for (exchange_id, level_price, quantity, fee_rate, quantity_scale) in ladder {
+            if total_quantity.is_zero() {
+                break;
+            }
 
-        // Keep routing portions until all quantity is allocated
-        while total_quantity > 0 {
-            let decision = self.route_order(order_id, price, total_quantity, is_buy_side);
+            let fill_quantity = total_quantity.min(quantity);
+            splits.push(SplitOrder {
+                exchange_id,
+                quantity: fill_quantity,
+                expected_price: level_price,
+                expected_fee: if self.consider_fees {
+                    to_f64(
+                        Self::notional_and_fee(
+                            level_price,
+                            fill_quantity,
+                            fee_rate,
+                            quantity_scale,
+                        )
+                        .1,
+                    )
+                } else {
+                    0.0
+                },
+                quantity_scale,
+            });
+            telemetry::record_routing_decision(exchange_id);
+            total_quantity -= fill_quantity;
+        }
+
+        splits
+    }
 
-            if decision.exchange_id == ExchangeID::Unknown {
-                break; // No more liquidity available
+    // Every opposite-side level within `price` on every active venue as
+    // (id, price, quantity, taker fee rate, quantity scale), cheapest first.
+    // Each entry takes liquidity, so it always pays the taker rate. With trade-through protection
+    // the ladder is ranked by price so no split fills at a price while a
+    // better one is still available elsewhere, and cost only breaks ties.
+    fn split_ladder(&self, price: f64, is_buy_side: bool) -> Vec<(ExchangeID, f64, Qty, f64, u64)> {
+        // Entries carry their per-unit cost so it's computed once
+        let mut levels = Vec::new();
+        for exchange_info in &self.exchanges {
+            if !self.is_routable(exchange_info) {
+                continue;
             }
 
-            let fill_quantity = total_quantity.min(decision.available_quantity);
+            let book = exchange_info.exchange.get_order_book();
+            let fee_rate = self.fees_for(exchange_info).taker_fee;
+            let metrics = exchange_info.exchange.get_metrics();
+            let latency_penalty = if self.consider_latency {
+                metrics.avg_latency.as_millis() as f64 / 10000.0
+            } else {
+                0.0
+            };
+            let fill_rate_penalty = self.fill_rate_penalty(&metrics);
+            let cost_fee_rate = if self.consider_fees { fee_rate } else { 0.0 };
 
-            splits.push(SplitOrder {
-                exchange_id: decision.exchange_id,
-                quantity: fill_quantity,
-                expected_price: decision.expected_price,
-                expected_fee: decision.expected_fee * fill_quantity as f64
-                    / decision.available_quantity as f64,
+            let limit_tick = price_to_tick(price);
+            let within_limit = |level_price: f64| {
+                if is_buy_side {
+                    price_to_tick(level_price) <= limit_tick
+                } else {
+                    price_to_tick(level_price) >= limit_tick
+                }
+            };
+            // Levels come best price first, so the first one past the limit
+            // ends this venue
+            for (level_price, quantity) in book
+                .iter_levels(!is_buy_side)
+                .take_while(|&(level_price, _)| within_limit(level_price))
+            {
+                // Same adjustments route_order applies, per unit: buys pay
+                // fee and latency on top, sells give them up from proceeds
+                let unit_cost = if is_buy_side {
+                    level_price
+                        * (1.0 + cost_fee_rate)
+                        * (1.0 + latency_penalty)
+                        * fill_rate_penalty
+                } else {
+                    level_price * (1.0 - cost_fee_rate) * (1.0 - latency_penalty)
+                        / fill_rate_penalty
+                };
+                levels.push((
+                    exchange_info.exchange.get_id(),
+                    level_price,
+                    quantity,
+                    fee_rate,
+                    book.quantity_scale(),
+                    unit_cost,
+                ));
+            }
+        }
+
+        // Lower cost is better for buys, higher proceeds for sells. The sort
+        // is stable, so exact ties keep the order venues were added.
+        let by_cost = |a: f64, b: f64| {
+            if is_buy_side {
+                a.total_cmp(&b)
+            } else {
+                b.total_cmp(&a)
+            }
+        };
+        if self.trade_through_protection {
+            levels.sort_by(|a, b| {
+                let by_price = if is_buy_side {
+                    price_to_tick(a.1).cmp(&price_to_tick(b.1))
+                } else {
+                    price_to_tick(b.1).cmp(&price_to_tick(a.1))
+                };
+                by_price.then(by_cost(a.5, b.5))
             });
+        } else {
+            levels.sort_by(|a, b| by_cost(a.5, b.5));
+        }
 
-            total_quantity -= fill_quantity;
+        levels
+            .into_iter()
+            .map(|(exchange_id, level_price, quantity, fee_rate, scale, _)| {
+                (exchange_id, level_price, quantity, fee_rate, scale)
+            })
+            .collect()
+    }
 
-            // Prevent infinite loop
-            if splits.len() >= self.exchanges.len() {
-                break;
+    // route_order_split plus a consolidated report of the allocation
+    pub fn route_order_split_execution(
+        &self,
+        order_id: u32,
+        price: f64,
+        total_quantity: Qty,
+        is_buy_side: bool,
+    ) -> SplitExecution {
+        let splits = self.route_order_split(order_id, price, total_quantity, is_buy_side);
+        SplitExecution::from_splits(splits, total_quantity, is_buy_side)
+    }
+
+    // Book an executed fill against the venue it happened on. The router
+    // never executes anything itself, so callers report fills here. The
+    // notional also counts toward the venue's volume fee tier.
+    pub fn record_fill(
+        &mut self,
+        exchange_id: ExchangeID,
+        price: f64,
+        quantity: Qty,
+        is_buy: bool,
+    ) {
+        let quantity = quantity.to_base(self.quantity_scale(exchange_id));
+        let price = to_decimal(price);
+        let venue = self.positions.entry(exchange_id).or_default();
+        venue.apply_fill(price, quantity, is_buy);
+        venue.volume += price * quantity;
+    }
+
+    // Notional booked at a venue through record_fill
+    pub fn traded_volume(&self, id: ExchangeID) -> f64 {
+        self.positions
+            .get(&id)
+            .map_or(0.0, |venue| to_f64(venue.volume))
+    }
+
+    // The rates a venue currently charges; None for an unknown venue
+    pub fn current_fees(&self, id: ExchangeID) -> Option<FeeSchedule> {
+        self.exchanges
+            .iter()
+            .find(|exchange_info| exchange_info.exchange.get_id() == id)
+            .map(|exchange_info| self.fees_for(exchange_info))
+    }
+
+    // Net position held at a venue in base units, converted from lots with
+    // the venue book's quantity scale; zero if nothing was recorded there
+    pub fn position(&self, id: ExchangeID) -> f64 {
+        self.positions
+            .get(&id)
+            .map_or(0.0, |venue| to_f64(venue.position))
+    }
+
+    // Realized P&L at a venue, excluding fees
+    pub fn pnl(&self, id: ExchangeID) -> f64 {
+        self.positions
+            .get(&id)
+            .map_or(0.0, |venue| to_f64(venue.realized_pnl))
+    }
+
+    pub fn total_position(&self) -> f64 {
+        to_f64(self.positions.values().map(|venue| venue.position).sum())
+    }
+
+    // Advisory plan for moving inventory towards `target_per_exchange`.
+    // Venues missing from the map are targeted at zero. The biggest surplus is
+    // paired with the biggest deficit until one side runs out, so when the
+    // targets do not sum to the total position the leftover is left in place.
+    pub fn plan_rebalance(
+        &self,
+        target_per_exchange: &HashMap<ExchangeID, f64>,
+    ) -> Vec<TransferSuggestion> {
+        let mut venues: Vec<ExchangeID> = self
+            .positions
+            .keys()
+            .chain(target_per_exchange.keys())
+            .copied()
+            .collect();
+        venues.sort();
+        venues.dedup();
+
+        // (venue, remaining amount) with the largest first
+        let mut surpluses = Vec::new();
+        let mut deficits = Vec::new();
+        for id in venues {
+            let target = target_per_exchange.get(&id).copied().unwrap_or(0.0);
+            let excess = self.position(id) - target;
+            if excess > 0.0 {
+                surpluses.push((id, excess));
+            } else if excess < 0.0 {
+                deficits.push((id, -excess));
             }
         }
+        surpluses.sort_by(|a, b| b.1.total_cmp(&a.1));
+        deficits.sort_by(|a, b| b.1.total_cmp(&a.1));
 
-        splits
+        let mut suggestions = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < surpluses.len() && j < deficits.len() {
+            let quantity = surpluses[i].1.min(deficits[j].1);
+            let (from_exchange, to_exchange) = (surpluses[i].0, deficits[j].0);
+            suggestions.push(TransferSuggestion {
+                from_exchange,
+                to_exchange,
+                quantity,
+                offset_trade_cost: self.offset_trade_cost(from_exchange, to_exchange, quantity),
+            });
+
+            surpluses[i].1 -= quantity;
+            deficits[j].1 -= quantity;
+            if surpluses[i].1 <= 0.0 {
+                i += 1;
+            }
+            if deficits[j].1 <= 0.0 {
+                j += 1;
+            }
+        }
+
+        suggestions
+    }
+
+    // Cost of rebalancing with trades instead of a transfer: sell at the
+    // surplus venue's bid and buy at the deficit venue's ask, taker fees on
+    // both legs. None when either venue is unknown or has an empty side.
+    fn offset_trade_cost(&self, from: ExchangeID, to: ExchangeID, quantity: f64) -> Option<f64> {
+        let find = |id| {
+            self.exchanges
+                .iter()
+                .find(|info| info.exchange.get_id() == id)
+        };
+        let (seller, buyer) = (find(from)?, find(to)?);
+        let sell_price = seller.exchange.get_order_book().get_best_bid()?;
+        let buy_price = buyer.exchange.get_order_book().get_best_ask()?;
+
+        let quantity = to_decimal(quantity);
+        let proceeds = to_decimal(sell_price) * quantity;
+        let cost = to_decimal(buy_price) * quantity;
+        let fees = proceeds * to_decimal(self.fees_for(seller).taker_fee)
+            + cost * to_decimal(self.fees_for(buyer).taker_fee);
+        Some(to_f64(cost - proceeds + fees))
+    }
+
+    pub fn set_quality_weights(&mut self, weights: QualityWeights) {
+        self.quality_weights = weights;
+    }
+
+    // Composite venue score from the current book and the venue's metrics;
+    // see QualityWeights for the formula. None for an unknown venue.
+    pub fn exchange_quality(&self, id: ExchangeID) -> Option<ExchangeQuality> {
+        let exchange_info = self
+            .exchanges
+            .iter()
+            .find(|info| info.exchange.get_id() == id)?;
+        let weights = &self.quality_weights;
+        let book = exchange_info.exchange.get_order_book();
+        let metrics = exchange_info.exchange.get_metrics();
+
+        let (spread_bps, depth_within_band) = match (book.midpoint(), book.spread_bps()) {
+            (Some(mid), Some(spread_bps)) => {
+                let band = mid * weights.depth_band_bps / 10000.0;
+                let depth = book
+                    .iter_bids()
+                    .take_while(|&(price, _)| price >= mid - band)
+                    .chain(
+                        book.iter_asks()
+                            .take_while(|&(price, _)| price <= mid + band),
+                    )
+                    .map(|(_, quantity)| quantity)
+                    .sum();
+                (Some(spread_bps), depth)
+            }
+            _ => (None, Qty::ZERO),
+        };
+        let latency_ms = metrics.avg_latency.as_secs_f64() * 1000.0;
+
+        let factors = [
+            (
+                weights.spread,
+                spread_bps.map_or(0.0, |bps| 1.0 / (1.0 + bps / weights.spread_reference_bps)),
+            ),
+            (
+                weights.depth,
+                depth_within_band.lots() as f64
+                    / (depth_within_band.lots() as f64 + weights.depth_reference),
+            ),
+            (weights.fill_rate, metrics.fill_rate),
+            (
+                weights.latency,
+                1.0 / (1.0 + latency_ms / weights.latency_reference_ms),
+            ),
+            (weights.uptime, metrics.uptime),
+        ];
+        let total_weight: f64 = factors.iter().map(|(weight, _)| weight).sum();
+        let score = if total_weight > 0.0 {
+            factors
+                .iter()
+                .map(|(weight, factor)| weight * factor)
+                .sum::<f64>()
+                / total_weight
+        } else {
+            0.0
+        };
+
+        Some(ExchangeQuality {
+            exchange_id: id,
+            spread_bps,
+            depth_within_band,
+            fill_rate: metrics.fill_rate,
+            latency_ms,
+            uptime: metrics.uptime,
+            score,
+        })
+    }
+
+    // Every venue's quality, best first
+    pub fn rank_exchanges(&self) -> Vec<ExchangeQuality> {
+        let mut ranking: Vec<ExchangeQuality> = self
+            .exchanges
+            .iter()
+            .filter_map(|info| self.exchange_quality(info.exchange.get_id()))
+            .collect();
+        ranking.sort_by(|a, b| b.score.total_cmp(&a.score));
+        ranking
     }
 
     pub fn set_exchange_active(&mut self, id: ExchangeID, active: bool) {
@@ -392,6 +1304,38 @@ impl SmartOrderRouter {
         }
     }
 
+    // Unregister a venue. Returns false if no venue has this id.
+    pub fn remove_exchange(&mut self, id: ExchangeID) -> bool {
+        match self
+            .exchanges
+            .iter()
+            .position(|exchange_info| exchange_info.exchange.get_id() == id)
+        {
+            Some(index) => {
+                self.exchanges.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_exchange(&self, id: ExchangeID) -> Option<&dyn Exchange> {
+        self.exchanges
+            .iter()
+            .find(|exchange_info| exchange_info.exchange.get_id() == id)
+            .map(|exchange_info| exchange_info.exchange.as_ref())
+    }
+
+    // Lets callers update a venue's book after it was handed to the router
+    pub fn get_exchange_mut(&mut self, id: ExchangeID) -> Option<&mut dyn Exchange> {
+        for exchange_info in &mut self.exchanges {
+            if exchange_info.exchange.get_id() == id {
+                return Some(exchange_info.exchange.as_mut());
+            }
+        }
+        None
+    }
+
     pub fn print_routing_stats(&self) {
         println!("\n=== Smart Order Router Statistics ===");
 
@@ -425,10 +1369,21 @@ impl SmartOrderRouter {
                 println!("None");
             }
 
+            print!("  Depth: Bids");
+            for (price, quantity) in book.iter_bids().take(STATS_DEPTH_LEVELS) {
+                print!(" {quantity}@{price:.2}");
+            }
+            print!(" | Asks");
+            for (price, quantity) in book.iter_asks().take(STATS_DEPTH_LEVELS) {
+                print!(" {quantity}@{price:.2}");
+            }
+            println!();
+
+            let fees = self.fees_for(exchange_info);
             println!(
                 "  Fees: Maker {:.2}% / Taker {:.2}%",
-                exchange_info.fees.maker_fee * 100.0,
-                exchange_info.fees.taker_fee * 100.0
+                fees.maker_fee * 100.0,
+                fees.taker_fee * 100.0
             );
 
             println!(
@@ -456,16 +1411,375 @@ impl SmartOrderRouter {
 pub struct AggregatedMarketData {
     pub best_bid: f64,
     pub best_ask: f64,
-    pub total_bid_quantity: u32,
-    pub total_ask_quantity: u32,
+    // Every resting level, summed across venues
+    pub total_bid_quantity: Qty,
+    pub total_ask_quantity: Qty,
+    // Each venue's best-level size, summed across venues
+    pub touch_bid_quantity: Qty,
+    pub touch_ask_quantity: Qty,
     pub best_bid_exchange: ExchangeID,
     pub best_ask_exchange: ExchangeID,
 }
 
+// A crossed market across two venues, net of taker fees
+#[derive(Debug, Clone)]
+pub struct ArbitrageOpportunity {
+    pub buy_exchange: ExchangeID,  // Venue whose ask we lift
+    pub sell_exchange: ExchangeID, // Venue whose bid we hit
+    pub buy_price: f64,
+    pub sell_price: f64,
+    // Capped by the smaller touch size; the same base amount in each
+    // venue's lots
+    pub buy_quantity: Qty,
+    pub sell_quantity: Qty,
+    pub edge_per_unit: f64,   // Net of fees on both legs
+    pub expected_profit: f64, // edge_per_unit * base quantity
+}
+
+// One leg of a rebalancing plan
+#[derive(Debug, Clone)]
+pub struct TransferSuggestion {
+    pub from_exchange: ExchangeID, // Venue above its target
+    pub to_exchange: ExchangeID,   // Venue below its target
+    pub quantity: f64,             // In base units
+    // What doing this with offsetting trades would cost right now, to weigh
+    // against the transfer's own withdrawal fee and delay
+    pub offset_trade_cost: Option<f64>,
+}
+
 #[derive(Debug)]
 pub struct SplitOrder {
     pub exchange_id: ExchangeID,
-    pub quantity: u32,
+    pub quantity: Qty,
     pub expected_price: f64,
     pub expected_fee: f64,
+    pub quantity_scale: u64, // Lots per base unit on the venue's book
+}
+
+// Consolidated view of a split allocation
+#[derive(Debug)]
+pub struct SplitExecution {
+    pub splits: Vec<SplitOrder>,
+    pub filled_quantity: Qty,
+    pub unfilled_quantity: Qty, // Left over when aggregate liquidity ran out
+    pub average_price: f64,     // Quantity-weighted across splits, 0.0 if nothing allocated
+    pub worst_price: Option<f64>, // Highest price for buys, lowest for sells
+    pub total_fee: f64,
+    pub total_cost: f64, // For buys: notional + fees, For sells: notional - fees
+}
+
+impl SplitExecution {
+    pub fn from_splits(
+        splits: Vec<SplitOrder>,
+        requested_quantity: Qty,
+        is_buy_side: bool,
+    ) -> Self {
+        let filled_quantity: Qty = splits.iter().map(|split| split.quantity).sum();
+        let base_quantity = |split: &SplitOrder| split.quantity.to_base(split.quantity_scale);
+        let filled_base: Decimal = splits.iter().map(base_quantity).sum();
+        let notional: Decimal = splits
+            .iter()
+            .map(|split| to_decimal(split.expected_price) * base_quantity(split))
+            .sum();
+        let total_fee: Decimal = splits
+            .iter()
+            .map(|split| to_decimal(split.expected_fee))
+            .sum();
+
+        let average_price = if !filled_quantity.is_zero() {
+            to_f64(notional / filled_base)
+        } else {
+            0.0
+        };
+        let prices = splits.iter().map(|split| split.expected_price);
+        let worst_price = if is_buy_side {
+            prices.reduce(f64::max)
+        } else {
+            prices.reduce(f64::min)
+        };
+        let total_cost = if is_buy_side {
+            notional + total_fee
+        } else {
+            notional - total_fee
+        };
+
+        SplitExecution {
+            splits,
+            filled_quantity,
+            unfilled_quantity: requested_quantity.saturating_sub(filled_quantity),
+            average_price,
+            worst_price,
+            total_fee: to_f64(total_fee),
+            total_cost: to_f64(total_cost),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestVenue {
+        id: ExchangeID,
+        book: OrderBook,
+        metrics: ExchangeMetrics,
+    }
+
+    impl Exchange for TestVenue {
+        fn get_order_book(&self) -> &OrderBook {
+            &self.book
+        }
+
+        fn get_order_book_mut(&mut self) -> &mut OrderBook {
+            &mut self.book
+        }
+
+        fn get_id(&self) -> ExchangeID {
+            self.id
+        }
+
+        fn get_name(&self) -> &str {
+            "test"
+        }
+
+        fn get_metrics(&self) -> ExchangeMetrics {
+            self.metrics.clone()
+        }
+    }
+
+    // A venue whose book holds `asks` and `bids` as (price, lots)
+    fn venue(id: ExchangeID, asks: &[(f64, u64)], bids: &[(f64, u64)]) -> Box<TestVenue> {
+        let mut book = OrderBook::new();
+        for (order_id, &(price, lots)) in (1..).zip(asks) {
+            book.add_order(order_id, price, Qty(lots), false);
+        }
+        for (order_id, &(price, lots)) in (100..).zip(bids) {
+            book.add_order(order_id, price, Qty(lots), true);
+        }
+        Box::new(TestVenue {
+            id,
+            book,
+            metrics: ExchangeMetrics::default(),
+        })
+    }
+
+    #[test]
+    fn custom_venues_route_like_built_in_ones() {
+        let okx = ExchangeID::custom("OKX").unwrap();
+        let bybit = ExchangeID::custom("Bybit").unwrap();
+        assert_ne!(okx, bybit);
+        assert_eq!(ExchangeID::custom("OKX").unwrap(), okx);
+        assert_eq!(okx.to_string(), "OKX");
+
+        let mut router = SmartOrderRouter::new(false, false);
+        router.add_exchange(venue(okx, &[(100.2, 1_000)], &[]), FeeSchedule::default());
+        router.add_exchange(venue(bybit, &[(100.1, 1_000)], &[]), FeeSchedule::default());
+
+        let route = |router: &SmartOrderRouter| {
+            router
+                .route_order(1, 101.0, Qty(500), true)
+                .unwrap()
+                .exchange_id
+        };
+        assert_eq!(route(&router), bybit);
+        router.set_exchange_active(bybit, false);
+        assert_eq!(route(&router), okx);
+    }
+
+    #[test]
+    fn custom_rejects_built_in_names() {
+        for name in ["Binance", "coinbase", "KRAKEN", "Unknown"] {
+            assert!(matches!(
+                ExchangeID::custom(name),
+                Err(Error::ReservedExchangeName(rejected)) if rejected == name
+            ));
+        }
+        // Built-ins sort ahead of custom venues, which sort by registration
+        let first = ExchangeID::custom("Bitstamp").unwrap();
+        let second = ExchangeID::custom("Gemini").unwrap();
+        let mut ids = vec![ExchangeID::Unknown, second, first, ExchangeID::Binance];
+        ids.sort();
+        assert_eq!(
+            ids,
+            [ExchangeID::Binance, first, second, ExchangeID::Unknown]
+        );
+    }
+
+    #[test]
+    fn tiered_schedule_picks_the_highest_tier_reached() {
+        let tiers = TieredFeeSchedule::new(FeeSchedule::new(0.0010, 0.0020))
+            .with_tier(10_000.0, 0.0, 0.0005)
+            .with_tier(1_000.0, 0.0005, 0.0010);
+
+        assert_eq!(tiers.schedule(0.0).taker_fee, 0.0020);
+        assert_eq!(tiers.schedule(999.99).taker_fee, 0.0020);
+        assert_eq!(tiers.schedule(1_000.0).taker_fee, 0.0010);
+        assert_eq!(tiers.schedule(50_000.0).taker_fee, 0.0005);
+    }
+
+    #[test]
+    fn fee_rate_drops_once_booked_notional_crosses_a_tier() {
+        let mut router = SmartOrderRouter::new(false, true);
+        router.add_exchange(
+            venue(ExchangeID::Binance, &[(100.0, 10_000)], &[]),
+            TieredFeeSchedule::new(FeeSchedule::new(0.0010, 0.0020))
+                .with_tier(1_000.0, 0.0005, 0.0010),
+        );
+        let fee = |router: &SmartOrderRouter| {
+            router
+                .route_order(1, 101.0, Qty(500), true)
+                .unwrap()
+                .expected_fee
+        };
+
+        // 5 units at $100 and 0.20% taker
+        assert!((fee(&router) - 1.0).abs() < 1e-9);
+
+        // 999 lots at the default 100 lots per unit is $999 of notional,
+        // still short of the tier
+        router.record_fill(ExchangeID::Binance, 100.0, Qty(999), true);
+        assert!((router.traded_volume(ExchangeID::Binance) - 999.0).abs() < 1e-9);
+        assert_eq!(
+            router.current_fees(ExchangeID::Binance).unwrap().taker_fee,
+            0.0020
+        );
+
+        router.record_fill(ExchangeID::Binance, 100.0, Qty(1), false);
+        assert_eq!(
+            router.current_fees(ExchangeID::Binance).unwrap().taker_fee,
+            0.0010
+        );
+        assert!((fee(&router) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn split_beats_whole_order_routing_and_respects_the_limit() {
+        let mut router = SmartOrderRouter::new(false, true);
+        // Binance has the best touch but a thin one; Coinbase is deeper
+        router.add_exchange(
+            venue(ExchangeID::Binance, &[(100.0, 100), (103.0, 900)], &[]),
+            FeeSchedule::new(0.0010, 0.0020),
+        );
+        router.add_exchange(
+            venue(ExchangeID::Coinbase, &[(101.0, 1_000)], &[]),
+            FeeSchedule::new(0.0010, 0.0020),
+        );
+
+        // Routing the whole order picks Coinbase: 10 units at $101
+        let whole = router.route_order(1, 103.0, Qty(1_000), true).unwrap();
+        assert_eq!(whole.exchange_id, ExchangeID::Coinbase);
+        assert!((whole.total_cost - 1010.0 * 1.002).abs() < 1e-9);
+
+        // The ladder takes Binance's touch first: 1 unit at $100, 9 at $101,
+        // all at the taker rate
+        let split = router.route_order_split_execution(1, 103.0, Qty(1_000), true);
+        assert!(split.unfilled_quantity.is_zero());
+        assert!((split.total_cost - 1009.0 * 1.002).abs() < 1e-9);
+        assert!(split.total_cost < whole.total_cost);
+
+        // A $100.50 limit stops at Binance's touch and leaves the rest
+        let limited = router.route_order_split_execution(1, 100.5, Qty(1_000), true);
+        assert_eq!(limited.filled_quantity, Qty(100));
+        assert_eq!(limited.unfilled_quantity, Qty(900));
+        assert_eq!(limited.worst_price, Some(100.0));
+    }
+
+    #[test]
+    fn arbitrage_matches_base_quantities_across_lot_scales() {
+        // Binance counts satoshis, Kraken hundredths
+        let scaled = |id, scale, ask: Option<(f64, u64)>, bid: Option<(f64, u64)>| {
+            let mut book = OrderBook::new().with_quantity_scale(scale);
+            if let Some((price, lots)) = ask {
+                book.add_order(1, price, Qty(lots), false);
+            }
+            if let Some((price, lots)) = bid {
+                book.add_order(2, price, Qty(lots), true);
+            }
+            Box::new(TestVenue {
+                id,
+                book,
+                metrics: ExchangeMetrics::default(),
+            })
+        };
+
+        // 0.5 BTC offered at $100, 2 BTC bid at $101
+        let mut router = SmartOrderRouter::new(false, false);
+        router.add_exchange(
+            scaled(
+                ExchangeID::Binance,
+                100_000_000,
+                Some((100.0, 50_000_000)),
+                None,
+            ),
+            FeeSchedule::default(),
+        );
+        router.add_exchange(
+            scaled(ExchangeID::Kraken, 100, None, Some((101.0, 200))),
+            FeeSchedule::default(),
+        );
+        let arb = router.detect_arbitrage().unwrap();
+        assert_eq!(arb.buy_exchange, ExchangeID::Binance);
+        assert_eq!(arb.buy_quantity, Qty(50_000_000));
+        assert_eq!(arb.sell_quantity, Qty(50));
+        assert!((arb.expected_profit - 0.5).abs() < 1e-9);
+        assert!((arb.edge_per_unit - 1.0).abs() < 1e-9);
+
+        // 0.123456 BTC only trades as 0.12 on Kraken's grid
+        let mut router = SmartOrderRouter::new(false, false);
+        router.add_exchange(
+            scaled(
+                ExchangeID::Binance,
+                100_000_000,
+                Some((100.0, 12_345_600)),
+                None,
+            ),
+            FeeSchedule::default(),
+        );
+        router.add_exchange(
+            scaled(ExchangeID::Kraken, 100, None, Some((101.0, 200))),
+            FeeSchedule::default(),
+        );
+        let arb = router.detect_arbitrage().unwrap();
+        assert_eq!(arb.buy_quantity, Qty(12_000_000));
+        assert_eq!(arb.sell_quantity, Qty(12));
+        assert!((arb.expected_profit - 0.12).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fills_are_tracked_per_venue_in_base_units() {
+        let mut router = SmartOrderRouter::new(false, false);
+        router.add_exchange(venue(ExchangeID::Binance, &[], &[]), FeeSchedule::default());
+        // Kraken's book counts 1000 lots to the unit
+        router.add_exchange(
+            Box::new(TestVenue {
+                id: ExchangeID::Kraken,
+                book: OrderBook::new().with_quantity_scale(1000),
+                metrics: ExchangeMetrics::default(),
+            }),
+            FeeSchedule::default(),
+        );
+
+        // Binance: long 2 units at 100 and 101, then sell 1 at 103
+        router.record_fill(ExchangeID::Binance, 100.0, Qty(100), true);
+        router.record_fill(ExchangeID::Binance, 101.0, Qty(100), true);
+        router.record_fill(ExchangeID::Binance, 103.0, Qty(100), false);
+        // Kraken: short 3 units at 102, cover 1 at 100
+        router.record_fill(ExchangeID::Kraken, 102.0, Qty(3000), false);
+        router.record_fill(ExchangeID::Kraken, 100.0, Qty(1000), true);
+
+        assert!((router.position(ExchangeID::Binance) - 1.0).abs() < 1e-9);
+        assert!((router.pnl(ExchangeID::Binance) - 2.5).abs() < 1e-9);
+        assert!((router.position(ExchangeID::Kraken) + 2.0).abs() < 1e-9);
+        assert!((router.pnl(ExchangeID::Kraken) - 2.0).abs() < 1e-9);
+        assert!((router.total_position() + 1.0).abs() < 1e-9);
+        assert_eq!(router.position(ExchangeID::Coinbase), 0.0);
+
+        // Flattening both venues moves the whole unit long from Binance
+        // toward Kraken's short; the rest of the short stays put
+        let plan = router.plan_rebalance(&HashMap::new());
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].from_exchange, ExchangeID::Binance);
+        assert_eq!(plan[0].to_exchange, ExchangeID::Kraken);
+        assert!((plan[0].quantity - 1.0).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,109 @@
+// Operational metrics for the live components. Recording calls are always
+// available so call sites need no feature gates; without the `metrics`
+// feature they compile to nothing and no exporter is linked.
+use crate::smart_order_router::ExchangeID;
+
+pub const TRADES_GENERATED: &str = "trades_generated_total";
+pub const BOOK_BEST_BID: &str = "book_best_bid";
+pub const BOOK_BEST_ASK: &str = "book_best_ask";
+pub const MM_BASE_INVENTORY: &str = "mm_base_inventory";
+pub const MM_QUOTE_INVENTORY: &str = "mm_quote_inventory";
+pub const MM_PNL: &str = "mm_pnl";
+pub const SOR_ROUTING_DECISIONS: &str = "sor_routing_decisions_total";
+
+// Start the Prometheus exporter serving `/metrics` on `addr`. Must be called
+// once, before any metrics are recorded.
+#[cfg(feature = "metrics")]
+pub fn install(addr: std::net::SocketAddr) -> Result<(), metrics_exporter_prometheus::BuildError> {
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn record_trades(count: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(TRADES_GENERATED).increment(count as u64);
+}
+
+// Unset sides are left at their last value
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn set_top_of_book(best_bid: Option<f64>, best_ask: Option<f64>) {
+    #[cfg(feature = "metrics")]
+    {
+        if let Some(bid) = best_bid {
+            metrics::gauge!(BOOK_BEST_BID).set(bid);
+        }
+        if let Some(ask) = best_ask {
+            metrics::gauge!(BOOK_BEST_ASK).set(ask);
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn set_mm_position(base_inventory: f64, quote_inventory: f64, pnl: f64) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::gauge!(MM_BASE_INVENTORY).set(base_inventory);
+        metrics::gauge!(MM_QUOTE_INVENTORY).set(quote_inventory);
+        metrics::gauge!(MM_PNL).set(pnl);
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn record_routing_decision(exchange: ExchangeID) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(SOR_ROUTING_DECISIONS, "exchange" => exchange.to_string()).increment(1);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    #[test]
+    fn registry_reports_every_metric_after_activity() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            record_trades(3);
+            record_trades(2);
+            set_top_of_book(Some(45000.1), Some(45000.2));
+            set_mm_position(1.5, 2500.0, -12.5);
+            record_routing_decision(ExchangeID::Kraken);
+            record_routing_decision(ExchangeID::Kraken);
+            record_routing_decision(ExchangeID::Binance);
+        });
+
+        let rendered = handle.render();
+        for line in [
+            "trades_generated_total 5",
+            "book_best_bid 45000.1",
+            "book_best_ask 45000.2",
+            "mm_base_inventory 1.5",
+            "mm_quote_inventory 2500",
+            "mm_pnl -12.5",
+            "sor_routing_decisions_total{exchange=\"Kraken\"} 2",
+            "sor_routing_decisions_total{exchange=\"Binance\"} 1",
+        ] {
+            assert!(
+                rendered.lines().any(|rendered_line| rendered_line == line),
+                "missing `{line}` in:\n{rendered}"
+            );
+        }
+    }
+
+    #[test]
+    fn unset_sides_keep_their_last_value() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            set_top_of_book(Some(100.0), Some(101.0));
+            set_top_of_book(Some(100.5), None);
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.lines().any(|line| line == "book_best_bid 100.5"));
+        assert!(rendered.lines().any(|line| line == "book_best_ask 101"));
+    }
+}